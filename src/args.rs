@@ -1,32 +1,1013 @@
-use clap::{Parser, ArgGroup};
+use std::env;
+use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use std::time::Duration;
+
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use regex::Regex;
+use crate::config::Config;
+#[cfg(feature = "syslog")]
+use crate::sink::Facility;
+use system_monitor::color::{Breakpoints, CRIT_PERCENT, ColorChoice, WARN_PERCENT};
+use system_monitor::render::TableStyle;
+use system_monitor::template::Template;
+use system_monitor::threshold::Limits;
+use system_monitor::{
+    CollectOptions, Field, Fields, FormatOptions, HEADER_EVERY, MemBasis, NetUnit, OutputFormat, ProcessTarget,
+    TimestampFormat, Units, parse_duration,
+};
+
+pub(crate) const ENV_HELP: &str = "\
+Environment:
+  Options left off the command line are read from SYSMON_* variables, then from
+  the config file: SYSMON_INTERVAL, SYSMON_COUNT, SYSMON_DURATION,
+  SYSMON_FORMAT, SYSMON_OUTPUT, SYSMON_TABLE, SYSMON_FIELDS, SYSMON_UNITS,
+  SYSMON_NET_UNIT, SYSMON_PRECISION, SYSMON_COLOR, SYSMON_SMOOTH,
+  SYSMON_WINDOW, SYSMON_DIFF, SYSMON_CPU_THRESHOLD, SYSMON_MEM_THRESHOLD,
+  SYSMON_TOP, SYSMON_PER_CORE, SYSMON_PER_DISK, SYSMON_HIDE_IDLE,
+  SYSMON_PER_INTERFACE, SYSMON_NO_NETWORK, SYSMON_NO_CPU, SYSMON_NO_MEMORY,
+  SYSMON_NO_DISK, SYSMON_NO_TEMPERATURES, SYSMON_GPU, SYSMON_INTERFACES,
+  SYSMON_IGNORE_INTERFACES, SYSMON_DISKS, SYSMON_TIMESTAMP_FORMAT,
+  SYSMON_NO_TIMESTAMP, SYSMON_ALIGN, SYSMON_MEM_BASIS. SYSMON_CONFIG names the
+  config file.";
 
 #[derive(Parser, Debug)]
 #[command(
     name = "system-monitor",
     about = "Minimal cross-platform system monitor",
-    version
-)]
-#[command(
-    group(
-        ArgGroup::new("mode")
-            .args(&["live", "log"])
-            .multiple(false)
-    )
+    version,
+    after_help = ENV_HELP,
+    args_conflicts_with_subcommands = true
 )]
 pub struct CliArgs {
-    /// Live mode: updates output in place
+    /// Read defaults from this file instead of $XDG_CONFIG_HOME/system-monitor/config.toml
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Options for the default snapshot command, so bare `system-monitor --json` keeps working
+    #[command(flatten)]
+    pub snapshot: SnapshotArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print a single sample and exit (the default without a subcommand)
+    #[command(after_help = ENV_HELP)]
+    Snapshot(SnapshotArgs),
+    /// Redraw the metrics in place every interval
+    #[command(after_help = ENV_HELP)]
+    Live(LiveArgs),
+    /// Print a new line every interval
+    ///
+    /// With --json each line is one compact JSON object with the timestamp
+    /// first (NDJSON), flushed as soon as it is written, so piping into
+    /// `jq -c .`, vector or fluentd handles samples one by one as they arrive.
+    #[command(after_help = ENV_HELP)]
+    Log(LogArgs),
+    /// Write a single machine-readable sample, JSON unless told otherwise
+    #[command(after_help = ENV_HELP)]
+    Export(ExportArgs),
+    /// Play a --record file back through the log or live output
+    ///
+    /// Samples render with the display options given now; the collection
+    /// options are accepted but have nothing to collect.
+    #[cfg(feature = "serde")]
+    Replay(ReplayArgs),
+    /// List the interfaces, disks or temperature sensors there are to monitor
+    ///
+    /// The names printed are the ones --interfaces and --disks take.
+    List(ListArgs),
+    /// Print a shell completion script to stdout
+    Completions(CompletionsArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct SnapshotArgs {
+    /// Sampling window, e.g. 500ms, 2s, 1m or plain (fractional) seconds
+    #[arg(long, default_value = "1", value_parser = parse_duration, allow_negative_numbers = true)]
+    pub interval: Duration,
+
+    #[command(flatten)]
+    pub format: FormatArgs,
+
+    #[command(flatten)]
+    pub quiet: QuietArgs,
+
+    /// Append the sample to this file instead of stdout ("-" means stdout)
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Draw text output as an aligned table: unicode (default) or ascii borders
+    #[arg(
+        long,
+        value_name = "STYLE",
+        num_args = 0..=1,
+        default_missing_value = "unicode",
+        conflicts_with = "output_format"
+    )]
+    pub table: Option<TableStyle>,
+
+    #[command(flatten)]
+    pub limits: LimitArgs,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct LiveArgs {
+    #[command(flatten)]
+    pub schedule: ScheduleArgs,
+
+    #[command(flatten)]
+    pub alerts: AlertArgs,
+
+    #[command(flatten)]
+    pub statsd: StatsdArgs,
+
+    /// Also save every raw sample to FILE (replacing it) for `replay`
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Draw the metrics as an aligned table: unicode (default) or ascii borders
+    #[arg(long, value_name = "STYLE", num_args = 0..=1, default_missing_value = "unicode")]
+    pub table: Option<TableStyle>,
+
+    /// Show CPU and memory usage smoothed over recent samples
+    ///
+    /// A fraction in (0, 1] is the weight of each new sample in an
+    /// exponential moving average: 1 shows the raw values, 0.2 smooths over
+    /// roughly the last ten samples. A whole number N of 2 or more shows the
+    /// mean of the last N samples instead, and averages the disk and network
+    /// rates the same way. The summary, --record and --output still get the
+    /// raw values.
+    #[arg(long, value_name = "ALPHA|N", value_parser = parse_smooth, allow_negative_numbers = true)]
+    pub smooth: Option<Smooth>,
+
+    /// Show disk and network rates averaged over this long, e.g. 5s, while
+    /// still redrawing every --interval
+    ///
+    /// Until the window has filled up the rates cover every sample so far.
+    /// A window no longer than --interval changes nothing.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub window: Option<Duration>,
+
+    /// Show how CPU and memory usage and the total rates changed since the
+    /// previous sample, e.g. "45.2% (+3.1)"
+    ///
+    /// Percentages change in percentage points. The first sample has nothing
+    /// to compare with and shows no changes.
     #[arg(long)]
+    pub diff: bool,
+
+    /// Full-screen dashboard with CPU, memory, disk and network panels and a history graph
+    ///
+    /// q quits and the arrow keys (or tab) move between panels; the graph
+    /// follows the focused panel.
+    #[cfg(feature = "tui")]
+    #[arg(long, conflicts_with_all = ["table", "smooth", "window", "diff"])]
+    pub tui: bool,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct LogArgs {
+    #[command(flatten)]
+    pub schedule: ScheduleArgs,
+
+    #[command(flatten)]
+    pub alerts: AlertArgs,
+
+    #[command(flatten)]
+    pub statsd: StatsdArgs,
+
+    #[command(flatten)]
+    pub format: FormatArgs,
+
+    #[command(flatten)]
+    pub quiet: QuietArgs,
+
+    /// Also save every sample to FILE (replacing it) for `replay`
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Append samples to this file instead of stdout ("-" means stdout)
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Empty the --output file before writing instead of appending to it
+    ///
+    /// When appending CSV to a file that already starts with the same header,
+    /// the header is not written again. With --max-file-size only the active
+    /// file is emptied; FILE.1 and older stay until rotation replaces them.
+    #[arg(long)]
+    pub truncate: bool,
+
+    /// Rotate the --output file once it reaches this size, e.g. 512K, 10M or 1G
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_file_size: Option<u64>,
+
+    /// Rotated files to keep with --max-file-size (FILE.1 is the newest)
+    #[arg(long, value_name = "N", default_value_t = 5, requires = "max_file_size")]
+    pub max_files: usize,
+
+    /// Repeat the --columns header every N rows; 0 prints it only once
+    #[arg(long, value_name = "N", default_value_t = HEADER_EVERY)]
+    pub header_every: usize,
+
+    /// Send each sample to the local syslog at INFO instead of stdout
+    #[cfg(feature = "syslog")]
+    #[arg(long, conflicts_with = "output")]
+    pub syslog: bool,
+
+    /// Syslog facility for --syslog
+    #[cfg(feature = "syslog")]
+    #[arg(long, value_enum, value_name = "FACILITY", default_value_t = Facility::User, requires = "syslog")]
+    pub syslog_facility: Facility,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Sampling window, e.g. 500ms, 2s, 1m or plain (fractional) seconds
+    #[arg(long, default_value = "1", value_parser = parse_duration, allow_negative_numbers = true)]
+    pub interval: Duration,
+
+    /// Output format: json, ndjson, csv, raw, prometheus, influx, text, line, columns or a template
+    #[arg(long, default_value = "json")]
+    pub format: OutputFormat,
+
+    /// Append the sample to this file instead of stdout ("-" means stdout)
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Serve Prometheus metrics on GET /metrics at this address, e.g. 0.0.0.0:9100
+    ///
+    /// Samples are cached for --interval, so scrapes more frequent than that
+    /// see the same values instead of waiting for a fresh window. Disk and
+    /// network bytes are served as _total counters since the server started.
+    #[cfg(feature = "serve")]
+    #[arg(long, visible_alias = "prometheus-listen", value_name = "ADDR", conflicts_with_all = ["format", "output"])]
+    pub serve: Option<String>,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// File written with --record
+    #[arg(value_name = "FILE")]
+    pub file: PathBuf,
+
+    /// Play back this many times faster than recorded, e.g. 10x or 0.5x
+    #[arg(long, value_name = "FACTOR", default_value = "1x", value_parser = parse_speed)]
+    pub speed: f64,
+
+    /// Redraw in place like `live` instead of printing a line per sample like `log`
+    #[arg(long, conflicts_with = "output_format")]
     pub live: bool,
 
-    /// Log mode: prints a new line every update
+    /// Draw --live output as an aligned table: unicode (default) or ascii borders
+    #[arg(long, value_name = "STYLE", num_args = 0..=1, default_missing_value = "unicode", requires = "live")]
+    pub table: Option<TableStyle>,
+
+    #[command(flatten)]
+    pub alerts: AlertArgs,
+
+    #[command(flatten)]
+    pub format: FormatArgs,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell to generate completions for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// What to list
+    #[arg(value_enum)]
+    pub what: Listing,
+
+    /// Print a JSON array instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// What `list` enumerates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Listing {
+    /// Network interfaces with their MAC address and MTU
+    Interfaces,
+    /// Mounted disks with their device, filesystem and size
+    Disks,
+    /// Temperature sensors with their current and critical readings
+    Sensors,
+}
+
+/// Pacing and bounds of the live and log loops
+#[derive(Args, Debug)]
+pub struct ScheduleArgs {
+    /// Update interval, e.g. 500ms, 2s, 1m or plain (fractional) seconds; also the sampling window
+    #[arg(long, default_value = "1", value_parser = parse_duration, allow_negative_numbers = true)]
+    pub interval: Duration,
+
+    /// Stop after this many samples (default: run until interrupted)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub count: Option<u64>,
+
+    /// Stop after this long, e.g. 30s, 5m, 1h or plain seconds
+    #[arg(long, value_parser = parse_duration)]
+    pub duration: Option<Duration>,
+
+    /// Stop once the --pid or --pid-name process exits instead of sampling on without it
+    #[arg(long, requires = "watch")]
+    pub exit_with_process: bool,
+}
+
+#[derive(Args, Debug)]
+#[group(id = "output_format", multiple = false)]
+pub struct FormatArgs {
+    /// Output format: text, line, json, ndjson, csv, raw, prometheus, influx, columns or a template
+    ///
+    /// A template is any value containing placeholders, for example
+    /// "cpu={cpu} mem={mem_used}/{mem_total} rx={net_rx}". Formatted values use
+    /// the plain name ({mem_used}); raw numbers add a suffix ({mem_used_bytes},
+    /// {net_rx_per_sec}, {cpu_percent}). {timestamp} follows --timestamp-format
+    /// and {{ / }} print literal braces.
+    #[arg(long, default_value = "text")]
+    pub format: OutputFormat,
+
+    /// Shorthand for --format json
+    #[arg(long)]
+    pub json: bool,
+
+    /// Shorthand for --format ndjson: one compact JSON object per line, for jq and log shippers
+    #[arg(long)]
+    pub ndjson: bool,
+
+    /// Shorthand for --format csv
+    #[arg(long)]
+    pub csv: bool,
+
+    /// Print bare numbers for scripts (shorthand for --format raw)
+    ///
+    /// Values are tab separated in a fixed order: cpu_percent, mem_used,
+    /// mem_total, swap_used, swap_total, disk_read, disk_write, net_rx,
+    /// net_tx. Byte counters are plain integers and CPU is a bare float.
+    /// Groups left out by --fields are dropped without changing the order of
+    /// the rest.
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Shorthand for --format influx, InfluxDB line protocol tagged with the host name
+    #[arg(long)]
+    pub influx: bool,
+
+    /// Shorthand for --format columns: one sample per line in fixed-width
+    /// columns (cpu, mem%, dsk-r, dsk-w, net-rx, net-tx), like vmstat
+    #[arg(long)]
+    pub columns: bool,
+}
+
+/// Thresholds that decide the exit status of a snapshot
+#[derive(Args, Debug)]
+pub struct LimitArgs {
+    /// Exit 1 when CPU usage reaches this percentage
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    pub cpu_warn: Option<f64>,
+
+    /// Exit 2 when CPU usage reaches this percentage
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    pub cpu_crit: Option<f64>,
+
+    /// Exit 1 when memory usage reaches this percentage
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    pub mem_warn: Option<f64>,
+
+    /// Exit 2 when memory usage reaches this percentage
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    pub mem_crit: Option<f64>,
+}
+
+/// Thresholds that mark samples of the live and log loops
+#[derive(Args, Debug)]
+pub struct AlertArgs {
+    /// Mark samples whose CPU usage reaches this percentage with [ALERT cpu]
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    pub cpu_threshold: Option<f64>,
+
+    /// Mark samples whose memory usage reaches this percentage with [ALERT mem]
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    pub mem_threshold: Option<f64>,
+
+    /// Stop with exit status 1 after the first marked sample
+    #[arg(long)]
+    pub exit_on_alert: bool,
+}
+
+/// A single bare value, e.g. for a polybar or tmux status line
+#[derive(Args, Debug)]
+pub struct QuietArgs {
+    /// Print only the value picked with --metric, without header or labels
+    #[arg(long, requires = "metric")]
+    pub quiet: bool,
+
+    /// The value --quiet prints
+    #[arg(long, value_enum, value_name = "NAME", requires = "quiet", conflicts_with = "output_format")]
+    pub metric: Option<Metric>,
+}
+
+/// Values `--metric` can pick, formatted as in text output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Metric {
+    Cpu,
+    Mem,
+    MemTotal,
+    MemPct,
+    Swap,
+    SwapPct,
+    DiskRead,
+    DiskWrite,
+    NetRx,
+    NetTx,
+    Uptime,
+}
+
+impl Metric {
+    /// The template placeholder printing this value
+    fn placeholder(&self) -> &'static str {
+        match self {
+            Metric::Cpu => "cpu",
+            Metric::Mem => "mem_used",
+            Metric::MemTotal => "mem_total",
+            Metric::MemPct => "mem_percent",
+            Metric::Swap => "swap_used",
+            Metric::SwapPct => "swap_percent",
+            Metric::DiskRead => "disk_read",
+            Metric::DiskWrite => "disk_write",
+            Metric::NetRx => "net_rx",
+            Metric::NetTx => "net_tx",
+            Metric::Uptime => "uptime",
+        }
+    }
+}
+
+impl QuietArgs {
+    /// A template of just the `--metric` value with `--quiet`, otherwise `format`'s choice
+    pub fn output_format(&self, format: &FormatArgs) -> OutputFormat {
+        match self.metric {
+            Some(metric) => OutputFormat::Template(
+                Template::parse(&format!("{{{}}}", metric.placeholder())).expect("every metric is a known placeholder"),
+            ),
+            None => format.output_format(),
+        }
+    }
+}
+
+/// Where the live and log loops push their samples as StatsD gauges
+#[derive(Args, Debug)]
+pub struct StatsdArgs {
+    /// Also send every sample as StatsD gauges over UDP, e.g. localhost:8125
+    #[arg(long, value_name = "HOST:PORT")]
+    pub statsd_host: Option<String>,
+
+    /// Prefix for the StatsD metric names
+    #[arg(long, value_name = "PREFIX", default_value = "system", requires = "statsd_host")]
+    pub statsd_prefix: String,
+
+    /// DogStatsD tags added to every gauge, e.g. env:prod,role:web
+    #[arg(long, value_name = "TAGS", value_delimiter = ',', requires = "statsd_host")]
+    pub statsd_tags: Vec<String>,
+}
+
+/// What to collect and how to present it, shared by every command
+#[derive(Args, Debug)]
+pub struct DisplayArgs {
+    /// Comma separated metric groups to show
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Field::Cpu, Field::Mem, Field::Disk, Field::Net]
+    )]
+    pub fields: Vec<Field>,
+
+    /// Unit system for byte values; bits also shows every rate in bits per second
+    #[arg(long, value_enum, default_value_t = Units::Si)]
+    pub units: Units,
+
+    /// Shorthand for --units binary
+    #[arg(long, conflicts_with = "units")]
+    pub binary_units: bool,
+
+    /// Show network rates in bytes or bits per second (Mbps, as links are rated)
+    #[arg(long, value_enum, default_value_t = NetUnit::Bytes)]
+    pub net_unit: NetUnit,
+
+    /// Base the memory percentage and memory thresholds on used memory or
+    /// on what isn't available, which leaves reclaimable cache out
+    #[arg(long, value_enum, default_value_t = MemBasis::Used)]
+    pub mem_basis: MemBasis,
+
+    /// Show byte values with exactly N decimals instead of ~3 significant digits
+    #[arg(long, value_name = "N")]
+    pub precision: Option<usize>,
+
+    /// When to color CPU and memory percentages
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Shorthand for --color never
+    #[arg(long, conflicts_with = "color")]
+    pub no_color: bool,
+
+    /// Color percentages yellow from this one on (default 50), e.g. 70, or
+    /// per metric: cpu=70,mem=85
+    ///
+    /// Only takes effect when output is colored. A value exactly at a
+    /// breakpoint already gets its color.
+    #[arg(long, value_name = "[METRIC=]PERCENT", value_delimiter = ',', value_parser = parse_breakpoint)]
+    pub warn: Vec<Breakpoint>,
+
+    /// Color percentages red from this one on (default 80), like --warn
+    #[arg(long, value_name = "[METRIC=]PERCENT", value_delimiter = ',', value_parser = parse_breakpoint)]
+    pub crit: Vec<Breakpoint>,
+
+    /// Also list the N processes using the most CPU and the N using the most memory
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Also list each CPU core's usage in text output
+    #[arg(long)]
+    pub per_core: bool,
+
+    /// Also break disk I/O and free space down per disk
+    #[arg(long)]
+    pub per_disk: bool,
+
+    /// Leave disks and interfaces without traffic out of --per-disk and --per-interface
+    #[arg(long)]
+    pub hide_idle: bool,
+
+    /// Also break network traffic down per interface
+    #[arg(long)]
+    pub per_interface: bool,
+
+    /// Skip network collection entirely and leave the network figures out
+    #[arg(long, conflicts_with_all = ["per_interface", "interfaces", "ignore_interfaces"])]
+    pub no_network: bool,
+
+    /// Skip CPU collection entirely and leave the CPU figures out
+    #[arg(long, conflicts_with = "per_core")]
+    pub no_cpu: bool,
+
+    /// Skip memory collection entirely and leave the memory and swap figures out
+    #[arg(long)]
+    pub no_memory: bool,
+
+    /// Skip disk collection entirely and leave the disk figures out
+    #[arg(long, conflicts_with_all = ["per_disk", "disks"])]
+    pub no_disk: bool,
+
+    /// Skip reading temperature sensors
     #[arg(long)]
-    pub log: bool,
+    pub no_temperatures: bool,
+
+    /// Also report NVIDIA GPU load and memory, "unavailable" without a driver or the gpu feature
+    #[arg(long)]
+    pub gpu: bool,
+
+    /// Also report CPU, resident and virtual memory and disk I/O of this process
+    #[arg(long, value_name = "PID", group = "watch")]
+    pub pid: Option<u32>,
+
+    /// Like --pid, for the process with this name (the lowest pid if several match)
+    #[arg(long, value_name = "NAME", group = "watch")]
+    pub pid_name: Option<String>,
+
+    /// Show only this process, given by pid or name, and stop when it exits
+    #[arg(long, value_name = "PID|NAME", group = "watch", conflicts_with = "fields")]
+    pub watch_process: Option<ProcessTarget>,
+
+    /// Add up every process with the --pid-name or --watch-process name instead of taking the lowest pid
+    #[arg(long, requires = "watch", conflicts_with = "pid")]
+    pub all_matches: bool,
+
+    /// Only count these network interfaces, e.g. eth0,wlan0 to leave out VPN and docker traffic
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    pub interfaces: Option<Vec<String>>,
+
+    /// Leave out interfaces whose name matches this regex, e.g. '^(veth|br-|docker)'
+    ///
+    /// Applies after --interfaces, to the totals and --per-interface alike.
+    #[arg(long, value_name = "REGEX", value_parser = parse_regex)]
+    pub ignore_interfaces: Option<Regex>,
+
+    /// Only count disks matching these device names or mount point prefixes, e.g. sda1,/home
+    #[arg(long, visible_alias = "mounts", value_name = "PATTERNS", value_delimiter = ',')]
+    pub disks: Option<Vec<String>>,
+
+    /// Timestamp style: rfc3339, unix, unix-ms or a strftime pattern like "%H:%M:%S"
+    #[arg(long, default_value = "rfc3339")]
+    pub timestamp_format: TimestampFormat,
+
+    /// Leave timestamps out of every record, e.g. when piping through ts(1)
+    #[arg(long, conflicts_with = "timestamp_format")]
+    pub no_timestamp: bool,
+
+    /// Right-align the values of key=value lines in fixed-width fields, so
+    /// columns stay put as values grow and `cut -c` works
+    #[arg(long)]
+    pub align: bool,
+}
+
+impl CliArgs {
+    /// The command to run; no subcommand means `snapshot`
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::Snapshot(self.snapshot))
+    }
+
+    /// Fill every option that was left at its default from `config`
+    pub fn apply_config(&mut self, config: &Config, matches: &ArgMatches) -> Result<(), String> {
+        let sub_matches = matches.subcommand().map_or(matches, |(_, sub_matches)| sub_matches);
+
+        match &mut self.command {
+            None => self.snapshot.apply_config(config, matches),
+            Some(Command::Snapshot(args)) => args.apply_config(config, sub_matches),
+            Some(Command::Live(args)) => {
+                config.apply_schedule(&mut args.schedule, sub_matches)?;
+                config.apply_alerts(&mut args.alerts, sub_matches);
+                config.apply_table(&mut args.table, sub_matches);
+                config.apply_smooth(&mut args.smooth, sub_matches);
+                config.apply_window(&mut args.window, sub_matches)?;
+                config.apply_diff(&mut args.diff, sub_matches);
+                config.apply_display(&mut args.display, sub_matches)
+            }
+            Some(Command::Log(args)) => {
+                config.apply_schedule(&mut args.schedule, sub_matches)?;
+                config.apply_alerts(&mut args.alerts, sub_matches);
+                config.apply_format(&mut args.format, sub_matches)?;
+                config.apply_output(&mut args.output, sub_matches);
+                config.apply_display(&mut args.display, sub_matches)
+            }
+            Some(Command::Export(args)) => {
+                if let Some(interval) = config.interval
+                    && is_default(sub_matches, "interval")
+                {
+                    args.interval = interval;
+                }
+                config.apply_bare_format(&mut args.format, sub_matches)?;
+                config.apply_output(&mut args.output, sub_matches);
+                config.apply_display(&mut args.display, sub_matches)
+            }
+            #[cfg(feature = "serde")]
+            Some(Command::Replay(args)) => {
+                config.apply_alerts(&mut args.alerts, sub_matches);
+                config.apply_format(&mut args.format, sub_matches)?;
+                config.apply_display(&mut args.display, sub_matches)
+            }
+            Some(Command::List(_) | Command::Completions(_)) => Ok(()),
+        }
+    }
+}
+
+impl SnapshotArgs {
+    fn apply_config(&mut self, config: &Config, matches: &ArgMatches) -> Result<(), String> {
+        if let Some(interval) = config.interval
+            && is_default(matches, "interval")
+        {
+            self.interval = interval;
+        }
+        config.apply_format(&mut self.format, matches)?;
+        config.apply_output(&mut self.output, matches);
+        config.apply_table(&mut self.table, matches);
+        config.apply_display(&mut self.display, matches)
+    }
+}
+
+impl LogArgs {
+    /// The output format, with --header-every applied to columns
+    pub fn output_format(&self) -> OutputFormat {
+        match self.quiet.output_format(&self.format) {
+            OutputFormat::Columns { .. } => OutputFormat::Columns { header_every: self.header_every },
+            format => format,
+        }
+    }
+
+    /// Presentation settings for wherever the samples go
+    pub fn format_options(&self) -> FormatOptions {
+        #[cfg(feature = "syslog")]
+        if self.syslog {
+            // Syslog is never a terminal, whatever stdout is
+            return FormatOptions { color: self.display.color_choice().enabled(false), ..self.display.format_options(None) };
+        }
+
+        self.display.format_options(self.output.as_deref())
+    }
+}
+
+impl FormatArgs {
+    /// The output format after resolving shorthand flags
+    pub fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else if self.ndjson {
+            OutputFormat::Ndjson
+        } else if self.csv {
+            OutputFormat::Csv
+        } else if self.raw {
+            OutputFormat::Raw
+        } else if self.influx {
+            OutputFormat::Influx
+        } else if self.columns {
+            OutputFormat::Columns { header_every: HEADER_EVERY }
+        } else {
+            self.format.clone()
+        }
+    }
+}
 
-    /// Update interval in seconds
-    #[arg(long, default_value_t = 1)]
-    pub interval: u64,
+impl LimitArgs {
+    pub fn limits(&self, mem_basis: MemBasis) -> Limits {
+        Limits { cpu_warn: self.cpu_warn, cpu_crit: self.cpu_crit, mem_warn: self.mem_warn, mem_crit: self.mem_crit, mem_basis }
+    }
 }
 
-pub fn parse_args() -> CliArgs {
-    CliArgs::parse()
+impl AlertArgs {
+    pub fn limits(&self, mem_basis: MemBasis) -> Limits {
+        Limits { cpu_warn: self.cpu_threshold, mem_warn: self.mem_threshold, mem_basis, ..Default::default() }
+    }
+}
+
+impl DisplayArgs {
+    /// Optional extras to collect with every sample
+    pub fn collect_options(&self) -> CollectOptions {
+        // Nothing system-wide is shown, so nothing needs collecting
+        let watch_only = self.watch_process.is_some();
+        CollectOptions {
+            cpu: !self.no_cpu && !watch_only,
+            memory: !self.no_memory && !watch_only,
+            disk: !self.no_disk && !watch_only,
+            network: !self.no_network && !watch_only,
+            temperature: !self.no_temperatures && !watch_only,
+            top_processes: self.top,
+            per_disk: self.per_disk,
+            hide_idle: self.hide_idle,
+            per_interface: self.per_interface,
+            interfaces: self.interfaces.clone(),
+            ignore_interfaces: self.ignore_interfaces.clone(),
+            disks: self.disks.clone(),
+            gpu: self.gpu,
+            process: self.process_target(),
+        }
+    }
+
+    /// Presentation settings for samples written to `output` (`None` is stdout)
+    pub fn format_options(&self, output: Option<&Path>) -> FormatOptions {
+        FormatOptions {
+            // --watch-process shows the process alone
+            fields: if self.watch_process.is_some() { Fields::from_list(&[]) } else { Fields::from_list(&self.fields) },
+            units: if self.binary_units { Units::Binary } else { self.units },
+            color: self.color_choice().enabled(writes_to_terminal(output)),
+            timestamp: (!self.no_timestamp).then(|| self.timestamp_format.clone()),
+            table: None,
+            precision: self.precision,
+            per_core: self.per_core,
+            width: None,
+            net_unit: self.net_unit,
+            process: self.process_target().is_some(),
+            align: self.align,
+            mem_basis: self.mem_basis,
+            cpu_colors: self.breakpoints(ColorMetric::Cpu),
+            mem_colors: self.breakpoints(ColorMetric::Mem),
+        }
+    }
+
+    fn process_target(&self) -> Option<ProcessTarget> {
+        let target = self
+            .pid
+            .map(ProcessTarget::Pid)
+            .or_else(|| self.pid_name.clone().map(ProcessTarget::Name))
+            .or_else(|| self.watch_process.clone())?;
+        Some(match target {
+            ProcessTarget::Name(name) if self.all_matches => ProcessTarget::AllNamed(name),
+            target => target,
+        })
+    }
+
+    /// The --warn and --crit values for `metric`: its own where given, else
+    /// the ones for both, else the defaults
+    fn breakpoints(&self, metric: ColorMetric) -> Breakpoints {
+        let pick = |list: &[Breakpoint], default: f64| {
+            let shared = list.iter().filter(|breakpoint| breakpoint.metric.is_none());
+            let own = list.iter().filter(|breakpoint| breakpoint.metric == Some(metric));
+            shared.chain(own).last().map_or(default, |breakpoint| breakpoint.percent)
+        };
+
+        Breakpoints { warn: pick(&self.warn, WARN_PERCENT), crit: pick(&self.crit, CRIT_PERCENT) }
+    }
+
+    fn color_choice(&self) -> ColorChoice {
+        if self.no_color {
+            ColorChoice::Never
+        } else {
+            self.color
+        }
+    }
+}
+
+/// Whether samples end up on an interactive terminal rather than a file or pipe
+fn writes_to_terminal(output: Option<&Path>) -> bool {
+    let to_stdout = output.is_none_or(|path| path == Path::new("-"));
+    to_stdout && io::stdout().is_terminal()
+}
+
+/// Parse a percentage between 0 and 100
+fn parse_percent(input: &str) -> Result<f64, String> {
+    let percent: f64 = input.parse().map_err(|_| format!("invalid percentage '{}'", input))?;
+    check_percent(percent)
+}
+
+/// A metric --warn and --crit can single out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMetric {
+    Cpu,
+    Mem,
+}
+
+/// One --warn or --crit value; `metric` is `None` for both
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Breakpoint {
+    pub metric: Option<ColorMetric>,
+    pub percent: f64,
+}
+
+fn parse_breakpoint(input: &str) -> Result<Breakpoint, String> {
+    let (metric, percent) = match input.split_once('=') {
+        Some(("cpu", percent)) => (Some(ColorMetric::Cpu), percent),
+        Some(("mem", percent)) => (Some(ColorMetric::Mem), percent),
+        Some((metric, _)) => return Err(format!("unknown metric '{}', expected cpu or mem", metric)),
+        None => (None, input),
+    };
+
+    Ok(Breakpoint { metric, percent: parse_percent(percent)? })
+}
+
+pub fn check_percent(percent: f64) -> Result<f64, String> {
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(format!("percentage must be between 0 and 100, got {}", percent));
+    }
+
+    Ok(percent)
+}
+
+pub fn parse_regex(input: &str) -> Result<Regex, String> {
+    Regex::new(input).map_err(|err| err.to_string())
+}
+
+/// A byte count with an optional K, M or G suffix (powers of 1024)
+fn parse_size(input: &str) -> Result<u64, String> {
+    let upper = input.trim().to_ascii_uppercase();
+    let digits = upper.strip_suffix('B').unwrap_or(&upper);
+    let (digits, multiplier) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 1 << 10),
+        Some('M') => (&digits[..digits.len() - 1], 1 << 20),
+        Some('G') => (&digits[..digits.len() - 1], 1 << 30),
+        _ => (digits, 1),
+    };
+    let size: u64 = digits.trim().parse().map_err(|_| format!("invalid size '{}', expected e.g. 512K, 10M or 1G", input))?;
+    if size == 0 {
+        return Err("size must be greater than 0".to_string());
+    }
+
+    size.checked_mul(multiplier).ok_or_else(|| format!("size '{}' is too large", input))
+}
+
+/// A playback speed factor, "10x" or plain "10"
+#[cfg(feature = "serde")]
+fn parse_speed(input: &str) -> Result<f64, String> {
+    let factor = input.strip_suffix(['x', 'X']).unwrap_or(input);
+    let speed: f64 = factor.trim().parse().map_err(|_| format!("invalid speed '{}', expected e.g. 10x", input))?;
+    if !(speed > 0.0 && speed.is_finite()) {
+        return Err(format!("speed must be greater than 0, got {}", input));
+    }
+
+    Ok(speed)
+}
+
+/// How `--smooth` averages what `live` shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smooth {
+    /// Exponential moving average with this weight for each new sample
+    Exponential(f64),
+    /// Mean of the last this many samples
+    Rolling(usize),
+}
+
+fn parse_smooth(input: &str) -> Result<Smooth, String> {
+    let value: f64 = input.parse().map_err(|_| format!("invalid smoothing factor '{}'", input))?;
+    check_smooth(value)
+}
+
+/// Whole numbers from 2 up count samples; anything else is a smoothing
+/// factor, which must be in (0, 1] as 0 would never move off the first sample
+pub fn check_smooth(value: f64) -> Result<Smooth, String> {
+    if value >= 2.0 && value.fract() == 0.0 {
+        return Ok(Smooth::Rolling(value as usize));
+    }
+    if !(value > 0.0 && value <= 1.0) {
+        return Err(format!(
+            "smoothing must be a factor greater than 0 and at most 1, or a sample count of at least 2, got {}",
+            value
+        ));
+    }
+
+    Ok(Smooth::Exponential(value))
+}
+
+/// Write the completion script for `shell` to `out`
+pub fn write_completions(shell: Shell, out: &mut dyn io::Write) {
+    clap_complete::generate(shell, &mut CliArgs::command(), "system-monitor", out);
+}
+
+/// Whether the option with `id` still has its built-in default
+pub fn is_default(matches: &ArgMatches, id: &str) -> bool {
+    matches
+        .value_source(id)
+        .is_none_or(|source| source == clap::parser::ValueSource::DefaultValue)
+}
+
+/// The command line as it was given, kept so a SIGHUP can merge the config
+/// file with it again
+pub struct Invocation {
+    matches: ArgMatches,
+}
+
+impl Invocation {
+    /// Parse the process's command line, exiting on bad arguments
+    pub fn parse() -> Invocation {
+        Invocation { matches: CliArgs::command().get_matches() }
+    }
+
+    /// The command line with the gaps filled, first from `SYSMON_*`
+    /// variables and then from the config file, both read afresh
+    pub fn resolve(&self) -> Result<CliArgs, Box<dyn Error>> {
+        let mut args = CliArgs::from_arg_matches(&self.matches).unwrap_or_else(|err| err.exit());
+
+        let env = Config::from_env()?;
+        let path = args.config.clone().or_else(|| env::var_os("SYSMON_CONFIG").map(PathBuf::from));
+        let config = env.or(Config::load(path.as_deref())?);
+        args.apply_config(&config, &self.matches)?;
+
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_rereads_the_config_file() {
+        let path = env::temp_dir().join(format!("system-monitor-reload-{}.toml", std::process::id()));
+        fs::write(&path, "interval = 5\nfields = [\"cpu\"]\n").unwrap();
+        let argv = ["system-monitor", "log", "--count", "3", "--config", path.to_str().unwrap()];
+        let invocation = Invocation { matches: CliArgs::command().get_matches_from(argv) };
+        let resolved = |invocation: &Invocation| match invocation.resolve().unwrap().into_command() {
+            Command::Log(args) => args,
+            _ => panic!("expected the log command"),
+        };
+
+        assert_eq!(resolved(&invocation).schedule.interval, Duration::from_secs(5));
+        fs::write(&path, "interval = 2\ncount = 10\n").unwrap();
+        let args = resolved(&invocation);
+        assert_eq!(args.schedule.interval, Duration::from_secs(2));
+        assert_eq!(args.display.fields, [Field::Cpu, Field::Mem, Field::Disk, Field::Net]);
+        // The command line still wins
+        assert_eq!(args.schedule.count, Some(3));
+
+        fs::write(&path, "interval = \"soon\"\n").unwrap();
+        assert!(invocation.resolve().is_err());
+        fs::remove_file(&path).unwrap();
+    }
 }
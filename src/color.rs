@@ -0,0 +1,160 @@
+use std::env;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::format_percent;
+use crate::threshold::{Status, severity};
+
+/// Usage from this percentage on is shown in yellow unless `--warn` says otherwise
+pub const WARN_PERCENT: f64 = 50.0;
+/// Usage from this percentage on is shown in red unless `--crit` says otherwise
+pub const CRIT_PERCENT: f64 = 80.0;
+
+const RESET: &str = "\x1b[0m";
+
+/// When to color terminal output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Color when writing to a terminal and NO_COLOR is unset
+    #[default]
+    Auto,
+    /// Always color, even when piped
+    Always,
+    /// Never color
+    Never,
+}
+
+impl ColorChoice {
+    /// Resolve the choice for an output that is or isn't a terminal
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && !no_color_requested(),
+        }
+    }
+}
+
+/// Whether the NO_COLOR convention (https://no-color.org) asks for plain output
+fn no_color_requested() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, Deserialize))]
+pub enum Color {
+    Green,
+    Yellow,
+    Red,
+}
+
+impl Color {
+    fn code(&self) -> &'static str {
+        match self {
+            Color::Green => "\x1b[32m",
+            Color::Yellow => "\x1b[33m",
+            Color::Red => "\x1b[31m",
+        }
+    }
+}
+
+/// Percentages at which a value turns yellow and then red
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Breakpoints {
+    pub warn: f64,
+    pub crit: f64,
+}
+
+impl Default for Breakpoints {
+    fn default() -> Self {
+        Breakpoints { warn: WARN_PERCENT, crit: CRIT_PERCENT }
+    }
+}
+
+impl Breakpoints {
+    /// Color for `percent`, classified the way thresholds are by [`severity`]
+    pub fn color(&self, percent: f64) -> Color {
+        severity_color(severity(percent, Some(self.warn), Some(self.crit)))
+    }
+}
+
+/// Green, yellow or red for ok, warning or critical
+pub fn severity_color(status: Status) -> Color {
+    match status {
+        Status::Ok => Color::Green,
+        Status::Warning => Color::Yellow,
+        Status::Critical => Color::Red,
+    }
+}
+
+/// Color for a usage percentage at the default [`Breakpoints`]
+pub fn usage_color(percent: f64) -> Color {
+    Breakpoints::default().color(percent)
+}
+
+/// Wrap `text` in the escape codes for `color`; `None` leaves it untouched
+pub fn paint(text: &str, color: Option<Color>) -> String {
+    match color {
+        Some(color) => format!("{}{}{}", color.code(), text, RESET),
+        None => text.to_string(),
+    }
+}
+
+/// `value` formatted like "42.0%" in its usage color, plain when NO_COLOR is set
+pub fn colorize_percent(value: f32) -> String {
+    let color = (!no_color_requested()).then(|| usage_color(value as f64));
+    paint(&format_percent(value), color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_color() {
+        assert_eq!(usage_color(0.0), Color::Green);
+        assert_eq!(usage_color(49.9), Color::Green);
+        assert_eq!(usage_color(50.0), Color::Yellow);
+        assert_eq!(usage_color(79.9), Color::Yellow);
+        assert_eq!(usage_color(80.0), Color::Red);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let strict = Breakpoints { warn: 70.0, crit: 90.0 };
+        assert_eq!(strict.color(69.9), Color::Green);
+        assert_eq!(strict.color(70.0), Color::Yellow);
+        assert_eq!(strict.color(90.0), Color::Red);
+
+        // A warning level at or over the critical one leaves no yellow
+        let sharp = Breakpoints { warn: 90.0, crit: 90.0 };
+        assert_eq!(sharp.color(89.0), Color::Green);
+        assert_eq!(sharp.color(90.0), Color::Red);
+    }
+
+    #[test]
+    fn test_color_choice() {
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(!ColorChoice::Auto.enabled(false));
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint("50.0%", None), "50.0%");
+        assert_eq!(paint("90.0%", Some(Color::Red)), "\x1b[31m90.0%\x1b[0m");
+    }
+
+    #[test]
+    fn test_colorize_percent() {
+        if no_color_requested() {
+            assert_eq!(colorize_percent(65.0), "65.0%");
+        } else {
+            assert_eq!(colorize_percent(12.0), "\x1b[32m12.0%\x1b[0m");
+            assert_eq!(colorize_percent(65.0), "\x1b[33m65.0%\x1b[0m");
+            assert_eq!(colorize_percent(95.5), "\x1b[31m95.5%\x1b[0m");
+        }
+    }
+}
@@ -0,0 +1,620 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::{ArgMatches, ValueEnum};
+use serde::{Deserialize, Deserializer};
+use system_monitor::color::ColorChoice;
+use system_monitor::render::TableStyle;
+use system_monitor::{Field, MemBasis, NetUnit, OutputFormat, TimestampFormat, Units, parse_duration};
+
+use crate::args::{AlertArgs, DisplayArgs, FormatArgs, ScheduleArgs, Smooth, check_percent, check_smooth, is_default, parse_regex};
+
+/// Defaults read from `config.toml` or `SYSMON_*` variables, one key per CLI option
+///
+/// Every key is optional and only fills in options that were not given on
+/// the command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(default, deserialize_with = "interval_value")]
+    pub interval: Option<Duration>,
+    pub count: Option<u64>,
+    pub duration: Option<String>,
+    pub format: Option<String>,
+    pub output: Option<PathBuf>,
+    pub table: Option<TableStyle>,
+    pub smooth: Option<f64>,
+    pub window: Option<String>,
+    pub diff: Option<bool>,
+    pub cpu_threshold: Option<f64>,
+    pub mem_threshold: Option<f64>,
+    pub fields: Option<Vec<Field>>,
+    pub units: Option<Units>,
+    pub net_unit: Option<NetUnit>,
+    pub mem_basis: Option<MemBasis>,
+    pub precision: Option<usize>,
+    pub color: Option<ColorChoice>,
+    pub top: Option<usize>,
+    pub per_core: Option<bool>,
+    pub per_disk: Option<bool>,
+    pub hide_idle: Option<bool>,
+    pub per_interface: Option<bool>,
+    pub no_network: Option<bool>,
+    pub no_cpu: Option<bool>,
+    pub no_memory: Option<bool>,
+    pub no_disk: Option<bool>,
+    pub no_temperatures: Option<bool>,
+    pub gpu: Option<bool>,
+    pub interfaces: Option<Vec<String>>,
+    pub ignore_interfaces: Option<String>,
+    pub disks: Option<Vec<String>>,
+    pub timestamp_format: Option<String>,
+    pub no_timestamp: Option<bool>,
+    pub align: Option<bool>,
+}
+
+impl Config {
+    /// Load `path`, or the default location when `None`
+    ///
+    /// A missing default file is an empty config; a missing explicit path is
+    /// an error.
+    pub fn load(path: Option<&Path>) -> Result<Config, Box<dyn Error>> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => Config::parse(&text)
+                .map_err(|err| format!("invalid config file {}: {}", path.display(), err).into()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !explicit => Ok(Config::default()),
+            Err(err) => Err(format!("cannot read config file {}: {}", path.display(), err).into()),
+        }
+    }
+
+    /// Read the `SYSMON_*` variables, e.g. `SYSMON_INTERVAL` or `SYSMON_TIMESTAMP_FORMAT`
+    pub fn from_env() -> Result<Config, String> {
+        Config::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Config, String> {
+        let var = |name: &str| lookup(name).filter(|value| !value.is_empty());
+        let config = Config {
+            interval: var("SYSMON_INTERVAL")
+                .map(|value| parse_duration(&value).map_err(|err| var_error("SYSMON_INTERVAL", &value, err)))
+                .transpose()?,
+            count: parse_var(&var, "SYSMON_COUNT")?,
+            duration: var("SYSMON_DURATION"),
+            format: var("SYSMON_FORMAT"),
+            output: var("SYSMON_OUTPUT").map(PathBuf::from),
+            table: enum_var(&var, "SYSMON_TABLE")?,
+            smooth: parse_var(&var, "SYSMON_SMOOTH")?,
+            window: var("SYSMON_WINDOW"),
+            diff: bool_var(&var, "SYSMON_DIFF")?,
+            cpu_threshold: parse_var(&var, "SYSMON_CPU_THRESHOLD")?,
+            mem_threshold: parse_var(&var, "SYSMON_MEM_THRESHOLD")?,
+            fields: match var("SYSMON_FIELDS") {
+                Some(list) => Some(
+                    list.split(',')
+                        .map(|field| parse_enum("SYSMON_FIELDS", field.trim()))
+                        .collect::<Result<_, _>>()?,
+                ),
+                None => None,
+            },
+            units: enum_var(&var, "SYSMON_UNITS")?,
+            net_unit: enum_var(&var, "SYSMON_NET_UNIT")?,
+            mem_basis: enum_var(&var, "SYSMON_MEM_BASIS")?,
+            precision: parse_var(&var, "SYSMON_PRECISION")?,
+            color: enum_var(&var, "SYSMON_COLOR")?,
+            top: parse_var(&var, "SYSMON_TOP")?,
+            per_core: bool_var(&var, "SYSMON_PER_CORE")?,
+            per_disk: bool_var(&var, "SYSMON_PER_DISK")?,
+            hide_idle: bool_var(&var, "SYSMON_HIDE_IDLE")?,
+            per_interface: bool_var(&var, "SYSMON_PER_INTERFACE")?,
+            no_network: bool_var(&var, "SYSMON_NO_NETWORK")?,
+            no_cpu: bool_var(&var, "SYSMON_NO_CPU")?,
+            no_memory: bool_var(&var, "SYSMON_NO_MEMORY")?,
+            no_disk: bool_var(&var, "SYSMON_NO_DISK")?,
+            no_temperatures: bool_var(&var, "SYSMON_NO_TEMPERATURES")?,
+            gpu: bool_var(&var, "SYSMON_GPU")?,
+            interfaces: var("SYSMON_INTERFACES").map(|list| split_list(&list)),
+            ignore_interfaces: var("SYSMON_IGNORE_INTERFACES"),
+            disks: var("SYSMON_DISKS").map(|list| split_list(&list)),
+            timestamp_format: var("SYSMON_TIMESTAMP_FORMAT"),
+            no_timestamp: bool_var(&var, "SYSMON_NO_TIMESTAMP")?,
+            align: bool_var(&var, "SYSMON_ALIGN")?,
+        };
+
+        // Strings are only parsed once applied; check them now so the error
+        // names the variable rather than a config key
+        if config.count == Some(0) {
+            return Err(var_error("SYSMON_COUNT", "0", "must be at least 1"));
+        }
+        if let Some(smooth) = config.smooth {
+            check_smooth(smooth).map_err(|err| var_error("SYSMON_SMOOTH", &smooth.to_string(), err))?;
+        }
+        for (name, threshold) in [("SYSMON_CPU_THRESHOLD", config.cpu_threshold), ("SYSMON_MEM_THRESHOLD", config.mem_threshold)] {
+            if let Some(percent) = threshold {
+                check_percent(percent).map_err(|err| var_error(name, &percent.to_string(), err))?;
+            }
+        }
+        if let Some(duration) = &config.duration {
+            parse_duration(duration).map_err(|err| var_error("SYSMON_DURATION", duration, err))?;
+        }
+        if let Some(window) = &config.window {
+            parse_duration(window).map_err(|err| var_error("SYSMON_WINDOW", window, err))?;
+        }
+        if let Some(pattern) = &config.ignore_interfaces {
+            parse_regex(pattern).map_err(|err| var_error("SYSMON_IGNORE_INTERFACES", pattern, err))?;
+        }
+        if let Some(format) = &config.format {
+            format.parse::<OutputFormat>().map_err(|err| var_error("SYSMON_FORMAT", format, err))?;
+        }
+        if let Some(timestamp_format) = &config.timestamp_format {
+            timestamp_format
+                .parse::<TimestampFormat>()
+                .map_err(|err| var_error("SYSMON_TIMESTAMP_FORMAT", timestamp_format, err))?;
+        }
+
+        Ok(config)
+    }
+
+    /// Keys set here win; the rest come from `fallback`
+    pub fn or(self, fallback: Config) -> Config {
+        Config {
+            interval: self.interval.or(fallback.interval),
+            count: self.count.or(fallback.count),
+            duration: self.duration.or(fallback.duration),
+            format: self.format.or(fallback.format),
+            output: self.output.or(fallback.output),
+            table: self.table.or(fallback.table),
+            smooth: self.smooth.or(fallback.smooth),
+            window: self.window.or(fallback.window),
+            diff: self.diff.or(fallback.diff),
+            cpu_threshold: self.cpu_threshold.or(fallback.cpu_threshold),
+            mem_threshold: self.mem_threshold.or(fallback.mem_threshold),
+            fields: self.fields.or(fallback.fields),
+            units: self.units.or(fallback.units),
+            net_unit: self.net_unit.or(fallback.net_unit),
+            mem_basis: self.mem_basis.or(fallback.mem_basis),
+            precision: self.precision.or(fallback.precision),
+            color: self.color.or(fallback.color),
+            top: self.top.or(fallback.top),
+            per_core: self.per_core.or(fallback.per_core),
+            per_disk: self.per_disk.or(fallback.per_disk),
+            hide_idle: self.hide_idle.or(fallback.hide_idle),
+            per_interface: self.per_interface.or(fallback.per_interface),
+            no_network: self.no_network.or(fallback.no_network),
+            no_cpu: self.no_cpu.or(fallback.no_cpu),
+            no_memory: self.no_memory.or(fallback.no_memory),
+            no_disk: self.no_disk.or(fallback.no_disk),
+            no_temperatures: self.no_temperatures.or(fallback.no_temperatures),
+            gpu: self.gpu.or(fallback.gpu),
+            interfaces: self.interfaces.or(fallback.interfaces),
+            ignore_interfaces: self.ignore_interfaces.or(fallback.ignore_interfaces),
+            disks: self.disks.or(fallback.disks),
+            timestamp_format: self.timestamp_format.or(fallback.timestamp_format),
+            no_timestamp: self.no_timestamp.or(fallback.no_timestamp),
+            align: self.align.or(fallback.align),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.count == Some(0) {
+            return Err("key `count`: must be at least 1".to_string());
+        }
+        if let Some(smooth) = self.smooth {
+            check_smooth(smooth).map_err(|err| key_error("smooth", err))?;
+        }
+        for (key, threshold) in [("cpu-threshold", self.cpu_threshold), ("mem-threshold", self.mem_threshold)] {
+            if let Some(percent) = threshold {
+                check_percent(percent).map_err(|err| key_error(key, err))?;
+            }
+        }
+        if let Some(pattern) = &self.ignore_interfaces {
+            parse_regex(pattern).map_err(|err| key_error("ignore-interfaces", err))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        // toml's errors quote the offending line, so they already name the key
+        let config: Config = toml::from_str(text).map_err(|err| err.to_string())?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn apply_schedule(&self, args: &mut ScheduleArgs, matches: &ArgMatches) -> Result<(), String> {
+        fill(&mut args.interval, self.interval, matches, "interval");
+        if let Some(count) = self.count
+            && is_default(matches, "count")
+        {
+            args.count = Some(count);
+        }
+        if let Some(duration) = &self.duration
+            && is_default(matches, "duration")
+        {
+            args.duration = Some(parse_duration(duration).map_err(|err| key_error("duration", err))?);
+        }
+
+        Ok(())
+    }
+
+    /// Fill the output format unless any of --format, --json, --ndjson, --csv, --raw or --columns was given
+    pub fn apply_format(&self, args: &mut FormatArgs, matches: &ArgMatches) -> Result<(), String> {
+        let explicit = ["format", "json", "ndjson", "csv", "raw", "columns"].iter().any(|id| !is_default(matches, id));
+        if let Some(format) = &self.format
+            && !explicit
+        {
+            args.format = parse_format(format)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fill `format` for commands that take a bare `--format`
+    pub fn apply_bare_format(&self, format: &mut OutputFormat, matches: &ArgMatches) -> Result<(), String> {
+        if let Some(value) = &self.format
+            && is_default(matches, "format")
+        {
+            *format = parse_format(value)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_output(&self, output: &mut Option<PathBuf>, matches: &ArgMatches) {
+        if output.is_none() && is_default(matches, "output") {
+            output.clone_from(&self.output);
+        }
+    }
+
+    pub fn apply_table(&self, table: &mut Option<TableStyle>, matches: &ArgMatches) {
+        if table.is_none() && is_default(matches, "table") {
+            *table = self.table;
+        }
+    }
+
+    /// Already checked by `validate`, so a bad value can't get this far
+    pub fn apply_smooth(&self, smooth: &mut Option<Smooth>, matches: &ArgMatches) {
+        if smooth.is_none() && is_default(matches, "smooth") {
+            *smooth = self.smooth.and_then(|value| check_smooth(value).ok());
+        }
+    }
+
+    pub fn apply_diff(&self, diff: &mut bool, matches: &ArgMatches) {
+        fill(diff, self.diff, matches, "diff");
+    }
+
+    pub fn apply_window(&self, window: &mut Option<Duration>, matches: &ArgMatches) -> Result<(), String> {
+        if let Some(value) = &self.window
+            && window.is_none()
+            && is_default(matches, "window")
+        {
+            *window = Some(parse_duration(value).map_err(|err| key_error("window", err))?);
+        }
+
+        Ok(())
+    }
+
+    /// Fill the live and log alert thresholds; `--exit-on-alert` stays a command line choice
+    pub fn apply_alerts(&self, args: &mut AlertArgs, matches: &ArgMatches) {
+        if args.cpu_threshold.is_none() && is_default(matches, "cpu_threshold") {
+            args.cpu_threshold = self.cpu_threshold;
+        }
+        if args.mem_threshold.is_none() && is_default(matches, "mem_threshold") {
+            args.mem_threshold = self.mem_threshold;
+        }
+    }
+
+    pub fn apply_display(&self, args: &mut DisplayArgs, matches: &ArgMatches) -> Result<(), String> {
+        if let Some(fields) = &self.fields
+            && is_default(matches, "fields")
+        {
+            args.fields.clone_from(fields);
+        }
+        // The shorthand flags count as an explicit choice too
+        if !args.binary_units {
+            fill(&mut args.units, self.units, matches, "units");
+        }
+        fill(&mut args.net_unit, self.net_unit, matches, "net_unit");
+        fill(&mut args.mem_basis, self.mem_basis, matches, "mem_basis");
+        if let Some(precision) = self.precision
+            && is_default(matches, "precision")
+        {
+            args.precision = Some(precision);
+        }
+        if !args.no_color {
+            fill(&mut args.color, self.color, matches, "color");
+        }
+        if let Some(top) = self.top
+            && is_default(matches, "top")
+        {
+            args.top = Some(top);
+        }
+        fill(&mut args.per_core, self.per_core, matches, "per_core");
+        fill(&mut args.per_disk, self.per_disk, matches, "per_disk");
+        fill(&mut args.hide_idle, self.hide_idle, matches, "hide_idle");
+        fill(&mut args.per_interface, self.per_interface, matches, "per_interface");
+        fill(&mut args.no_network, self.no_network, matches, "no_network");
+        fill(&mut args.no_cpu, self.no_cpu, matches, "no_cpu");
+        fill(&mut args.no_memory, self.no_memory, matches, "no_memory");
+        fill(&mut args.no_disk, self.no_disk, matches, "no_disk");
+        fill(&mut args.no_temperatures, self.no_temperatures, matches, "no_temperatures");
+        fill(&mut args.gpu, self.gpu, matches, "gpu");
+        if let Some(interfaces) = &self.interfaces
+            && is_default(matches, "interfaces")
+        {
+            args.interfaces = Some(interfaces.clone());
+        }
+        if let Some(pattern) = &self.ignore_interfaces
+            && is_default(matches, "ignore_interfaces")
+        {
+            args.ignore_interfaces = Some(parse_regex(pattern).map_err(|err| key_error("ignore-interfaces", err))?);
+        }
+        if let Some(disks) = &self.disks
+            && is_default(matches, "disks")
+        {
+            args.disks = Some(disks.clone());
+        }
+        if let Some(timestamp_format) = &self.timestamp_format
+            && is_default(matches, "timestamp_format")
+            && !args.no_timestamp
+        {
+            args.timestamp_format = timestamp_format
+                .parse::<TimestampFormat>()
+                .map_err(|err| key_error("timestamp-format", err))?;
+        }
+        if let Some(no_timestamp) = self.no_timestamp
+            && is_default(matches, "no_timestamp")
+            && is_default(matches, "timestamp_format")
+        {
+            args.no_timestamp = no_timestamp;
+        }
+        fill(&mut args.align, self.align, matches, "align");
+
+        Ok(())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/system-monitor/config.toml`, falling back to `~/.config`
+fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("system-monitor").join("config.toml"))
+}
+
+fn fill<T>(target: &mut T, value: Option<T>, matches: &ArgMatches, id: &str) {
+    if let Some(value) = value
+        && is_default(matches, id)
+    {
+        *target = value;
+    }
+}
+
+/// `interval = 5`, `interval = 0.5` or `interval = "500ms"`
+fn interval_value<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Seconds(f64),
+        Text(String),
+    }
+
+    let text = match Value::deserialize(deserializer)? {
+        Value::Seconds(seconds) => seconds.to_string(),
+        Value::Text(text) => text,
+    };
+    parse_duration(&text)
+        .map(Some)
+        .map_err(|err| serde::de::Error::custom(key_error("interval", err)))
+}
+
+fn parse_format(value: &str) -> Result<OutputFormat, String> {
+    value.parse().map_err(|err| key_error("format", err))
+}
+
+fn parse_var<T>(var: &dyn Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    var(name)
+        .map(|value| value.parse().map_err(|err| var_error(name, &value, err)))
+        .transpose()
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',').map(|item| item.trim().to_string()).collect()
+}
+
+fn enum_var<T: ValueEnum>(var: &dyn Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>, String> {
+    var(name).map(|value| parse_enum(name, &value)).transpose()
+}
+
+fn parse_enum<T: ValueEnum>(name: &str, value: &str) -> Result<T, String> {
+    let names: Vec<String> = T::value_variants()
+        .iter()
+        .filter_map(|variant| variant.to_possible_value())
+        .map(|possible| possible.get_name().to_string())
+        .collect();
+    T::from_str(value, true)
+        .map_err(|_| var_error(name, value, format!("expected one of {}", names.join(", "))))
+}
+
+fn bool_var(var: &dyn Fn(&str) -> Option<String>, name: &str) -> Result<Option<bool>, String> {
+    var(name)
+        .map(|value| match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(true),
+            "0" | "false" | "no" | "off" => Ok(false),
+            _ => Err(var_error(name, &value, "expected true or false")),
+        })
+        .transpose()
+}
+
+fn var_error(name: &str, value: &str, err: impl Display) -> String {
+    format!("invalid value '{}' for {}: {}", value, name, err)
+}
+
+fn key_error(key: &str, err: String) -> String {
+    format!("key `{}`: {}", key, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::{CliArgs, Command};
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn parse(argv: &[&str], config: &str) -> Command {
+        let matches = CliArgs::command().get_matches_from(argv);
+        let mut args = CliArgs::from_arg_matches(&matches).unwrap();
+        args.apply_config(&Config::parse(config).unwrap(), &matches).unwrap();
+        args.into_command()
+    }
+
+    #[test]
+    fn test_cli_flags_override_partial_config() {
+        let config = "interval = 5\nfields = [\"cpu\", \"mem\"]\nformat = \"json\"\n";
+
+        let Command::Log(args) = parse(&["system-monitor", "log", "--interval", "2"], config) else {
+            panic!("expected the log command");
+        };
+        assert_eq!(args.schedule.interval, Duration::from_secs(2));
+        assert_eq!(args.display.fields, vec![Field::Cpu, Field::Mem]);
+        assert_eq!(args.format.output_format(), OutputFormat::Json);
+        assert_eq!(args.schedule.count, None);
+
+        let thresholds = "cpu-threshold = 90\nmem-threshold = 75.5\n";
+        let Command::Live(args) = parse(&["system-monitor", "live", "--cpu-threshold", "50"], thresholds) else {
+            panic!("expected the live command");
+        };
+        assert_eq!(args.alerts.cpu_threshold, Some(50.0));
+        assert_eq!(args.alerts.mem_threshold, Some(75.5));
+
+        let Command::Live(args) = parse(&["system-monitor", "live"], "window = \"5s\"\n") else {
+            panic!("expected the live command");
+        };
+        assert_eq!(args.window, Some(Duration::from_secs(5)));
+
+        let Command::Live(args) = parse(&["system-monitor", "live"], "smooth = 5\n") else {
+            panic!("expected the live command");
+        };
+        assert_eq!(args.smooth, Some(Smooth::Rolling(5)));
+
+        let Command::Live(args) = parse(&["system-monitor", "live"], "diff = true\n") else {
+            panic!("expected the live command");
+        };
+        assert!(args.diff);
+
+        let Command::Snapshot(args) = parse(&["system-monitor", "--csv"], config) else {
+            panic!("expected the default snapshot command");
+        };
+        assert_eq!(args.interval, Duration::from_secs(5));
+        assert_eq!(args.format.output_format(), OutputFormat::Csv);
+    }
+
+    #[test]
+    fn test_env_overrides_file_but_not_flags() {
+        let env = Config::from_vars(|name| match name {
+            "SYSMON_INTERVAL" => Some("3".to_string()),
+            "SYSMON_FIELDS" => Some("net,disk".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let file = Config::parse("interval = 5\nunits = \"binary\"\n").unwrap();
+        let config = env.or(file);
+
+        let matches = CliArgs::command().get_matches_from(["system-monitor", "log", "--fields", "cpu"]);
+        let mut args = CliArgs::from_arg_matches(&matches).unwrap();
+        args.apply_config(&config, &matches).unwrap();
+        let Command::Log(args) = args.into_command() else {
+            panic!("expected the log command");
+        };
+
+        assert_eq!(args.schedule.interval, Duration::from_secs(3));
+        assert_eq!(args.display.units, Units::Binary);
+        assert_eq!(args.display.fields, vec![Field::Cpu]);
+    }
+
+    #[test]
+    fn test_env_errors_name_the_variable() {
+        let lookup = |value: &'static str| move |name: &str| (name == "SYSMON_INTERVAL").then(|| value.to_string());
+
+        assert!(Config::from_vars(lookup("abc")).unwrap_err().contains("SYSMON_INTERVAL"));
+        assert!(Config::from_vars(lookup("0")).unwrap_err().contains("SYSMON_INTERVAL"));
+        let units = Config::from_vars(|name| (name == "SYSMON_UNITS").then(|| "octal".to_string()));
+        assert!(units.unwrap_err().contains("SYSMON_UNITS"));
+        let smooth = Config::from_vars(|name| (name == "SYSMON_SMOOTH").then(|| "0".to_string()));
+        assert!(smooth.unwrap_err().contains("SYSMON_SMOOTH"));
+        let ignore = Config::from_vars(|name| (name == "SYSMON_IGNORE_INTERFACES").then(|| "[".to_string()));
+        assert!(ignore.unwrap_err().contains("SYSMON_IGNORE_INTERFACES"));
+    }
+
+    #[test]
+    fn test_env_help_lists_every_variable() {
+        let read = std::cell::RefCell::new(Vec::new());
+        Config::from_vars(|name| {
+            read.borrow_mut().push(name.to_string());
+            None
+        })
+        .unwrap();
+        let help = crate::args::ENV_HELP.replace(['\n', ',', '.'], " ");
+        // Names only, not the "SYSMON_*" in the prose
+        let listed: Vec<&str> = help
+            .split_whitespace()
+            .filter(|word| word.starts_with("SYSMON_") && word.chars().all(|c| c.is_ascii_uppercase() || c == '_'))
+            .collect();
+
+        let read = read.into_inner();
+        for name in &read {
+            assert!(listed.contains(&name.as_str()), "--help doesn't list {}", name);
+        }
+        // SYSMON_CONFIG is read before the file it names
+        for name in listed.iter().filter(|&&name| name != "SYSMON_CONFIG") {
+            assert!(read.iter().any(|read| read == name), "--help lists {}, which isn't read", name);
+        }
+    }
+
+    #[test]
+    fn test_errors_name_the_key() {
+        let unknown = Config::parse("intervall = 5").unwrap_err();
+        assert!(unknown.contains("intervall"), "{}", unknown);
+
+        let wrong_type = Config::parse("interval = \"fast\"").unwrap_err();
+        assert!(wrong_type.contains("interval"), "{}", wrong_type);
+
+        assert!(Config::parse("units = \"octal\"").is_err());
+        assert!(Config::parse("interval = 0").unwrap_err().contains("`interval`"));
+        assert!(Config::parse("interval = -1").unwrap_err().contains("`interval`"));
+        assert!(Config::parse("smooth = 1.5").unwrap_err().contains("`smooth`"));
+        assert!(Config::parse("smooth = 2.5").unwrap_err().contains("`smooth`"));
+        assert!(Config::parse("mem-threshold = 120").unwrap_err().contains("`mem-threshold`"));
+        assert!(Config::parse("ignore-interfaces = \"^(veth\"").unwrap_err().contains("`ignore-interfaces`"));
+    }
+
+    #[test]
+    fn test_interval_accepts_numbers_and_suffixes() {
+        let interval = |text: &str| Config::parse(text).unwrap().interval;
+
+        assert_eq!(interval("interval = 2"), Some(Duration::from_secs(2)));
+        assert_eq!(interval("interval = 0.25"), Some(Duration::from_millis(250)));
+        assert_eq!(interval("interval = \"500ms\""), Some(Duration::from_millis(500)));
+        assert_eq!(interval("interval = \"1m\""), Some(Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,132 @@
+use serde::Serialize;
+use sysinfo::{Components, Disks, Networks};
+
+use crate::format_bytes;
+use crate::render::columns;
+
+/// A network interface, as `--interfaces` names it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Interface {
+    pub name: String,
+    /// "00:00:00:00:00:00" for interfaces without one, like loopback
+    pub mac_address: String,
+    /// 0 where the OS doesn't report it
+    pub mtu: u64,
+}
+
+/// A mounted disk, as `--disks` matches it by name or mount point
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Disk {
+    pub name: String,
+    pub mount_point: String,
+    pub file_system: String,
+    pub total_bytes: u64,
+}
+
+/// A temperature sensor, labelled the way the Temperatures section shows it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sensor {
+    pub label: String,
+    /// Current reading, `None` when the sensor can't be read right now
+    pub temperature_celsius: Option<f32>,
+    pub critical_celsius: Option<f32>,
+}
+
+/// Every network interface, by name
+pub fn interfaces() -> Vec<Interface> {
+    let mut interfaces: Vec<Interface> = Networks::new_with_refreshed_list()
+        .iter()
+        .map(|(name, data)| Interface {
+            name: name.clone(),
+            mac_address: data.mac_address().to_string(),
+            mtu: data.mtu(),
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
+}
+
+/// Every mounted disk, by mount point
+pub fn disks() -> Vec<Disk> {
+    let mut disks: Vec<Disk> = Disks::new_with_refreshed_list()
+        .iter()
+        .map(|disk| Disk {
+            name: disk.name().to_string_lossy().into_owned(),
+            mount_point: disk.mount_point().display().to_string(),
+            file_system: disk.file_system().to_string_lossy().into_owned(),
+            total_bytes: disk.total_space(),
+        })
+        .collect();
+    disks.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+    disks
+}
+
+/// Every temperature sensor, by label
+pub fn sensors() -> Vec<Sensor> {
+    let mut sensors: Vec<Sensor> = Components::new_with_refreshed_list()
+        .iter()
+        .map(|component| Sensor {
+            label: component.label().to_string(),
+            temperature_celsius: component.temperature(),
+            critical_celsius: component.critical(),
+        })
+        .collect();
+    sensors.sort_by(|a, b| a.label.cmp(&b.label));
+    sensors
+}
+
+/// Interfaces as an aligned table with a header row
+pub fn interface_table(interfaces: &[Interface]) -> String {
+    let rows = interfaces
+        .iter()
+        .map(|interface| vec![interface.name.clone(), interface.mac_address.clone(), interface.mtu.to_string()]);
+    columns(&["NAME", "MAC", "MTU"], rows)
+}
+
+/// Disks as an aligned table with a header row
+pub fn disk_table(disks: &[Disk]) -> String {
+    let rows = disks.iter().map(|disk| {
+        vec![disk.mount_point.clone(), disk.name.clone(), disk.file_system.clone(), format_bytes(disk.total_bytes)]
+    });
+    columns(&["MOUNT", "NAME", "FS", "SIZE"], rows)
+}
+
+/// Sensors as an aligned table with a header row; unreadable values show as "-"
+pub fn sensor_table(sensors: &[Sensor]) -> String {
+    let celsius = |value: Option<f32>| value.map_or_else(|| "-".to_string(), |value| format!("{:.1}°C", value));
+    let rows = sensors
+        .iter()
+        .map(|sensor| vec![sensor.label.clone(), celsius(sensor.temperature_celsius), celsius(sensor.critical_celsius)]);
+    columns(&["LABEL", "TEMP", "CRITICAL"], rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_line_up() {
+        let disks = [
+            Disk { name: "/dev/sda1".to_string(), mount_point: "/".to_string(), file_system: "ext4".to_string(), total_bytes: 500_000_000_000 },
+            Disk { name: "tmpfs".to_string(), mount_point: "/run/user/1000".to_string(), file_system: "tmpfs".to_string(), total_bytes: 1_600_000_000 },
+        ];
+        assert_eq!(
+            disk_table(&disks),
+            "MOUNT           NAME       FS     SIZE\n\
+             /               /dev/sda1  ext4   500 GB\n\
+             /run/user/1000  tmpfs      tmpfs  1.60 GB\n"
+        );
+
+        let sensors = [Sensor { label: "acpitz temp1".to_string(), temperature_celsius: Some(41.0), critical_celsius: None }];
+        assert_eq!(sensor_table(&sensors), "LABEL         TEMP    CRITICAL\nacpitz temp1  41.0°C  -\n");
+    }
+
+    #[test]
+    fn test_interfaces_are_listed_by_name() {
+        let interfaces = interfaces();
+        assert!(interfaces.windows(2).all(|pair| pair[0].name <= pair[1].name));
+        if cfg!(target_os = "linux") {
+            assert!(interfaces.iter().any(|interface| interface.name == "lo"), "{:?}", interfaces);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{Fields, MetricsSnapshot};
+
+/// Render `snapshot` as one InfluxDB line protocol point
+///
+/// For example `system,host=web1 cpu=12.3,mem_used=123456i,net_rx=789i
+/// 1714500000000000000`: byte counts are integers over the sampling window,
+/// the timestamp is in nanoseconds, and tags with an empty value are dropped
+/// since the protocol has no way to spell them. Only the groups in `fields`
+/// that the sample collected become fields.
+pub fn render(snapshot: &MetricsSnapshot, measurement: &str, tags: &[(String, String)], fields: &Fields) -> String {
+    let mut line = escape(measurement, &[',', ' ']);
+    for (key, value) in tags.iter().filter(|(_, value)| !value.is_empty()) {
+        line.push_str(&format!(",{}={}", escape_key(key), escape_key(value)));
+    }
+
+    let shown = snapshot.shown_fields(fields);
+    let mut values = Vec::new();
+    let mut integer = |key: &str, value: u64| values.push(format!("{}={}i", key, value));
+    if shown.mem {
+        integer("mem_used", snapshot.memory_used_bytes);
+        integer("mem_total", snapshot.memory_total_bytes);
+        integer("swap_used", snapshot.swap_used_bytes);
+        integer("swap_total", snapshot.swap_total_bytes);
+    }
+    if shown.disk {
+        integer("disk_read", snapshot.disk_read_bytes);
+        integer("disk_write", snapshot.disk_write_bytes);
+    }
+    if shown.net {
+        integer("net_rx", snapshot.net_rx_bytes.unwrap_or(0));
+        integer("net_tx", snapshot.net_tx_bytes.unwrap_or(0));
+    }
+    if shown.cpu {
+        values.insert(0, format!("cpu={}", snapshot.cpu_usage_percent));
+    }
+
+    let nanos = snapshot.timestamp.timestamp_nanos_opt().unwrap_or_default();
+    format!("{} {} {}", line, values.join(","), nanos)
+}
+
+/// Tag keys, tag values and field keys escape commas, equals signs and spaces
+fn escape_key(text: &str) -> String {
+    escape(text, &[',', '=', ' '])
+}
+
+fn escape(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Local};
+
+    use super::*;
+
+    #[test]
+    fn test_render_point() {
+        let snapshot = MetricsSnapshot {
+            timestamp: DateTime::from_timestamp(1_714_500_000, 0).unwrap().with_timezone(&Local),
+            cpu_usage_percent: 12.3,
+            memory_used_bytes: 123_456,
+            net_rx_bytes: Some(789),
+            net_tx_bytes: Some(0),
+            ..Default::default()
+        };
+        let tags = [("host".to_string(), "myhost".to_string())];
+
+        assert_eq!(
+            render(&snapshot, "system", &tags, &Fields::from_list(&[crate::Field::Cpu, crate::Field::Net])),
+            "system,host=myhost cpu=12.3,net_rx=789i,net_tx=0i 1714500000000000000"
+        );
+        assert!(render(&snapshot, "system", &tags, &Fields::all()).contains(",mem_used=123456i,"));
+    }
+
+    #[test]
+    fn test_tags_are_escaped() {
+        let tags = [("host".to_string(), "my host,eu=1".to_string()), ("role".to_string(), String::new())];
+        let line = render(&MetricsSnapshot::default(), "sys tem,x", &tags, &Fields::all());
+
+        assert!(line.starts_with("sys\\ tem\\,x,host=my\\ host\\,eu\\=1 cpu=0,"), "{}", line);
+        assert!(!line.contains("role"));
+    }
+}
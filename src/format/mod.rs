@@ -0,0 +1,4 @@
+//! Output formats for external tools
+pub mod influx;
+pub mod prometheus;
+pub mod statsd;
@@ -0,0 +1,279 @@
+use std::fmt::{Display, Write};
+
+use crate::MetricsSnapshot;
+
+/// `Content-Type` of [`render`]'s output when served over HTTP
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Render `snapshot` in the Prometheus text exposition format
+///
+/// Everything is a gauge. The byte figures are what moved during the
+/// sampling window rather than running totals, so they must not be fed to
+/// `rate()`; the `_per_second` gauges already are rates.
+pub fn render(snapshot: &MetricsSnapshot) -> String {
+    render_into(snapshot, None)
+}
+
+/// Render `snapshot` for a scrape, with the byte figures as `_total`
+/// counters from `totals` instead of per-window gauges
+///
+/// This is what a long-running exporter should serve: Prometheus expects
+/// monotonic counters it can apply `rate()` to, and a window's worth of
+/// bytes means nothing once scrapes and samples drift apart.
+pub fn render_scrape(snapshot: &MetricsSnapshot, totals: &Totals) -> String {
+    render_into(snapshot, Some(totals))
+}
+
+/// Bytes moved since an exporter started, summed over its samples
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub disk_read_bytes: u64,
+    pub disk_written_bytes: u64,
+    pub net_received_bytes: u64,
+    pub net_transmitted_bytes: u64,
+}
+
+impl Totals {
+    pub fn add(&mut self, snapshot: &MetricsSnapshot) {
+        self.disk_read_bytes += snapshot.disk_read_bytes;
+        self.disk_written_bytes += snapshot.disk_write_bytes;
+        self.net_received_bytes += snapshot.net_rx_bytes.unwrap_or(0);
+        self.net_transmitted_bytes += snapshot.net_tx_bytes.unwrap_or(0);
+    }
+}
+
+fn render_into(snapshot: &MetricsSnapshot, totals: Option<&Totals>) -> String {
+    let mut out = Exposition::default();
+
+    if snapshot.collected.cpu {
+        out.gauge("system_cpu_usage_percent", "Global CPU usage across all cores, 0-100", snapshot.cpu_usage_percent);
+        out.family("system_cpu_core_usage_percent", "Usage of each logical core, 0-100");
+        for (core, usage) in snapshot.per_core_usage.iter().enumerate() {
+            out.sample("system_cpu_core_usage_percent", &[("core", &core.to_string())], usage);
+        }
+        if let Some((one, five, fifteen)) = snapshot.load_average {
+            out.gauge("system_load1", "1 minute load average", one);
+            out.gauge("system_load5", "5 minute load average", five);
+            out.gauge("system_load15", "15 minute load average", fifteen);
+        }
+    }
+    if !snapshot.temperatures.is_empty() {
+        out.family("system_temperature_celsius", "Current sensor temperature in Celsius");
+        for (label, celsius) in &snapshot.temperatures {
+            out.sample("system_temperature_celsius", &[("sensor", label)], celsius);
+        }
+    }
+
+    if snapshot.collected.mem {
+        out.gauge("system_memory_used_bytes", "RAM in use", snapshot.memory_used_bytes);
+        out.gauge("system_memory_total_bytes", "Installed RAM", snapshot.memory_total_bytes);
+        out.gauge("system_swap_used_bytes", "Swap in use", snapshot.swap_used_bytes);
+        out.gauge("system_swap_total_bytes", "Configured swap", snapshot.swap_total_bytes);
+    }
+
+    if snapshot.collected.disk {
+        if let Some(totals) = totals {
+            out.counter("system_disk_read_bytes_total", "Bytes read from all disks", totals.disk_read_bytes);
+            out.counter("system_disk_written_bytes_total", "Bytes written to all disks", totals.disk_written_bytes);
+        } else {
+            out.gauge(
+                "system_disk_read_bytes",
+                "Bytes read from all disks during the sampling window, not a counter",
+                snapshot.disk_read_bytes,
+            );
+            out.gauge(
+                "system_disk_written_bytes",
+                "Bytes written to all disks during the sampling window, not a counter",
+                snapshot.disk_write_bytes,
+            );
+        }
+        out.gauge("system_disk_read_bytes_per_second", "Disk read throughput", snapshot.disk_read_per_sec);
+        out.gauge("system_disk_written_bytes_per_second", "Disk write throughput", snapshot.disk_write_per_sec);
+        if !snapshot.per_disk.is_empty() {
+            out.family("system_disk_available_bytes", "Free space per mount point");
+            for disk in &snapshot.per_disk {
+                out.sample(
+                    "system_disk_available_bytes",
+                    &[("mount_point", &disk.mount_point), ("device", &disk.name)],
+                    disk.available_bytes,
+                );
+            }
+            out.family("system_disk_total_bytes", "Size per mount point");
+            for disk in &snapshot.per_disk {
+                out.sample(
+                    "system_disk_total_bytes",
+                    &[("mount_point", &disk.mount_point), ("device", &disk.name)],
+                    disk.total_bytes,
+                );
+            }
+        }
+    }
+
+    if let Some(totals) = totals && snapshot.net_rx_bytes.is_some() {
+        out.counter("system_network_received_bytes_total", "Bytes received on all interfaces", totals.net_received_bytes);
+        out.counter(
+            "system_network_transmitted_bytes_total",
+            "Bytes sent on all interfaces",
+            totals.net_transmitted_bytes,
+        );
+    } else if totals.is_none() {
+        if let Some(rx_bytes) = snapshot.net_rx_bytes {
+            out.gauge(
+                "system_network_received_bytes",
+                "Bytes received on all interfaces during the sampling window, not a counter",
+                rx_bytes,
+            );
+        }
+        if let Some(tx_bytes) = snapshot.net_tx_bytes {
+            out.gauge(
+                "system_network_transmitted_bytes",
+                "Bytes sent on all interfaces during the sampling window, not a counter",
+                tx_bytes,
+            );
+        }
+    }
+    if let Some(rx_per_sec) = snapshot.net_rx_per_sec {
+        out.gauge("system_network_received_bytes_per_second", "Receive rate", rx_per_sec);
+    }
+    if let Some(tx_per_sec) = snapshot.net_tx_per_sec {
+        out.gauge("system_network_transmitted_bytes_per_second", "Transmit rate", tx_per_sec);
+    }
+    if !snapshot.per_interface.is_empty() {
+        out.family("system_network_interface_received_bytes_per_second", "Receive rate per interface");
+        for interface in &snapshot.per_interface {
+            out.sample(
+                "system_network_interface_received_bytes_per_second",
+                &[("interface", &interface.name)],
+                interface.rx_per_sec,
+            );
+        }
+        out.family("system_network_interface_transmitted_bytes_per_second", "Transmit rate per interface");
+        for interface in &snapshot.per_interface {
+            out.sample(
+                "system_network_interface_transmitted_bytes_per_second",
+                &[("interface", &interface.name)],
+                interface.tx_per_sec,
+            );
+        }
+    }
+
+    if let Some(gpu) = &snapshot.gpu {
+        out.gauge("system_gpu_utilization_percent", "Mean utilization of the NVIDIA GPUs, 0-100", gpu.utilization_percent);
+        out.gauge("system_gpu_memory_used_bytes", "VRAM in use on all NVIDIA GPUs", gpu.memory_used_bytes);
+        out.gauge("system_gpu_memory_total_bytes", "VRAM on all NVIDIA GPUs", gpu.memory_total_bytes);
+    }
+
+    out.gauge("system_uptime_seconds", "Time since the system booted", snapshot.uptime_secs);
+    out.gauge("system_boot_time_seconds", "Boot time in seconds since the Unix epoch", snapshot.boot_time_secs);
+
+    out.text
+}
+
+#[derive(Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    /// `# HELP` and `# TYPE` lines for a gauge family
+    fn family(&mut self, name: &str, help: &str) {
+        self.typed_family(name, help, "gauge");
+    }
+
+    fn typed_family(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.text, "# HELP {} {}", name, help);
+        let _ = writeln!(self.text, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        if labels.is_empty() {
+            let _ = writeln!(self.text, "{} {}", name, value);
+            return;
+        }
+
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+            .collect();
+        let _ = writeln!(self.text, "{}{{{}}} {}", name, labels.join(","), value);
+    }
+
+    fn gauge(&mut self, name: &str, help: &str, value: impl Display) {
+        self.family(name, help);
+        self.sample(name, &[], value);
+    }
+
+    fn counter(&mut self, name: &str, help: &str, value: impl Display) {
+        self.typed_family(name, help, "counter");
+        self.sample(name, &[], value);
+    }
+}
+
+/// Escape a label value as the exposition format requires
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_gauges() {
+        let snapshot = MetricsSnapshot {
+            cpu_usage_percent: 12.3,
+            per_core_usage: vec![10.0, 14.5],
+            memory_used_bytes: 123_456,
+            uptime_secs: 3600,
+            ..Default::default()
+        };
+        let text = render(&snapshot);
+
+        assert!(text.starts_with(
+            "# HELP system_cpu_usage_percent Global CPU usage across all cores, 0-100\n\
+             # TYPE system_cpu_usage_percent gauge\n\
+             system_cpu_usage_percent 12.3\n"
+        ));
+        assert!(text.contains("system_cpu_core_usage_percent{core=\"1\"} 14.5\n"));
+        assert!(text.contains("system_memory_used_bytes 123456\n"));
+        assert!(text.contains("system_uptime_seconds 3600\n"));
+        assert!(!text.contains("system_load1"));
+        assert!(!text.contains("system_temperature_celsius"));
+        assert!(!text.contains("system_network_received_bytes"));
+    }
+
+    #[test]
+    fn test_scrapes_count_bytes_since_start() {
+        let sample = MetricsSnapshot {
+            disk_read_bytes: 100,
+            net_rx_bytes: Some(40),
+            net_tx_bytes: Some(2),
+            net_rx_per_sec: Some(40),
+            ..Default::default()
+        };
+        let mut totals = Totals::default();
+        totals.add(&sample);
+        totals.add(&sample);
+        let text = render_scrape(&sample, &totals);
+
+        assert!(text.contains("# TYPE system_network_received_bytes_total counter
+system_network_received_bytes_total 80
+"));
+        assert!(text.contains("system_disk_read_bytes_total 200
+"));
+        assert!(text.contains("system_network_received_bytes_per_second 40
+"));
+        assert!(!text.contains("system_network_received_bytes "));
+        assert!(!text.contains("system_disk_read_bytes "));
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        let snapshot = MetricsSnapshot {
+            temperatures: vec![("acpi \"zone\"\\0".to_string(), 40.0)],
+            ..Default::default()
+        };
+
+        assert!(render(&snapshot).contains("system_temperature_celsius{sensor=\"acpi \\\"zone\\\"\\\\0\"} 40\n"));
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt::Display;
+
+use crate::MetricsSnapshot;
+
+/// Render `snapshot` as StatsD gauges, one `name:value|g` line per metric
+///
+/// Names are `prefix` followed by the metric, e.g. `system.cpu.usage`. Tags
+/// such as `env:prod` are appended in the DogStatsD `|#tag,tag` extension and
+/// left off entirely when `tags` is empty. Groups the sample didn't collect
+/// are skipped rather than reported as zeros.
+pub fn render(snapshot: &MetricsSnapshot, prefix: &str, tags: &[String]) -> Vec<String> {
+    let suffix = if tags.is_empty() { String::new() } else { format!("|#{}", tags.join(",")) };
+    let mut lines = Vec::new();
+    let mut gauge = |name: &str, value: &dyn Display| {
+        lines.push(format!("{}.{}:{}|g{}", prefix, name, value, suffix));
+    };
+
+    if snapshot.collected.cpu {
+        gauge("cpu.usage", &snapshot.cpu_usage_percent);
+        if let Some((one, five, fifteen)) = snapshot.load_average {
+            gauge("load.1", &one);
+            gauge("load.5", &five);
+            gauge("load.15", &fifteen);
+        }
+    }
+    if snapshot.collected.mem {
+        gauge("mem.used", &snapshot.memory_used_bytes);
+        gauge("mem.total", &snapshot.memory_total_bytes);
+        gauge("swap.used", &snapshot.swap_used_bytes);
+        gauge("swap.total", &snapshot.swap_total_bytes);
+    }
+    if snapshot.collected.disk {
+        gauge("disk.read_bytes", &snapshot.disk_read_bytes);
+        gauge("disk.write_bytes", &snapshot.disk_write_bytes);
+        gauge("disk.read_per_sec", &snapshot.disk_read_per_sec);
+        gauge("disk.write_per_sec", &snapshot.disk_write_per_sec);
+    }
+    if let (Some(rx_bytes), Some(tx_bytes)) = (snapshot.net_rx_bytes, snapshot.net_tx_bytes) {
+        gauge("net.rx_bytes", &rx_bytes);
+        gauge("net.tx_bytes", &tx_bytes);
+    }
+    if let (Some(rx_per_sec), Some(tx_per_sec)) = (snapshot.net_rx_per_sec, snapshot.net_tx_per_sec) {
+        gauge("net.rx_per_sec", &rx_per_sec);
+        gauge("net.tx_per_sec", &tx_per_sec);
+    }
+    gauge("uptime", &snapshot.uptime_secs);
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fields;
+
+    #[test]
+    fn test_render_gauges() {
+        let snapshot = MetricsSnapshot {
+            cpu_usage_percent: 12.5,
+            memory_used_bytes: 4_096,
+            net_rx_bytes: Some(789),
+            net_tx_bytes: Some(10),
+            ..Default::default()
+        };
+        let lines = render(&snapshot, "system", &[]);
+
+        assert_eq!(lines[0], "system.cpu.usage:12.5|g");
+        assert!(lines.contains(&"system.mem.used:4096|g".to_string()));
+        assert!(lines.contains(&"system.net.rx_bytes:789|g".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("net.rx_per_sec")));
+    }
+
+    #[test]
+    fn test_tags_and_skipped_groups() {
+        let snapshot = MetricsSnapshot {
+            collected: Fields { cpu: true, mem: false, disk: false, net: false },
+            cpu_usage_percent: 50.0,
+            ..Default::default()
+        };
+        let lines = render(&snapshot, "host.web1", &["env:prod".to_string(), "role:web".to_string()]);
+
+        assert_eq!(lines, ["host.web1.cpu.usage:50|g|#env:prod,role:web", "host.web1.uptime:0|g|#env:prod,role:web"]);
+    }
+}
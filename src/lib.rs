@@ -1,134 +1,1607 @@
 use std::{error::Error, fmt};
+use std::str::FromStr;
+use std::io::{self, Write};
 use std::time::Duration;
-use sysinfo::{Disks, System, Networks};
+use chrono::format::StrftimeItems;
+use chrono::{DateTime, Local, SecondsFormat};
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+use crate::color::{Breakpoints, Color, paint};
+pub use crate::monitor::Monitor;
+use crate::render::TableStyle;
+use crate::template::Template;
+
+pub mod color;
+pub mod devices;
+pub mod format;
+pub mod monitor;
+pub mod rate;
+pub mod record;
+pub mod render;
+pub mod summary;
+pub mod template;
+pub mod threshold;
+
+/// Version of the JSON keys and CSV columns samples are written with
+///
+/// Bump it whenever a key or column is renamed, removed or changes meaning,
+/// so consumers can tell the layouts apart. Adding keys doesn't need a bump.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Rows between repeated [`OutputFormat::Columns`] headers unless `--header-every` says otherwise
+pub const HEADER_EVERY: usize = 20;
+
+/// How a snapshot is rendered on output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Text,
+    /// The `key=value` line log mode prints for text, for one-shot samples too
+    Line,
+    /// One JSON object per sample with raw values
+    Json,
+    /// Like JSON, but always one compact object per line, even for a single sample
+    Ndjson,
+    /// Comma-separated raw values behind a single header row
+    Csv,
+    /// Tab-separated raw values without units, timestamp or header
+    Raw,
+    /// Prometheus text exposition, all metrics as gauges
+    Prometheus,
+    /// InfluxDB line protocol, one point per sample tagged with the host name
+    Influx,
+    /// Fixed-width columns like `vmstat`, the header repeated every `header_every` rows (0: only once)
+    Columns { header_every: usize },
+    /// A user supplied line with `{placeholder}` substitution
+    Template(Template),
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    /// Accepts a format name, or anything containing `{` as a template
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "text" => Ok(OutputFormat::Text),
+            "line" => Ok(OutputFormat::Line),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "csv" => Ok(OutputFormat::Csv),
+            "raw" => Ok(OutputFormat::Raw),
+            "prometheus" => Ok(OutputFormat::Prometheus),
+            "influx" => Ok(OutputFormat::Influx),
+            "columns" => Ok(OutputFormat::Columns { header_every: HEADER_EVERY }),
+            template if template.contains('{') => Template::parse(template)
+                .map(OutputFormat::Template)
+                .map_err(|err| err.to_string()),
+            other => Err(format!(
+                "unknown format '{}', expected text, line, json, ndjson, csv, raw, prometheus, influx, columns or a template like \"cpu={{cpu}}\"",
+                other
+            )),
+        }
+    }
+}
+
+/// A group of related metrics that can be selected with `--fields`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Cpu,
+    Mem,
+    Disk,
+    Net,
+}
+
+impl Field {
+    /// The group a serialized snapshot key belongs to, `None` for bookkeeping keys
+    fn of_key(key: &str) -> Option<Field> {
+        match key {
+            "cpu_usage_percent" | "per_core_usage" | "load_average" | "temperatures" | "process_count"
+            | "thread_count" => Some(Field::Cpu),
+            "memory_used_bytes" | "memory_total_bytes" | "memory_available_bytes" | "memory_free_bytes"
+            | "swap_used_bytes" | "swap_total_bytes" => {
+                Some(Field::Mem)
+            }
+            "disk_read_bytes" | "disk_write_bytes" | "disk_read_per_sec" | "disk_write_per_sec"
+            | "per_disk" => Some(Field::Disk),
+            "net_rx_bytes" | "net_tx_bytes" | "net_rx_per_sec" | "net_tx_per_sec"
+            | "per_interface" => Some(Field::Net),
+            _ => None,
+        }
+    }
+}
+
+/// Which metric groups are shown; every group is on by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Fields {
+    pub cpu: bool,
+    pub mem: bool,
+    pub disk: bool,
+    pub net: bool,
+}
+
+impl Fields {
+    pub fn all() -> Self {
+        Fields { cpu: true, mem: true, disk: true, net: true }
+    }
+
+    pub fn from_list(list: &[Field]) -> Self {
+        Fields {
+            cpu: list.contains(&Field::Cpu),
+            mem: list.contains(&Field::Mem),
+            disk: list.contains(&Field::Disk),
+            net: list.contains(&Field::Net),
+        }
+    }
+
+    /// The groups that are on, in `--fields` order
+    pub fn to_list(&self) -> Vec<Field> {
+        [Field::Cpu, Field::Mem, Field::Disk, Field::Net].into_iter().filter(|&field| self.contains(field)).collect()
+    }
+
+    pub fn contains(&self, field: Field) -> bool {
+        match field {
+            Field::Cpu => self.cpu,
+            Field::Mem => self.mem,
+            Field::Disk => self.disk,
+            Field::Net => self.net,
+        }
+    }
+}
+
+impl Default for Fields {
+    fn default() -> Self {
+        Fields::all()
+    }
+}
+
+/// Unit system for human readable byte values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// Powers of 1000: KB, MB, GB
+    #[default]
+    #[value(alias = "decimal")]
+    #[serde(alias = "decimal")]
+    Si,
+    /// Powers of 1024: KiB, MiB, GiB
+    Binary,
+    /// Powers of 1000, with every rate in bits per second: Mbps, Gbps
+    Bits,
+}
+
+impl Units {
+    pub fn format_bytes(&self, bytes: u64) -> String {
+        self.format_bytes_with(bytes, None)
+    }
+
+    pub fn format_speed(&self, bytes_per_sec: u64) -> String {
+        self.format_speed_with(bytes_per_sec, None)
+    }
+
+    /// Format with a fixed number of decimals, or adaptively when `None`
+    pub fn format_bytes_with(&self, bytes: u64, precision: Option<usize>) -> String {
+        match self {
+            Units::Si | Units::Bits => format_scaled(bytes, 1000, SI_UNITS, precision),
+            Units::Binary => format_scaled(bytes, 1024, BINARY_UNITS, precision),
+        }
+    }
+
+    /// A rate; [`Units::Bits`] only changes these, never amounts like memory
+    pub fn format_speed_with(&self, bytes_per_sec: u64, precision: Option<usize>) -> String {
+        match self {
+            Units::Bits => format_scaled(bytes_per_sec.saturating_mul(8), 1000, BIT_UNITS, precision),
+            _ => format!("{}/s", self.format_bytes_with(bytes_per_sec, precision)),
+        }
+    }
+}
+
+/// How network rates are shown in human readable output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetUnit {
+    /// Bytes per second in the chosen [`Units`]: KB/s, MB/s
+    #[default]
+    Bytes,
+    /// Bits per second in powers of 1000, the way links are rated: Mbps, Gbps
+    Bits,
+}
+
+/// What the memory percentage counts as in use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemBasis {
+    /// Used over total, the way sysinfo reports it
+    #[default]
+    Used,
+    /// Everything but the available memory, so reclaimable cache doesn't count
+    Available,
+}
+
+/// How sample timestamps are written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// Local time as RFC3339 with second precision
+    #[default]
+    Rfc3339,
+    /// Seconds since the Unix epoch
+    Unix,
+    /// Milliseconds since the Unix epoch
+    UnixMs,
+    /// A chrono strftime pattern such as `%H:%M:%S`
+    Strftime(String),
+}
+
+impl FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            "unix" => Ok(TimestampFormat::Unix),
+            "unix-ms" => Ok(TimestampFormat::UnixMs),
+            pattern if pattern.contains('%') => match StrftimeItems::new(pattern).parse() {
+                Ok(_) => Ok(TimestampFormat::Strftime(pattern.to_string())),
+                Err(_) => Err(format!("invalid strftime pattern '{}'", pattern)),
+            },
+            other => Err(format!(
+                "unknown timestamp format '{}', expected rfc3339, unix, unix-ms or a strftime pattern",
+                other
+            )),
+        }
+    }
+}
+
+impl TimestampFormat {
+    pub fn format(&self, timestamp: &DateTime<Local>) -> String {
+        match self {
+            TimestampFormat::Rfc3339 => timestamp.to_rfc3339_opts(SecondsFormat::Secs, false),
+            TimestampFormat::Unix => timestamp.timestamp().to_string(),
+            TimestampFormat::UnixMs => timestamp.timestamp_millis().to_string(),
+            TimestampFormat::Strftime(pattern) => timestamp.format(pattern).to_string(),
+        }
+    }
+
+    /// Epoch formats become JSON numbers, everything else a string
+    fn to_json(&self, timestamp: &DateTime<Local>) -> serde_json::Value {
+        match self {
+            TimestampFormat::Unix => timestamp.timestamp().into(),
+            TimestampFormat::UnixMs => timestamp.timestamp_millis().into(),
+            _ => self.format(timestamp).into(),
+        }
+    }
+}
+
+/// Presentation settings shared by every output format
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub fields: Fields,
+    pub units: Units,
+    /// Color usage percentages in text output; structured formats ignore it
+    pub color: bool,
+    /// Timestamp style for every format that records one; `None` leaves it out
+    pub timestamp: Option<TimestampFormat>,
+    /// Draw text output as a bordered table instead of indented lines
+    pub table: Option<TableStyle>,
+    /// Fixed decimals for byte values instead of ~3 significant digits
+    pub precision: Option<usize>,
+    /// List each core's usage in text output; structured formats always carry it
+    pub per_core: bool,
+    /// Terminal width to wrap the per-core list at; `None` means 80 columns
+    pub width: Option<usize>,
+    /// Network rates in bytes or bits per second; other speeds are always bytes
+    pub net_unit: NetUnit,
+    /// Add the watched process's columns to CSV; other formats show it
+    /// whenever it was collected
+    pub process: bool,
+    /// Right-align `key=value` line values in fixed-width fields; see [`FormattedMetrics::fmt_aligned_line`]
+    pub align: bool,
+    /// What the shown memory percentage and its color are based on
+    pub mem_basis: MemBasis,
+    /// Where CPU usage, per core too, turns yellow and red when [`color`](Self::color) is on
+    pub cpu_colors: Breakpoints,
+    /// Where the memory percentage turns yellow and red
+    pub mem_colors: Breakpoints,
+}
+
+impl FormatOptions {
+    /// A network rate in [`FormatOptions::net_unit`]
+    pub fn format_net_speed(&self, bytes_per_sec: u64) -> String {
+        match self.net_unit {
+            NetUnit::Bytes => self.units.format_speed_with(bytes_per_sec, self.precision),
+            NetUnit::Bits => format_scaled(bytes_per_sec.saturating_mul(8), 1000, BIT_UNITS, self.precision),
+        }
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            fields: Fields::default(),
+            units: Units::default(),
+            color: false,
+            timestamp: Some(TimestampFormat::default()),
+            table: None,
+            precision: None,
+            per_core: false,
+            width: None,
+            net_unit: NetUnit::default(),
+            process: false,
+            align: false,
+            mem_basis: MemBasis::default(),
+            cpu_colors: Breakpoints::default(),
+            mem_colors: Breakpoints::default(),
+        }
+    }
+}
+
+/// One sample of system metrics
+///
+/// Field names are part of the JSON output and must stay stable.
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 pub struct MetricsSnapshot {
+    /// When the sampling window ended, serialized as RFC3339
+    pub timestamp: DateTime<Local>,
+    /// Metric groups this sample collected; the figures of any other group are zeros
+    #[serde(skip)]
+    pub collected: Fields,
+    /// Measured length of the sampling window; every rate is relative to it
+    ///
+    /// Serialized as whole milliseconds under `elapsed_ms`, so drift from the
+    /// requested interval shows in the output.
+    #[serde(rename = "elapsed_ms", with = "millis")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub elapsed: Duration,
+    /// Seconds since the system booted
+    pub uptime_secs: u64,
+    /// When the system booted, in seconds since the Unix epoch
+    pub boot_time_secs: u64,
+    /// Global CPU usage across all cores, 0-100
     pub cpu_usage_percent: f32,
+    /// Usage of each logical core in sysinfo's order, 0-100
+    pub per_core_usage: Vec<f32>,
+    /// 1, 5 and 15 minute load averages; `None` where the platform has none (Windows)
+    pub load_average: Option<(f64, f64, f64)>,
+    /// Sensor label and current temperature in Celsius; empty when no sensors are exposed
+    pub temperatures: Vec<(String, f32)>,
+    /// Processes running, kernel threads included but not the threads of user processes
+    pub process_count: usize,
+    /// Threads across all processes; `None` where sysinfo can't list them (only Linux can)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_count: Option<usize>,
+    /// RAM in use, in bytes
+    pub memory_used_bytes: u64,
+    /// Installed RAM, in bytes
+    pub memory_total_bytes: u64,
+    /// RAM that can be handed out without swapping, reclaimable cache included
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub memory_available_bytes: u64,
+    /// RAM not used for anything, not even cache
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub memory_free_bytes: u64,
+    /// Swap in use, in bytes
+    pub swap_used_bytes: u64,
+    /// Configured swap, in bytes; zero when swap is disabled
+    pub swap_total_bytes: u64,
+    /// Bytes read from all disks during the sampling window, counting each
+    /// device once however many mounts it has
+    pub disk_read_bytes: u64,
+    /// Bytes written to all disks during the sampling window
+    pub disk_write_bytes: u64,
+    /// Read throughput over the measured sampling window, in bytes per second
+    pub disk_read_per_sec: u64,
+    /// Write throughput over the measured sampling window, in bytes per second
+    pub disk_write_per_sec: u64,
+    /// Bytes received on all interfaces during the sampling window; `None`
+    /// when network collection was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub net_rx_bytes: Option<u64>,
+    /// Bytes sent on all interfaces during the sampling window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub net_tx_bytes: Option<u64>,
+    /// Receive rate over the measured sampling window, in bytes per second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub net_rx_per_sec: Option<u64>,
+    /// Transmit rate over the measured sampling window, in bytes per second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub net_tx_per_sec: Option<u64>,
+    /// Breakdown of the disk totals, only collected when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub per_disk: Vec<DiskInfo>,
+    /// Breakdown of the network totals by interface name, only collected when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub per_interface: Vec<InterfaceInfo>,
+    /// Heaviest processes by CPU, only collected when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_processes: Vec<ProcessInfo>,
+    /// Heaviest processes by resident memory, collected along with `top_processes`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_memory_processes: Vec<ProcessInfo>,
+    /// NVIDIA GPU load and memory, only collected when requested; `None` when
+    /// no GPU could be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpu: Option<GpuInfo>,
+    /// Whether GPU figures were asked for, so a missing `gpu` reads "unavailable"
+    #[serde(skip)]
+    pub gpu_requested: bool,
+    /// The process picked with [`CollectOptions::process`]; `None` while it
+    /// isn't running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<WatchedProcess>,
+    /// Whether a process was asked for, so a missing `process` reads "not running"
+    #[serde(skip)]
+    pub process_requested: bool,
+    /// Metrics that reached an alert threshold, e.g. `["cpu"]`; see [`threshold::alerts`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<String>,
+}
+
+/// Space and I/O of a single disk
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct DiskInfo {
+    /// Device name as the OS reports it, e.g. "/dev/nvme0n1p2"
+    pub name: String,
+    pub mount_point: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    /// Bytes read during the sampling window
+    pub read_bytes: u64,
+    /// Bytes written during the sampling window
+    pub written_bytes: u64,
+    pub read_per_sec: u64,
+    pub write_per_sec: u64,
+}
+
+/// Load and memory of the NVIDIA GPUs, combined over every device
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct GpuInfo {
+    /// Number of devices NVML reported
+    pub devices: u32,
+    /// Mean utilization across the devices, 0-100
+    pub utilization_percent: f32,
+    /// VRAM in use on all devices, in bytes
     pub memory_used_bytes: u64,
+    /// VRAM on all devices, in bytes
     pub memory_total_bytes: u64,
+}
+
+/// Traffic of a single network interface
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct InterfaceInfo {
+    pub name: String,
+    /// Bytes received during the sampling window
+    pub received_bytes: u64,
+    /// Bytes sent during the sampling window
+    pub transmitted_bytes: u64,
+    pub rx_per_sec: u64,
+    pub tx_per_sec: u64,
+}
+
+/// One process in the top list
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    /// CPU usage over the sampling window; may exceed 100 on multi-core machines
+    pub cpu_usage_percent: f32,
+    /// Resident memory, in bytes
+    pub memory_bytes: u64,
+}
+
+/// CPU, memory and disk I/O of the process picked with `--pid`, `--pid-name`
+/// or `--watch-process`
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct WatchedProcess {
+    /// The lowest pid when several processes are added up
+    pub pid: u32,
+    pub name: String,
+    /// How many processes the figures add up; more than one only with `--all-matches`
+    #[cfg_attr(feature = "serde", serde(default = "one"))]
+    pub processes: usize,
+    /// CPU usage over the sampling window; may exceed 100 on multi-core machines
+    pub cpu_usage_percent: f32,
+    /// Resident memory, in bytes
+    pub memory_bytes: u64,
+    /// Virtual memory, in bytes
+    pub virtual_memory_bytes: u64,
+    /// Bytes the process read during the sampling window; zero where the
+    /// platform doesn't account I/O per process
     pub disk_read_bytes: u64,
+    /// Bytes the process wrote during the sampling window
     pub disk_write_bytes: u64,
-    pub net_rx_bytes: u64,
-    pub net_tx_bytes: u64,
+    pub disk_read_per_sec: u64,
+    pub disk_write_per_sec: u64,
+}
+
+/// How [`CollectOptions::process`] finds its process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessTarget {
+    Pid(u32),
+    /// The lowest pid with exactly this name, looked up again every sample so
+    /// a restarted service is picked up
+    Name(String),
+    /// Every process with exactly this name, added up, e.g. the workers of a
+    /// pre-forking server
+    AllNamed(String),
+}
+
+impl FromStr for ProcessTarget {
+    type Err = String;
+
+    /// A pid when `input` is all digits, otherwise a process name
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.parse() {
+            Ok(pid) => Ok(ProcessTarget::Pid(pid)),
+            Err(_) if input.is_empty() => Err("expected a pid or a process name".to_string()),
+            Err(_) => Ok(ProcessTarget::Name(input.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ProcessTarget {
+    /// "with pid 1234" or "named 'nginx'", to follow "no process"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessTarget::Pid(pid) => write!(f, "with pid {}", pid),
+            ProcessTarget::Name(name) | ProcessTarget::AllNamed(name) => write!(f, "named '{}'", name),
+        }
+    }
+}
+
+/// Which metric groups to collect, and the optional extras on top of them
+///
+/// Every group left out skips its sysinfo refresh, which is most of the
+/// cost of a sample. [`CollectOptions::default`] only collects CPU and
+/// memory; [`CollectOptions::all`] collects every group.
+#[derive(Debug, Clone)]
+pub struct CollectOptions {
+    /// Global and per-core CPU usage
+    pub cpu: bool,
+    /// RAM and swap
+    pub memory: bool,
+    /// Disk I/O totals and, with `per_disk`, the breakdown
+    pub disk: bool,
+    /// Network totals and, with `per_interface`, the breakdown; the network
+    /// fields stay `None` without it
+    pub network: bool,
+    /// Sensor temperatures
+    pub temperature: bool,
+    /// List this many processes with the highest CPU usage, and as many by memory
+    pub top_processes: Option<usize>,
+    /// Report each disk on its own besides the totals
+    pub per_disk: bool,
+    /// Leave disks and interfaces without traffic during the window out of
+    /// the breakdowns; the totals still count them
+    pub hide_idle: bool,
+    /// Report each network interface on its own besides the totals
+    pub per_interface: bool,
+    /// Only count (and list) these network interfaces; `None` means all of them
+    pub interfaces: Option<Vec<String>>,
+    /// Then leave out interfaces whose name this matches; see
+    /// [`interface_counted`](monitor::interface_counted)
+    pub ignore_interfaces: Option<Regex>,
+    /// Only count (and list) disks whose device name or mount point matches one
+    /// of these; see [`disk_matches`](monitor::disk_matches)
+    pub disks: Option<Vec<String>>,
+    /// NVIDIA GPU load and memory through NVML; always unavailable without
+    /// the `gpu` feature
+    pub gpu: bool,
+    /// Also report one process in [`MetricsSnapshot::process`]
+    pub process: Option<ProcessTarget>,
+}
+
+impl CollectOptions {
+    /// Every metric group, without the optional extras
+    pub fn all() -> Self {
+        CollectOptions { disk: true, network: true, temperature: true, ..CollectOptions::default() }
+    }
+
+    /// The groups collected, in terms of the `--fields` groups they feed
+    pub fn fields(&self) -> Fields {
+        Fields { cpu: self.cpu, mem: self.memory, disk: self.disk, net: self.network }
+    }
+}
+
+impl Default for CollectOptions {
+    fn default() -> Self {
+        CollectOptions {
+            cpu: true,
+            memory: true,
+            disk: false,
+            network: false,
+            temperature: false,
+            top_processes: None,
+            per_disk: false,
+            hide_idle: false,
+            per_interface: false,
+            interfaces: None,
+            ignore_interfaces: None,
+            disks: None,
+            gpu: false,
+            process: None,
+        }
+    }
+}
+
+/// Reasons a sample can't be trusted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CollectError {
+    /// sysinfo found no CPUs, so CPU usage would be a meaningless zero
+    NoCpus,
+    /// Total memory was reported as zero
+    NoMemory,
+}
+
+impl fmt::Display for CollectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollectError::NoCpus => write!(f, "no CPUs detected"),
+            CollectError::NoMemory => write!(f, "total memory reported as zero"),
+        }
+    }
+}
+
+impl Error for CollectError {}
+
+/// Collect metrics over a one second sampling window
+pub fn collect_metrics() -> Result<MetricsSnapshot, CollectError> {
+    collect_metrics_with_interval(Duration::from_secs(1))
+}
+
+/// Collect metrics with proper refresh for accurate network and disk I/O
+///
+/// Disk and network figures are the bytes moved during `interval`. An
+/// `interval` below [`sysinfo::MINIMUM_CPU_UPDATE_INTERVAL`] is stretched to
+/// it so the CPU figure is a real measurement; see [`Monitor::sample`].
+pub fn collect_metrics_with_interval(interval: Duration) -> Result<MetricsSnapshot, CollectError> {
+    collect_metrics_with(interval, &CollectOptions::all())
+}
+
+/// Collect metrics over `interval`, including the optional extras in `options`
+///
+/// Loops that sample repeatedly should keep a [`Monitor`] instead, which
+/// skips the baseline refresh and keeps sysinfo's history between samples.
+pub fn collect_metrics_with(
+    interval: Duration,
+    options: &CollectOptions,
+) -> Result<MetricsSnapshot, CollectError> {
+    let mut monitor = Monitor::with_options(options.clone());
+    std::thread::sleep(interval);
+
+    monitor.sample()
+}
+
+/// [`collect_metrics_with_interval`] for async code, awaiting the window
+/// instead of blocking a worker thread
+///
+/// The baseline and final refreshes still happen and briefly block as in
+/// the sync version; only the wait between them yields to the runtime.
+#[cfg(feature = "tokio")]
+pub async fn collect_metrics_async(interval: Duration) -> Result<MetricsSnapshot, CollectError> {
+    collect_metrics_with_async(interval, &CollectOptions::all()).await
+}
+
+/// [`collect_metrics_with`] for async code; see [`collect_metrics_async`]
+#[cfg(feature = "tokio")]
+pub async fn collect_metrics_with_async(
+    interval: Duration,
+    options: &CollectOptions,
+) -> Result<MetricsSnapshot, CollectError> {
+    let mut monitor = Monitor::with_options(options.clone());
+    tokio::time::sleep(interval).await;
+
+    monitor.sample()
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FormattedMetrics {
+    pub fields: Fields,
+    /// Time since boot, e.g. "3d 4h 12m"
+    pub uptime: String,
+    pub cpu_usage: String,
+    /// Empty unless [`FormatOptions::per_core`] is set
+    pub per_core_usage: Vec<String>,
+    /// The three load averages, e.g. "0.42 0.55 0.60"
+    pub load_average: Option<String>,
+    /// Sensor label and formatted reading, e.g. "62.0°C"
+    pub temperatures: Vec<(String, String)>,
+    /// Process count with the thread count where known, e.g. "312 (1840 threads)"
+    pub processes: String,
+    pub memory_used: String,
+    pub memory_total: String,
+    pub memory_available: String,
+    pub memory_free: String,
+    /// Percentage on [`FormatOptions::mem_basis`]
+    pub memory_usage_percent: String,
+    pub swap_used: String,
+    pub swap_total: String,
+    pub swap_usage_percent: String,
+    pub disk_read: String,
+    pub disk_write: String,
+    pub net_rx: String,
+    pub net_tx: String,
+    /// Colors applied to percentages on display, `None` for plain text
+    pub cpu_color: Option<Color>,
+    pub per_core_colors: Vec<Option<Color>>,
+    pub memory_color: Option<Color>,
+    pub per_disk: Vec<FormattedDisk>,
+    pub per_interface: Vec<FormattedInterface>,
+    pub top_processes: Vec<FormattedProcess>,
+    pub top_memory_processes: Vec<FormattedProcess>,
+    /// `None` when GPU figures weren't requested or no GPU could be read; see `gpu_requested`
+    pub gpu: Option<FormattedGpu>,
+    pub gpu_requested: bool,
+    /// `None` when no process was asked for or it isn't running; see `process_requested`
+    pub process: Option<FormattedWatchedProcess>,
+    pub process_requested: bool,
+    /// Sparklines of recent samples drawn next to their rows; only live mode fills them in
+    pub trends: Trends,
+    /// Changes from the previous sample shown after their values; only `live --diff` fills them in
+    pub deltas: Deltas,
+    /// Metrics that reached an alert threshold
+    pub alerts: Vec<String>,
+    /// Columns available for wrapped lists such as the per-core usage
+    pub width: usize,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FormattedDisk {
+    pub name: String,
+    pub mount_point: String,
+    pub read: String,
+    pub write: String,
+    pub available: String,
+    pub total: String,
+}
+
+impl FormattedDisk {
+    /// Rates and free space on one line, e.g. "read 0 B/s  write 1.20 MB/s  free 120 GB / 500 GB"
+    pub fn summary(&self) -> String {
+        format!("read {}  write {}  free {} / {}", self.read, self.write, self.available, self.total)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FormattedInterface {
+    pub name: String,
+    pub rx: String,
+    pub tx: String,
+}
+
+impl FormattedInterface {
+    /// Both rates on one line, e.g. "rx 1.20 MB/s  tx 300 KB/s"
+    pub fn summary(&self) -> String {
+        format!("rx {}  tx {}", self.rx, self.tx)
+    }
+}
+
+/// [`render::sparkline`]s of recent samples, empty strings where there is no history
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Trends {
+    pub cpu: String,
+    pub memory: String,
+    pub net_rx: String,
+    pub net_tx: String,
+}
+
+impl Trends {
+    /// CPU, memory usage and network rates of `history`, oldest first, `width` samples wide
+    pub fn from_history(history: &[MetricsSnapshot], width: usize) -> Trends {
+        let line = |value: &dyn Fn(&MetricsSnapshot) -> f64| {
+            let values: Vec<f64> = history.iter().map(value).collect();
+            render::sparkline(&values, width)
+        };
+
+        Trends {
+            cpu: line(&|snapshot| snapshot.cpu_usage_percent as f64),
+            memory: line(&|snapshot| snapshot.memory_usage_percent().unwrap_or(0.0)),
+            net_rx: line(&|snapshot| snapshot.net_rx_per_sec.unwrap_or(0) as f64),
+            net_tx: line(&|snapshot| snapshot.net_tx_per_sec.unwrap_or(0) as f64),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpu.is_empty() && self.memory.is_empty() && self.net_rx.is_empty() && self.net_tx.is_empty()
+    }
+}
+
+/// Signed changes from one sample to the next, e.g. "+3.1" or "-1.20 MB/s";
+/// empty strings where there is nothing to compare
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Deltas {
+    /// In percentage points, like the memory percentage
+    pub cpu: String,
+    pub memory: String,
+    pub disk_read: String,
+    pub disk_write: String,
+    pub net_rx: String,
+    pub net_tx: String,
+}
+
+impl Deltas {
+    /// How CPU and memory usage and the total rates moved from `previous` to `current`
+    pub fn between(previous: &MetricsSnapshot, current: &MetricsSnapshot, options: &FormatOptions) -> Deltas {
+        let speed = |value: u64| options.units.format_speed_with(value, options.precision);
+        let rate = |previous: u64, current: u64, format: &dyn Fn(u64) -> String| {
+            format_signed(current as i128 - previous as i128, format)
+        };
+        let net = |previous: Option<u64>, current: Option<u64>| match (previous, current) {
+            (Some(previous), Some(current)) => rate(previous, current, &|value| options.format_net_speed(value)),
+            _ => String::new(),
+        };
+        let memory = previous.memory_percent(options.mem_basis).zip(current.memory_percent(options.mem_basis));
+
+        Deltas {
+            cpu: format_signed_percent(current.cpu_usage_percent as f64 - previous.cpu_usage_percent as f64),
+            memory: memory.map(|(previous, current)| format_signed_percent(current - previous)).unwrap_or_default(),
+            disk_read: rate(previous.disk_read_per_sec, current.disk_read_per_sec, &speed),
+            disk_write: rate(previous.disk_write_per_sec, current.disk_write_per_sec, &speed),
+            net_rx: net(previous.net_rx_per_sec, current.net_rx_per_sec),
+            net_tx: net(previous.net_tx_per_sec, current.net_tx_per_sec),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        [&self.cpu, &self.memory, &self.disk_read, &self.disk_write, &self.net_rx, &self.net_tx]
+            .iter()
+            .all(|delta| delta.is_empty())
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FormattedGpu {
+    pub usage: String,
+    pub memory_used: String,
+    pub memory_total: String,
+}
+
+impl FormattedGpu {
+    /// Load and VRAM on one line, e.g. "42.0%  VRAM 2.00 GB / 8.00 GB"
+    pub fn summary(&self) -> String {
+        format!("{}  VRAM {} / {}", self.usage, self.memory_used, self.memory_total)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FormattedWatchedProcess {
+    pub pid: String,
+    pub name: String,
+    /// See [`WatchedProcess::processes`]
+    pub processes: usize,
+    pub cpu_usage: String,
+    pub memory: String,
+    pub virtual_memory: String,
+    pub disk_read: String,
+    pub disk_write: String,
+}
+
+impl FormattedWatchedProcess {
+    /// Everything on one line, e.g. "nginx (1234)  cpu 2.0%  rss 12.0 MB  virt 1.20 GB  read 0 B/s  write 0 B/s"
+    ///
+    /// A group of processes shows its size in place of the pid: "nginx (4 processes)  cpu ...".
+    pub fn summary(&self) -> String {
+        let id = if self.processes > 1 { format!("{} processes", self.processes) } else { self.pid.clone() };
+        format!(
+            "{} ({})  cpu {}  rss {}  virt {}  read {}  write {}",
+            self.name, id, self.cpu_usage, self.memory, self.virtual_memory, self.disk_read, self.disk_write
+        )
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FormattedProcess {
+    pub pid: String,
+    pub name: String,
+    pub cpu_usage: String,
+    pub memory: String,
+}
+
+impl MetricsSnapshot {
+    /// Raw values of the selected groups as `(column, value)` pairs
+    fn metric_columns(&self, fields: &Fields) -> Vec<(&'static str, String)> {
+        let mut columns = Vec::new();
+        let shown = self.shown_fields(fields);
+        // Empty cells rather than dropped columns for groups that weren't
+        // collected, so rows still match the header
+        let cell = |collected: bool, value: &dyn ToString| if collected { value.to_string() } else { String::new() };
+
+        if fields.cpu {
+            columns.push(("cpu_percent", cell(shown.cpu, &self.cpu_usage_percent)));
+        }
+        if fields.mem {
+            columns.push(("mem_used", cell(shown.mem, &self.memory_used_bytes)));
+            columns.push(("mem_total", cell(shown.mem, &self.memory_total_bytes)));
+            columns.push(("swap_used", cell(shown.mem, &self.swap_used_bytes)));
+            columns.push(("swap_total", cell(shown.mem, &self.swap_total_bytes)));
+        }
+        if fields.disk {
+            columns.push(("disk_read", cell(shown.disk, &self.disk_read_bytes)));
+            columns.push(("disk_write", cell(shown.disk, &self.disk_write_bytes)));
+        }
+        if fields.net {
+            columns.push(("net_rx", cell(shown.net, &self.net_rx_bytes.unwrap_or(0))));
+            columns.push(("net_tx", cell(shown.net, &self.net_tx_bytes.unwrap_or(0))));
+        }
+
+        columns
+    }
+
+    /// The groups of `fields` this sample has figures for
+    pub(crate) fn shown_fields(&self, fields: &Fields) -> Fields {
+        Fields {
+            cpu: fields.cpu && self.collected.cpu,
+            mem: fields.mem && self.collected.mem,
+            disk: fields.disk && self.collected.disk,
+            net: fields.net && self.collected.net && self.net_rx_bytes.is_some(),
+        }
+    }
+
+    /// CSV columns: the timestamp, when enabled, followed by the metrics
+    fn csv_columns(&self, options: &FormatOptions) -> Vec<(&'static str, String)> {
+        let mut columns = Vec::new();
+        if let Some(timestamp) = &options.timestamp {
+            columns.push(("timestamp", timestamp.format(&self.timestamp)));
+        }
+        columns.extend(self.metric_columns(&options.fields));
+        if options.process {
+            // Empty cells while the process isn't running
+            let process = |value: &dyn Fn(&WatchedProcess) -> String| self.process.as_ref().map(value).unwrap_or_default();
+            columns.push(("process_pid", process(&|process| process.pid.to_string())));
+            columns.push(("process_cpu_percent", process(&|process| process.cpu_usage_percent.to_string())));
+            columns.push(("process_rss", process(&|process| process.memory_bytes.to_string())));
+            columns.push(("process_virtual", process(&|process| process.virtual_memory_bytes.to_string())));
+            columns.push(("process_disk_read", process(&|process| process.disk_read_bytes.to_string())));
+            columns.push(("process_disk_write", process(&|process| process.disk_write_bytes.to_string())));
+        }
+
+        columns
+    }
+
+    /// Render raw values as one CSV row matching [`csv_header`]
+    pub fn to_csv_row(&self, options: &FormatOptions) -> String {
+        let values: Vec<String> =
+            self.csv_columns(options).into_iter().map(|(_, value)| value).collect();
+        values.join(",")
+    }
+
+    /// One InfluxDB line protocol point for `measurement` with every collected
+    /// group as fields; see [`format::influx::render`]
+    pub fn to_line_protocol(&self, measurement: &str, tags: &[(String, String)]) -> String {
+        format::influx::render(self, measurement, tags, &Fields::all())
+    }
+
+    /// StatsD gauge lines named `prefix.metric`, with optional DogStatsD
+    /// tags like `env:prod`; see [`format::statsd::render`]
+    pub fn to_statsd(&self, prefix: &str, tags: &[String]) -> Vec<String> {
+        format::statsd::render(self, prefix, tags)
+    }
+
+    /// Render raw values tab separated in [`csv_header`] order, minus the timestamp
+    pub fn to_raw_row(&self, fields: &Fields) -> String {
+        let values: Vec<String> =
+            self.metric_columns(fields).into_iter().map(|(_, value)| value).collect();
+        values.join("\t")
+    }
+
+    /// Serialize the selected groups as a JSON object; unselected keys are omitted
+    pub fn to_json(&self, options: &FormatOptions) -> serde_json::Result<serde_json::Value> {
+        // Going through text keeps f32 values short; to_value would widen them
+        // to f64 and print artifacts like 1.0101009607315063
+        let mut value: serde_json::Value = serde_json::from_str(&serde_json::to_string(self)?)?;
+        if let Some(object) = value.as_object_mut() {
+            let fields = self.shown_fields(&options.fields);
+            object.retain(|key, _| Field::of_key(key).is_none_or(|field| fields.contains(field)));
+
+            match &options.timestamp {
+                Some(format) => {
+                    object.insert("timestamp".to_string(), format.to_json(&self.timestamp));
+                }
+                None => {
+                    object.shift_remove("timestamp");
+                }
+            }
+            // Right after the timestamp, ahead of the metrics
+            let at = object.contains_key("timestamp") as usize;
+            object.shift_insert(at, "schema_version".to_string(), SCHEMA_VERSION.into());
+            object.shift_insert(at + 1, "version".to_string(), env!("CARGO_PKG_VERSION").into());
+        }
+
+        Ok(value)
+    }
+
+    /// Share of memory in use, or `None` when the total is unknown
+    pub fn memory_usage_percent(&self) -> Option<f64> {
+        percent_of(self.memory_used_bytes, self.memory_total_bytes)
+    }
+
+    /// Share of memory in use counted on `basis`
+    ///
+    /// Samples without an available figure, like recordings from before it
+    /// was collected, fall back to used memory.
+    pub fn memory_percent(&self, basis: MemBasis) -> Option<f64> {
+        match basis {
+            MemBasis::Available if self.memory_available_bytes > 0 => percent_of(
+                self.memory_total_bytes.saturating_sub(self.memory_available_bytes),
+                self.memory_total_bytes,
+            ),
+            _ => self.memory_usage_percent(),
+        }
+    }
+
+    /// Share of swap in use, or `None` when there is no swap
+    pub fn swap_usage_percent(&self) -> Option<f64> {
+        percent_of(self.swap_used_bytes, self.swap_total_bytes)
+    }
+
+    pub fn format(&self) -> FormattedMetrics {
+        self.format_with(&FormatOptions::default())
+    }
+
+    pub fn format_with(&self, options: &FormatOptions) -> FormattedMetrics {
+        let bytes = |value: u64| options.units.format_bytes_with(value, options.precision);
+        let speed = |value: u64| options.units.format_speed_with(value, options.precision);
+        let format_process = |process: &ProcessInfo| FormattedProcess {
+            pid: process.pid.to_string(),
+            name: process.name.clone(),
+            cpu_usage: format_percent(process.cpu_usage_percent),
+            memory: bytes(process.memory_bytes),
+        };
+        let color_for = |breakpoints: &Breakpoints, percent: f64| options.color.then(|| breakpoints.color(percent));
+
+        FormattedMetrics {
+            // Nothing to show for a group that was never collected
+            fields: self.shown_fields(&options.fields),
+            uptime: format_duration(Duration::from_secs(self.uptime_secs)),
+            cpu_usage: format_percent(self.cpu_usage_percent),
+            per_core_usage: if options.per_core {
+                self.per_core_usage.iter().map(|&usage| format_percent(usage)).collect()
+            } else {
+                Vec::new()
+            },
+            load_average: self
+                .load_average
+                .map(|(one, five, fifteen)| format!("{:.2} {:.2} {:.2}", one, five, fifteen)),
+            temperatures: self
+                .temperatures
+                .iter()
+                .map(|(label, celsius)| (label.clone(), format!("{:.1}°C", celsius)))
+                .collect(),
+            processes: match self.thread_count {
+                Some(threads) => format!("{} ({} threads)", self.process_count, threads),
+                None => self.process_count.to_string(),
+            },
+            memory_used: bytes(self.memory_used_bytes),
+            memory_total: bytes(self.memory_total_bytes),
+            memory_available: bytes(self.memory_available_bytes),
+            memory_free: bytes(self.memory_free_bytes),
+            memory_usage_percent: format_optional_percent(self.memory_percent(options.mem_basis)),
+            swap_used: bytes(self.swap_used_bytes),
+            swap_total: bytes(self.swap_total_bytes),
+            swap_usage_percent: format_optional_percent(self.swap_usage_percent()),
+            disk_read: speed(self.disk_read_per_sec),
+            disk_write: speed(self.disk_write_per_sec),
+            net_rx: self.net_rx_per_sec.map(|rate| options.format_net_speed(rate)).unwrap_or_default(),
+            net_tx: self.net_tx_per_sec.map(|rate| options.format_net_speed(rate)).unwrap_or_default(),
+            cpu_color: color_for(&options.cpu_colors, self.cpu_usage_percent as f64),
+            per_core_colors: self.per_core_usage.iter().map(|&usage| color_for(&options.cpu_colors, usage as f64)).collect(),
+            memory_color: self.memory_percent(options.mem_basis).and_then(|percent| color_for(&options.mem_colors, percent)),
+            per_disk: self
+                .per_disk
+                .iter()
+                .map(|disk| FormattedDisk {
+                    name: disk.name.clone(),
+                    mount_point: disk.mount_point.clone(),
+                    read: speed(disk.read_per_sec),
+                    write: speed(disk.write_per_sec),
+                    available: bytes(disk.available_bytes),
+                    total: bytes(disk.total_bytes),
+                })
+                .collect(),
+            per_interface: self
+                .per_interface
+                .iter()
+                .map(|interface| FormattedInterface {
+                    name: interface.name.clone(),
+                    rx: options.format_net_speed(interface.rx_per_sec),
+                    tx: options.format_net_speed(interface.tx_per_sec),
+                })
+                .collect(),
+            top_processes: self.top_processes.iter().map(format_process).collect(),
+            top_memory_processes: self.top_memory_processes.iter().map(format_process).collect(),
+            gpu: self.gpu.as_ref().map(|gpu| FormattedGpu {
+                usage: format_percent(gpu.utilization_percent),
+                memory_used: bytes(gpu.memory_used_bytes),
+                memory_total: bytes(gpu.memory_total_bytes),
+            }),
+            gpu_requested: self.gpu_requested,
+            process: self.process.as_ref().map(|process| FormattedWatchedProcess {
+                pid: process.pid.to_string(),
+                name: process.name.clone(),
+                processes: process.processes,
+                cpu_usage: format_percent(process.cpu_usage_percent),
+                memory: bytes(process.memory_bytes),
+                virtual_memory: bytes(process.virtual_memory_bytes),
+                disk_read: speed(process.disk_read_per_sec),
+                disk_write: speed(process.disk_write_per_sec),
+            }),
+            process_requested: self.process_requested,
+            trends: Trends::default(),
+            deltas: Deltas::default(),
+            alerts: self.alerts.clone(),
+            width: options.width.unwrap_or(80),
+        }
+    }
+}
+
+/// Recordings from before [`WatchedProcess::processes`] always watched one
+#[cfg(feature = "serde")]
+fn one() -> usize {
+    1
+}
+
+/// A [`Duration`] as whole milliseconds
+mod millis {
+    use std::time::Duration;
+
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        <u64 as serde::Deserialize>::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// `used` as a percentage of `total`, `None` when `total` is zero
+fn percent_of(used: u64, total: u64) -> Option<f64> {
+    if total == 0 {
+        return None;
+    }
+
+    Some((used as f64 / total as f64) * 100.0)
+}
+
+/// Format a percentage that may be unavailable as "N/A"
+fn format_optional_percent(percent: Option<f64>) -> String {
+    match percent {
+        Some(percent) => format_percent(percent as f32),
+        None => "N/A".to_string(),
+    }
+}
+
+impl FormattedMetrics {
+    /// Render the selected metrics as a single `key=value` line, e.g. for log mode
+    pub fn fmt_line(&self) -> String {
+        self.line(false)
+    }
+
+    /// Like [`fmt_line`](Self::fmt_line), with every value right-aligned in
+    /// a fixed-width field
+    ///
+    /// Percentages take [`PERCENT_WIDTH`] columns and byte values and rates
+    /// [`BYTES_WIDTH`], so a value growing from "9.99MB" to "10.0MB" no
+    /// longer shifts what follows it and `cut -c` keeps working. Parts that
+    /// come and go, like the alert marker, still change the length.
+    pub fn fmt_aligned_line(&self) -> String {
+        self.line(true)
+    }
+
+    fn line(&self, align: bool) -> String {
+        // Padded before painting so escape codes don't count towards the width
+        let field = |value: &str, width: usize, color: Option<Color>| {
+            if align { paint(&format!("{:>width$}", value), color) } else { paint(value, color) }
+        };
+        let percent = |value: &str, color: Option<Color>| field(value, PERCENT_WIDTH, color);
+        let bytes = |value: &str, color: Option<Color>| field(&compact(value), BYTES_WIDTH, color);
+        let mut parts = Vec::new();
+
+        if self.fields.cpu {
+            parts.push(format!("cpu={}", percent(&self.cpu_usage, self.cpu_color)));
+            if !self.per_core_usage.is_empty() {
+                let cores: Vec<String> = self
+                    .per_core_usage
+                    .iter()
+                    .enumerate()
+                    .map(|(core, usage)| percent(usage, self.per_core_colors.get(core).copied().flatten()))
+                    .collect();
+                parts.push(format!("cores={}", cores.join(",")));
+            }
+        }
+        if self.fields.mem {
+            parts.push(format!("mem={}", percent(&self.memory_usage_percent, self.memory_color)));
+            parts.push(format!("mem_used={}", bytes(&self.memory_used, None)));
+            parts.push(format!("mem_total={}", bytes(&self.memory_total, None)));
+            parts.push(format!("swap={}/{}", bytes(&self.swap_used, None), bytes(&self.swap_total, None)));
+        }
+        if self.fields.disk {
+            parts.push(format!("read={}", bytes(&self.disk_read, None)));
+            parts.push(format!("write={}", bytes(&self.disk_write, None)));
+        }
+        if self.fields.net {
+            parts.push(format!("rx={}", bytes(&self.net_rx, None)));
+            parts.push(format!("tx={}", bytes(&self.net_tx, None)));
+        }
+        if self.gpu_requested {
+            match &self.gpu {
+                Some(gpu) => {
+                    parts.push(format!("gpu={}", percent(&gpu.usage, None)));
+                    parts.push(format!("vram={}/{}", bytes(&gpu.memory_used, None), bytes(&gpu.memory_total, None)));
+                }
+                None => parts.push("gpu=unavailable".to_string()),
+            }
+        }
+        if self.process_requested {
+            match &self.process {
+                Some(process) => {
+                    parts.push(format!("pid={}", field(&process.pid, PID_WIDTH, None)));
+                    if process.processes > 1 {
+                        parts.push(format!("procs={}", process.processes));
+                    }
+                    parts.push(format!("pcpu={}", percent(&process.cpu_usage, None)));
+                    parts.push(format!("rss={}", bytes(&process.memory, None)));
+                    parts.push(format!("virt={}", bytes(&process.virtual_memory, None)));
+                    parts.push(format!("pread={}", bytes(&process.disk_read, None)));
+                    parts.push(format!("pwrite={}", bytes(&process.disk_write, None)));
+                }
+                None => parts.push("pid=none".to_string()),
+            }
+        }
+        if !self.alerts.is_empty() {
+            parts.push(self.alert_marker());
+        }
+
+        parts.join(" ")
+    }
+
+    /// The multi-line text block, as a table in `table` style when given
+    pub fn block(&self, table: Option<TableStyle>) -> String {
+        match table {
+            Some(style) => render::table(self, style),
+            None => self.to_string(),
+        }
+    }
+
+    /// The GPU line's value, "unavailable" when no GPU could be read
+    pub fn gpu_summary(&self) -> String {
+        self.gpu.as_ref().map_or_else(|| "unavailable".to_string(), FormattedGpu::summary)
+    }
+
+    /// The process line's value, "not running" while the watched process isn't
+    pub fn process_summary(&self) -> String {
+        self.process.as_ref().map_or_else(|| "not running".to_string(), FormattedWatchedProcess::summary)
+    }
+
+    /// "[ALERT cpu,mem]", or an empty string without alerts
+    pub fn alert_marker(&self) -> String {
+        if self.alerts.is_empty() {
+            return String::new();
+        }
+
+        format!("[ALERT {}]", self.alerts.join(","))
+    }
+
+    fn painted_cores(&self) -> impl Iterator<Item = (usize, String)> + '_ {
+        self.per_core_usage.iter().enumerate().map(|(core, usage)| {
+            (core, paint(usage, self.per_core_colors.get(core).copied().flatten()))
+        })
+    }
+
+    /// The per-core usage as "cpu0 12.0%" cells, as many per line as fit in
+    /// [`width`](FormattedMetrics::width) behind the 19-column label
+    fn core_lines(&self) -> Vec<String> {
+        let cell_width = self
+            .per_core_usage
+            .iter()
+            .enumerate()
+            .map(|(core, usage)| format!("cpu{} {}", core, usage).chars().count())
+            .max()
+            .unwrap_or(0);
+        let per_line = (self.width.saturating_sub(19) + 2) / (cell_width + 2);
+
+        let cells: Vec<String> = self
+            .painted_cores()
+            .map(|(core, usage)| {
+                // Pad by the plain text so escape codes don't count towards the width
+                let padding = cell_width - format!("cpu{} {}", core, self.per_core_usage[core]).chars().count();
+                format!("cpu{} {}{}", core, usage, " ".repeat(padding))
+            })
+            .collect();
+        cells
+            .chunks(per_line.max(1))
+            .map(|line| line.join("  ").trim_end().to_string())
+            .collect()
+    }
+}
+
+/// CSV header row for the selected groups, in the same order as
+/// [`MetricsSnapshot::to_csv_row`]
+pub fn csv_header(options: &FormatOptions) -> String {
+    let names: Vec<&str> = MetricsSnapshot::default()
+        .csv_columns(options)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    names.join(",")
+}
+
+/// Header row for [`OutputFormat::Columns`], its time column as wide as
+/// `snapshot`'s timestamp when timestamps are shown
+pub fn columns_header(snapshot: &MetricsSnapshot, options: &FormatOptions) -> String {
+    let time_width = options.timestamp.as_ref().map(|timestamp| timestamp.format(&snapshot.timestamp).chars().count());
+    render::column_header(time_width)
+}
+
+/// The comment line ahead of a CSV header naming [`SCHEMA_VERSION`] and the
+/// version that wrote the file
+pub fn csv_schema_line() -> String {
+    format!("# schema_version={} system-monitor {}", SCHEMA_VERSION, env!("CARGO_PKG_VERSION"))
 }
 
-/// Collect metrics with proper refresh for accurate network and disk I/O
-pub fn collect_metrics() -> MetricsSnapshot {
-    let mut system = System::new();
-    let mut disks = Disks::new_with_refreshed_list();
-    let mut networks = Networks::new_with_refreshed_list();
-    
-    // First refresh to establish baseline
-    system.refresh_all();
-    disks.refresh(false);
-    networks.refresh(false);
-    
-    // Wait for the interval
-    std::thread::sleep(Duration::from_millis(5000));
-    
-    // Second refresh to get deltas
-    system.refresh_all();
-    disks.refresh(false);
-    networks.refresh(false);
-    
-    let cpu_usage = system.global_cpu_usage();
-    
-    // Memory
-    let memory_used = system.used_memory();
-    let memory_total = system.total_memory();
-    
-    // Disk I/O (sum all disks) - these are now bytes since last refresh
-    let (disk_read, disk_write) = disks
-        .iter()
-        .map(|disk| disk.usage())
-        .fold((0, 0), |(read, write), usage| {
-            (read + usage.read_bytes, write + usage.written_bytes)
-        });
-    
-    // Network (sum all interfaces) - these are now bytes since last refresh
-    let (rx_bytes, tx_bytes) = networks
-        .iter()
-        .fold((0, 0), |(rx, tx), (_, data)| {
-            (rx + data.received(), tx + data.transmitted())
-        });
-    
-    MetricsSnapshot {
-        cpu_usage_percent: cpu_usage,
-        memory_used_bytes: memory_used,
-        memory_total_bytes: memory_total,
-        disk_read_bytes: disk_read,
-        disk_write_bytes: disk_write,
-        net_rx_bytes: rx_bytes,
-        net_tx_bytes: tx_bytes,
+/// Values padded to this many columns line their sparklines up
+const TREND_COLUMN: usize = 26;
+
+/// Padding and `trend` to follow the plain text `value`, or nothing without a trend
+///
+/// Measured on the plain text so escape codes don't count towards the width.
+fn trend_suffix(value: &str, trend: &str) -> String {
+    if trend.is_empty() {
+        return String::new();
     }
+
+    let padding = TREND_COLUMN.saturating_sub(value.chars().count()).max(2);
+    format!("{}{}", " ".repeat(padding), trend)
 }
 
-#[derive(Debug)]
-pub struct FormattedMetrics {
-    pub cpu_usage: String,
-    pub memory_used: String,
-    pub memory_total: String,
-    pub memory_usage_percent: String,
-    pub disk_read: String,
-    pub disk_write: String,
-    pub net_rx: String,
-    pub net_tx: String,
+/// A [`Deltas`] entry in parentheses after its value, nothing without one
+fn delta_suffix(delta: &str) -> String {
+    if delta.is_empty() { String::new() } else { format!(" ({})", delta) }
 }
 
-impl MetricsSnapshot {
-    pub fn format(&self) -> FormattedMetrics {
-        FormattedMetrics {
-            cpu_usage: format!("{:.1}%", self.cpu_usage_percent),
-            memory_used: format_bytes(self.memory_used_bytes),
-            memory_total: format_bytes(self.memory_total_bytes),
-            memory_usage_percent: format!(
-                "{:.1}%",
-                (self.memory_used_bytes as f64 / self.memory_total_bytes as f64) * 100.0
-            ),
-            disk_read: format_bytes(self.disk_read_bytes),
-            disk_write: format_bytes(self.disk_write_bytes),
-            net_rx: format_bytes(self.net_rx_bytes),
-            net_tx: format_bytes(self.net_tx_bytes),
-        }
-    }
+/// Width of an aligned percentage, enough for "100.0%"
+pub const PERCENT_WIDTH: usize = 6;
+/// Width of an aligned byte value or rate, enough for adaptive values up to "1023PiB/s"
+pub const BYTES_WIDTH: usize = 10;
+/// Width of an aligned PID; Linux PIDs go up to 4194304
+const PID_WIDTH: usize = 7;
+
+/// Drop the space between value and unit so fields stay whitespace separated
+fn compact(value: &str) -> String {
+    value.replace(' ', "")
 }
 
 impl fmt::Display for FormattedMetrics {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "System Metrics:")?;
-        writeln!(f, "  CPU Usage:       {}", self.cpu_usage)?;
-        writeln!(f, "  Memory:          {} / {} ({})", 
-            self.memory_used, self.memory_total, self.memory_usage_percent)?;
-        writeln!(f, "  Disk Read:       {}", self.disk_read)?;
-        writeln!(f, "  Disk Write:      {}", self.disk_write)?;
-        writeln!(f, "  Network RX:      {}", self.net_rx)?;
-        writeln!(f, "  Network TX:      {}", self.net_tx)?;
+        if self.alerts.is_empty() {
+            writeln!(f, "System Metrics:")?;
+        } else {
+            writeln!(f, "System Metrics: {}", self.alert_marker())?;
+        }
+        writeln!(f, "  Uptime:          {}", self.uptime)?;
+        if self.fields.cpu {
+            let cpu_delta = delta_suffix(&self.deltas.cpu);
+            writeln!(f, "  CPU Usage:       {}{}{}", paint(&self.cpu_usage, self.cpu_color), cpu_delta,
+                trend_suffix(&format!("{}{}", self.cpu_usage, cpu_delta), &self.trends.cpu))?;
+            for (row, line) in self.core_lines().iter().enumerate() {
+                let label = if row == 0 { "Cores:" } else { "" };
+                writeln!(f, "  {:<17}{}", label, line)?;
+            }
+            if let Some(load) = &self.load_average {
+                writeln!(f, "  Load:            {}", load)?;
+            }
+            writeln!(f, "  Processes:       {}", self.processes)?;
+            if !self.temperatures.is_empty() {
+                writeln!(f, "  Temperatures:")?;
+                for (label, reading) in &self.temperatures {
+                    writeln!(f, "    {}: {}", label, reading)?;
+                }
+            }
+        }
+        if self.fields.mem {
+            let memory_delta = delta_suffix(&self.deltas.memory);
+            let memory =
+                format!("{} / {} ({}){}", self.memory_used, self.memory_total, self.memory_usage_percent, memory_delta);
+            writeln!(f, "  Memory:          {} / {} ({}){}{}",
+                self.memory_used, self.memory_total,
+                paint(&self.memory_usage_percent, self.memory_color), memory_delta,
+                trend_suffix(&memory, &self.trends.memory))?;
+            writeln!(f, "  Available:       {} (free {})", self.memory_available, self.memory_free)?;
+            writeln!(f, "  Swap:            {} / {} ({})",
+                self.swap_used, self.swap_total, self.swap_usage_percent)?;
+        }
+        if self.fields.disk {
+            writeln!(f, "  Disk Read:       {}{}", self.disk_read, delta_suffix(&self.deltas.disk_read))?;
+            writeln!(f, "  Disk Write:      {}{}", self.disk_write, delta_suffix(&self.deltas.disk_write))?;
+            if !self.per_disk.is_empty() {
+                writeln!(f, "  Disks:")?;
+                // Line the device names and summaries up behind the longest
+                // mount point and name
+                let width = self
+                    .per_disk
+                    .iter()
+                    .map(|disk| disk.mount_point.chars().count() + 2)
+                    .fold(15, usize::max);
+                let name_width = self
+                    .per_disk
+                    .iter()
+                    .map(|disk| disk.name.chars().count())
+                    .max()
+                    .filter(|&longest| longest > 0)
+                    .map_or(0, |longest| longest + 2);
+                for disk in &self.per_disk {
+                    writeln!(f, "    {:<width$}{:<name_width$}{}", disk.mount_point, disk.name, disk.summary())?;
+                }
+            }
+        }
+        if self.fields.net {
+            let rx = format!("{}{}", self.net_rx, delta_suffix(&self.deltas.net_rx));
+            let tx = format!("{}{}", self.net_tx, delta_suffix(&self.deltas.net_tx));
+            writeln!(f, "  Network RX:      {}{}", rx, trend_suffix(&rx, &self.trends.net_rx))?;
+            writeln!(f, "  Network TX:      {}{}", tx, trend_suffix(&tx, &self.trends.net_tx))?;
+            if !self.per_interface.is_empty() {
+                writeln!(f, "  Network:")?;
+                let width = self
+                    .per_interface
+                    .iter()
+                    .map(|interface| interface.name.chars().count() + 2)
+                    .fold(15, usize::max);
+                for interface in &self.per_interface {
+                    writeln!(f, "    {:<width$}{}", interface.name, interface.summary())?;
+                }
+            }
+        }
+        if self.gpu_requested {
+            writeln!(f, "  GPU:             {}", self.gpu_summary())?;
+        }
+        if self.process_requested {
+            writeln!(f, "  Process:         {}", self.process_summary())?;
+        }
+        for (title, processes) in [
+            ("Top Processes:", &self.top_processes),
+            ("Top Processes by Memory:", &self.top_memory_processes),
+        ] {
+            if processes.is_empty() {
+                continue;
+            }
+            writeln!(f, "  {}", title)?;
+            writeln!(f, "    {:>7}  {:>6}  {:>9}  NAME", "PID", "CPU", "MEM")?;
+            for process in processes {
+                writeln!(f, "    {:>7}  {:>6}  {:>9}  {}",
+                    process.pid, process.cpu_usage, process.memory, process.name)?;
+            }
+        }
         Ok(())
     }
 }
 
-/// Format bytes into human-readable format (B, KB, MB, GB, TB)
+const SI_UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB", "EB"];
+const BINARY_UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+const BIT_UNITS: &[&str] = &["bps", "Kbps", "Mbps", "Gbps", "Tbps", "Pbps", "Ebps"];
+
+/// Format bytes into human-readable format (B, KB, MB, GB, TB, PB, EB)
 pub fn format_bytes(bytes: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB"];
-    
+    format_scaled(bytes, 1000, SI_UNITS, None)
+}
+
+/// Like [`format_bytes`], but always with `decimals` decimal places
+///
+/// Plain bytes stay whole: "512 B", never "512.00 B".
+pub fn format_bytes_precision(bytes: u64, decimals: usize) -> String {
+    format_scaled(bytes, 1000, SI_UNITS, Some(decimals))
+}
+
+/// Format bytes with base-1024 IEC units (B, KiB, MiB, GiB, TiB, PiB, EiB)
+pub fn format_bytes_binary(bytes: u64) -> String {
+    format_scaled(bytes, 1024, BINARY_UNITS, None)
+}
+
+/// Scale `bytes` by powers of `base` with `precision` decimals, or ~3
+/// significant digits when `None`
+///
+/// The unit is picked on the rounded value, so 999_999 bytes shows as
+/// "1.00 MB" rather than "1000 KB". u64 tops out at 18.4 EB, so the unit
+/// table never runs out.
+fn format_scaled(bytes: u64, base: u64, units: &[&str], precision: Option<usize>) -> String {
     if bytes == 0 {
-        return "0 B".to_string();
-    }
-    
-    let bytes_f = bytes as f64;
-    let exponent = (bytes_f.log10() / 1000_f64.log10()).floor() as usize;
-    let exponent = exponent.min(UNITS.len() - 1);
-    
-    let value = bytes_f / 1000_f64.powi(exponent as i32);
-    let unit = UNITS[exponent];
-    
-    if value >= 100.0 {
-        format!("{:.0} {}", value, unit)
-    } else if value >= 10.0 {
-        format!("{:.1} {}", value, unit)
+        return format!("0 {}", units[0]);
+    }
+
+    // Integer division avoids log10 imprecision right at the powers of base
+    let mut exponent = 0;
+    let mut divisor = 1u64;
+    while exponent + 1 < units.len() && bytes / divisor >= base {
+        exponent += 1;
+        divisor *= base;
+    }
+
+    // Step up once the value would round to `base`; adaptive output has no
+    // decimals by then
+    let mut value = bytes as f64 / divisor as f64;
+    let carry = 0.5 / 10f64.powi(precision.unwrap_or(0) as i32);
+    if exponent > 0 && exponent + 1 < units.len() && value >= base as f64 - carry {
+        exponent += 1;
+        value /= base as f64;
+    }
+
+    let decimals = if exponent == 0 { 0 } else { precision.unwrap_or_else(|| adaptive_decimals(value)) };
+    format!("{:.*} {}", decimals, value, units[exponent])
+}
+
+/// 2 decimals under 10, 1 under 100 and none above, measured after rounding
+fn adaptive_decimals(value: f64) -> usize {
+    if value >= 99.95 {
+        0
+    } else if value >= 9.995 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Parse a duration such as `500ms`, `30s`, `5m`, `1h` or plain seconds (`1.5`)
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    if input.starts_with('-') {
+        return Err(format!("invalid duration '{}': must be greater than zero", input));
+    }
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}': expected a number like 30s, 5m or 1.5", input))?;
+    let seconds = match unit.trim() {
+        "" | "s" => value,
+        "ms" => value / 1000.0,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        other => return Err(format!("unknown duration unit '{}': use ms, s, m or h", other)),
+    };
+
+    if seconds <= 0.0 {
+        return Err(format!("invalid duration '{}': must be greater than zero", input));
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|err| format!("invalid duration '{}': {}", input, err))
+}
+
+/// Humanize a duration down to the minute, e.g. "3d 4h 12m"
+///
+/// Anything under a minute is shown in seconds instead.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds < 60 {
+        return format!("{}s", seconds);
+    }
+
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
     } else {
-        format!("{:.2} {}", value, unit)
+        format!("{}m", minutes)
     }
 }
 
@@ -137,15 +1610,142 @@ pub fn format_speed(bytes_per_sec: u64) -> String {
     format!("{}/s", format_bytes(bytes_per_sec))
 }
 
+/// Format a rate in bits per second with powers of 1000 (bps, Kbps, Mbps, Gbps)
+///
+/// Network links are rated this way; multiply a byte rate by 8 first.
+pub fn format_bits(bits_per_sec: u64) -> String {
+    format_scaled(bits_per_sec, 1000, BIT_UNITS, None)
+}
+
 /// Format percentage with one decimal place
+///
+/// Rounding never makes a value look full or idle when it isn't: anything
+/// short of 100 shows at most "99.9%", and anything above 0 that would round
+/// to "0.0%" shows as "<0.1%".
 pub fn format_percent(value: f32) -> String {
-    format!("{:.1}%", value)
+    if value > 0.0 && value < 0.05 {
+        "<0.1%".to_string()
+    } else if (99.95..100.0).contains(&value) {
+        "99.9%".to_string()
+    } else {
+        format!("{:.1}%", value)
+    }
+}
+
+/// A change in percentage points with its sign and one decimal place, e.g. "+3.1"
+///
+/// Changes that round away show as "±0.0" rather than picking a sign.
+pub fn format_signed_percent(delta: f64) -> String {
+    if delta.abs() < 0.05 { "±0.0".to_string() } else { format!("{:+.1}", delta) }
+}
+
+/// A change in bytes with its sign, the magnitude formatted by `format`, e.g. "-300 KB/s"
+pub fn format_signed(delta: i128, format: impl Fn(u64) -> String) -> String {
+    let magnitude = format(delta.unsigned_abs().min(u64::MAX as u128) as u64);
+    match delta.signum() {
+        1 => format!("+{}", magnitude),
+        -1 => format!("-{}", magnitude),
+        _ => format!("±{}", magnitude),
+    }
+}
+
+pub fn print_once(interval: Duration, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let snapshot = collect_metrics_with_interval(interval)?;
+
+    write_snapshot(&mut io::stdout(), &snapshot, &format, &FormatOptions::default())
+}
+
+/// The multi-line text block for a sample, as a table when one was requested
+pub fn text_block(snapshot: &MetricsSnapshot, options: &FormatOptions) -> String {
+    snapshot.format_with(options).block(options.table)
+}
+
+/// Write a single standalone sample to `out` in the given format
+pub fn write_snapshot(
+    out: &mut dyn Write,
+    snapshot: &MetricsSnapshot,
+    format: &OutputFormat,
+    options: &FormatOptions,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Text => write!(out, "{}", text_block(snapshot, options))?,
+        OutputFormat::Json => {
+            writeln!(out, "{}", serde_json::to_string_pretty(&snapshot.to_json(options)?)?)?
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "{}\n{}\n{}", csv_schema_line(), csv_header(options), snapshot.to_csv_row(options))?
+        }
+        OutputFormat::Columns { .. } => {
+            writeln!(out, "{}", columns_header(snapshot, options))?;
+            write_sample(out, snapshot, format, options)?
+        }
+        _ => write_sample(out, snapshot, format, options)?,
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Write one sample of a continuous stream, one line per sample
+///
+/// CSV rows come without the header, which the caller writes once up front;
+/// columns rows likewise leave repeating [`columns_header`] to the caller.
+pub fn write_sample(
+    out: &mut dyn Write,
+    snapshot: &MetricsSnapshot,
+    format: &OutputFormat,
+    options: &FormatOptions,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Text | OutputFormat::Line => {
+            let metrics = snapshot.format_with(options);
+            let line = if options.align { metrics.fmt_aligned_line() } else { metrics.fmt_line() };
+            match &options.timestamp {
+                Some(timestamp) => writeln!(out, "{} {}", timestamp.format(&snapshot.timestamp), line)?,
+                None => writeln!(out, "{}", line)?,
+            }
+        }
+        OutputFormat::Json | OutputFormat::Ndjson => writeln!(out, "{}", snapshot.to_json(options)?)?,
+        OutputFormat::Csv => writeln!(out, "{}", snapshot.to_csv_row(options))?,
+        OutputFormat::Raw => writeln!(out, "{}", snapshot.to_raw_row(&options.fields))?,
+        OutputFormat::Prometheus => write!(out, "{}", format::prometheus::render(snapshot))?,
+        OutputFormat::Influx => {
+            let host = sysinfo::System::host_name().unwrap_or_default();
+            let tags = [("host".to_string(), host)];
+            writeln!(out, "{}", format::influx::render(snapshot, "system", &tags, &options.fields))?
+        }
+        OutputFormat::Template(template) => writeln!(out, "{}", template.render(snapshot, options))?,
+        OutputFormat::Columns { .. } => {
+            let time = options.timestamp.as_ref().map(|timestamp| timestamp.format(&snapshot.timestamp));
+            writeln!(out, "{}", render::column_row(&snapshot.format_with(options), time.as_deref()))?
+        }
+    }
+    // Flush every sample so pipes see lines as they happen and a crash
+    // never loses more than the sample in flight
+    out.flush()?;
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_format_percent_keeps_full_and_idle_honest() {
+        assert_eq!(format_percent(99.96), "99.9%");
+        assert_eq!(format_percent(100.0), "100.0%");
+        assert_eq!(format_percent(0.04), "<0.1%");
+        assert_eq!(format_percent(0.0), "0.0%");
+        assert_eq!(format_percent(0.05), "0.1%");
+        assert_eq!(format_percent(42.25), "42.2%");
+        // Processes on several cores go past 100
+        assert_eq!(format_percent(199.96), "200.0%");
+
+        let nearly_full = MetricsSnapshot { memory_used_bytes: 99_960, memory_total_bytes: 100_000, ..Default::default() };
+        assert_eq!(nearly_full.format().memory_usage_percent, "99.9%");
+    }
+
     #[test]
     fn test_format_bytes() {
         assert_eq!(format_bytes(0), "0 B");
@@ -157,15 +1757,749 @@ mod tests {
         assert_eq!(format_bytes(150_000_000_000), "150 GB");
     }
 
+    #[test]
+    fn test_format_bytes_rounding_boundaries() {
+        assert_eq!(format_bytes(999), "999 B");
+        assert_eq!(format_bytes(999_499), "999 KB");
+        assert_eq!(format_bytes(999_500), "1.00 MB");
+        assert_eq!(format_bytes(999_999), "1.00 MB");
+        assert_eq!(format_bytes(1_000_000), "1.00 MB");
+        assert_eq!(format_bytes(9_999_000), "10.0 MB");
+        assert_eq!(format_bytes(99_990_000), "100 MB");
+        assert_eq!(format_bytes_binary(1_048_575), "1.00 MiB");
+    }
+
+    #[test]
+    fn test_format_bytes_exabytes() {
+        assert_eq!(format_bytes(1_000_000_000_000_000), "1.00 PB");
+        assert_eq!(format_bytes(15_000_000_000_000_000), "15.0 PB");
+        assert_eq!(format_bytes(15_000_000_000_000_000_000), "15.0 EB");
+        assert_eq!(format_bytes(u64::MAX), "18.4 EB");
+        assert_eq!(format_bytes_binary(u64::MAX), "16.0 EiB");
+    }
+
+    #[test]
+    fn test_format_bits() {
+        assert_eq!(format_bits(1_000_000), "1.00 Mbps");
+        assert_eq!(format_bits(0), "0 bps");
+        assert_eq!(format_bits(999), "999 bps");
+        assert_eq!(format_bits(12_500_000_000), "12.5 Gbps");
+
+        let options = FormatOptions { net_unit: NetUnit::Bits, ..Default::default() };
+        assert_eq!(options.format_net_speed(12_500_000), "100 Mbps");
+        let snapshot = MetricsSnapshot {
+            net_rx_bytes: Some(0),
+            net_rx_per_sec: Some(187_500),
+            disk_read_per_sec: 187_500,
+            ..Default::default()
+        };
+        let formatted = snapshot.format_with(&options);
+        assert_eq!(formatted.net_rx, "1.50 Mbps");
+        assert_eq!(formatted.disk_read, "188 KB/s");
+    }
+
+    #[test]
+    fn test_format_bytes_precision() {
+        assert_eq!(format_bytes_precision(1536, 0), "2 KB");
+        assert_eq!(format_bytes_precision(1536, 1), "1.5 KB");
+        assert_eq!(format_bytes_precision(150_000_000_000, 2), "150.00 GB");
+        assert_eq!(format_bytes_precision(512, 2), "512 B");
+        assert_eq!(format_bytes_precision(0, 2), "0 B");
+        // Rounding up to the base moves to the next unit at any precision
+        assert_eq!(format_bytes_precision(999_600, 0), "1 MB");
+        assert_eq!(format_bytes_precision(999_996, 2), "1.00 MB");
+        assert_eq!(format_bytes_precision(999_994, 2), "999.99 KB");
+        assert_eq!(Units::Binary.format_speed_with(1536, Some(3)), "1.500 KiB/s");
+    }
+
+    #[test]
+    fn test_format_bytes_binary() {
+        assert_eq!(format_bytes_binary(0), "0 B");
+        assert_eq!(format_bytes_binary(1000), "1000 B");
+        assert_eq!(format_bytes_binary(1024), "1.00 KiB");
+        assert_eq!(format_bytes_binary(1536), "1.50 KiB");
+        assert_eq!(format_bytes_binary(1_048_576), "1.00 MiB");
+        assert_eq!(format_bytes_binary(16 * 1024 * 1024 * 1024), "16.0 GiB");
+    }
+
+    #[test]
+    fn test_units_for_rates() {
+        assert_eq!(Units::Si.format_speed(1_000_000), "1.00 MB/s");
+        assert_eq!(Units::Binary.format_speed(1_000_000), "977 KiB/s");
+        assert_eq!(Units::Bits.format_speed(1_000_000), "8.00 Mbps");
+        assert_eq!(Units::Bits.format_bytes(1_000_000), "1.00 MB");
+        assert_eq!(Units::from_str("decimal", true), Ok(Units::Si));
+
+        let snapshot = MetricsSnapshot {
+            memory_used_bytes: 4_000_000_000,
+            memory_total_bytes: 16_000_000_000,
+            disk_write_per_sec: 1_000_000,
+            net_tx_bytes: Some(0),
+            net_tx_per_sec: Some(125_000),
+            ..Default::default()
+        };
+        let formatted = snapshot.format_with(&FormatOptions { units: Units::Bits, ..Default::default() });
+        assert_eq!(formatted.memory_used, "4.00 GB");
+        assert_eq!(formatted.disk_write, "8.00 Mbps");
+        assert_eq!(formatted.net_tx, "1.00 Mbps");
+    }
+
+    #[test]
+    fn test_units_boundaries() {
+        assert_eq!(Units::Si.format_bytes(1023), "1.02 KB");
+        assert_eq!(Units::Si.format_bytes(1024), "1.02 KB");
+        assert_eq!(Units::Si.format_bytes(1_048_576), "1.05 MB");
+        assert_eq!(Units::Binary.format_bytes(1023), "1023 B");
+        assert_eq!(Units::Binary.format_bytes(1024), "1.00 KiB");
+        assert_eq!(Units::Binary.format_bytes(1_048_576), "1.00 MiB");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(format_duration(Duration::from_millis(59_900)), "59s");
+        assert_eq!(format_duration(Duration::from_secs(60)), "1m");
+        assert_eq!(format_duration(Duration::from_secs(3 * 3600 + 5 * 60 + 7)), "3h 5m");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1h 0m");
+        assert_eq!(format_duration(Duration::from_secs(3 * 86_400 + 4 * 3600 + 12 * 60)), "3d 4h 12m");
+        assert_eq!(format_duration(Duration::from_secs(400 * 86_400 + 59)), "400d 0h 0m");
+    }
+
     #[test]
     fn test_format_speed() {
         assert_eq!(format_speed(1_500_000), "1.50 MB/s");
         assert_eq!(format_speed(150_000_000), "150 MB/s");
     }
-}
 
-pub fn print_once() -> Result<(), Box<dyn Error>> {
-    println!("{:?}", collect_metrics().format());
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("json".parse(), Ok(OutputFormat::Json));
+        assert_eq!("ndjson".parse(), Ok(OutputFormat::Ndjson));
+        assert_eq!("line".parse(), Ok(OutputFormat::Line));
+        assert_eq!("columns".parse(), Ok(OutputFormat::Columns { header_every: HEADER_EVERY }));
+        assert!(matches!("cpu={cpu}".parse(), Ok(OutputFormat::Template(_))));
+        assert!("yaml".parse::<OutputFormat>().is_err());
+        assert!("cpu={nope}".parse::<OutputFormat>().unwrap_err().contains("{nope}"));
+    }
 
-    Ok(())
+    #[test]
+    fn test_process_target_from_str() {
+        assert_eq!("1234".parse(), Ok(ProcessTarget::Pid(1234)));
+        assert_eq!("nginx".parse(), Ok(ProcessTarget::Name("nginx".to_string())));
+        assert!("".parse::<ProcessTarget>().is_err());
+    }
+
+    #[test]
+    fn test_timestamp_formats() {
+        let timestamp = DateTime::parse_from_rfc3339("2024-05-01T12:00:00.250+00:00")
+            .unwrap()
+            .with_timezone(&Local);
+
+        assert_eq!("unix".parse::<TimestampFormat>().unwrap().format(&timestamp), "1714564800");
+        assert_eq!("unix-ms".parse::<TimestampFormat>().unwrap().format(&timestamp), "1714564800250");
+        assert_eq!(
+            "%Y".parse::<TimestampFormat>().unwrap().format(&timestamp),
+            timestamp.format("%Y").to_string()
+        );
+        assert!("%Q".parse::<TimestampFormat>().is_err());
+        assert!("iso".parse::<TimestampFormat>().is_err());
+    }
+
+    #[test]
+    fn test_no_timestamp_drops_column_and_key() {
+        let snapshot = MetricsSnapshot { memory_total_bytes: 1, ..Default::default() };
+        let options = FormatOptions { timestamp: None, ..Default::default() };
+
+        assert!(csv_header(&options).starts_with("cpu_percent,"));
+        assert!(snapshot.to_json(&options).unwrap().get("timestamp").is_none());
+
+        let unix = FormatOptions { timestamp: Some(TimestampFormat::Unix), ..Default::default() };
+        assert_eq!(snapshot.to_json(&unix).unwrap()["timestamp"], serde_json::json!(0));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_collect_metrics_async() {
+        let snapshot = collect_metrics_async(Duration::from_millis(200)).await.unwrap();
+
+        assert!(snapshot.memory_total_bytes > 0);
+        assert!(snapshot.elapsed >= Duration::from_millis(200));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_round_trips_through_json() {
+        let snapshot = MetricsSnapshot {
+            cpu_usage_percent: 12.5,
+            load_average: Some((0.5, 0.25, 0.125)),
+            temperatures: vec![("CPU".to_string(), 61.5)],
+            memory_used_bytes: 4_100_000_000,
+            per_disk: vec![DiskInfo { name: "/dev/sda1".to_string(), mount_point: "/".to_string(), ..Default::default() }],
+            alerts: vec!["cpu".to_string()],
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let decoded: MetricsSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+        assert_eq!(decoded.timestamp, snapshot.timestamp);
+        assert_eq!(decoded.load_average, Some((0.5, 0.25, 0.125)));
+        assert_eq!(decoded.per_disk[0].name, "/dev/sda1");
+
+        let formatted = snapshot.format();
+        let decoded: FormattedMetrics = serde_json::from_str(&serde_json::to_string(&formatted).unwrap()).unwrap();
+        assert_eq!(decoded.to_string(), formatted.to_string());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("0.5s"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration(" 2 s "), Ok(Duration::from_secs(2)));
+
+        assert!(parse_duration("").is_err());
+        assert_eq!(parse_duration("0"), Err("invalid duration '0': must be greater than zero".to_string()));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("0ms").is_err());
+        assert!(parse_duration("-5s").unwrap_err().contains("greater than zero"));
+        assert!(parse_duration("-0.5").is_err());
+        assert!(parse_duration("1.2.3").is_err());
+        assert!(parse_duration("5d").is_err());
+        assert!(parse_duration("abc").is_err());
+    }
+
+    #[test]
+    fn test_fmt_line() {
+        let snapshot = MetricsSnapshot {
+            cpu_usage_percent: 12.34,
+            memory_used_bytes: 4_100_000_000,
+            memory_total_bytes: 16_000_000_000,
+            disk_write_per_sec: 2_370_000,
+            net_rx_bytes: Some(1_200_000),
+            net_tx_bytes: Some(300_000),
+            net_rx_per_sec: Some(1_200_000),
+            net_tx_per_sec: Some(300_000),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            snapshot.format().fmt_line(),
+            "cpu=12.3% mem=25.6% mem_used=4.10GB mem_total=16.0GB swap=0B/0B read=0B/s write=2.37MB/s rx=1.20MB/s tx=300KB/s"
+        );
+
+        // --format line gives a one-shot sample the same single line
+        let mut out = Vec::new();
+        let options = FormatOptions { timestamp: None, ..Default::default() };
+        write_snapshot(&mut out, &snapshot, &OutputFormat::Line, &options).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{}\n", snapshot.format().fmt_line()));
+    }
+
+    #[test]
+    fn test_aligned_line_keeps_its_length() {
+        const PB: u64 = 1_000_000_000_000_000;
+        let idle = MetricsSnapshot {
+            net_rx_bytes: Some(0),
+            net_tx_bytes: Some(0),
+            net_rx_per_sec: Some(0),
+            net_tx_per_sec: Some(0),
+            ..Default::default()
+        };
+        let busy = MetricsSnapshot {
+            cpu_usage_percent: 100.0,
+            memory_used_bytes: 999 * PB,
+            memory_total_bytes: 999 * PB,
+            swap_used_bytes: 999 * PB,
+            swap_total_bytes: 999 * PB,
+            disk_read_per_sec: 999 * PB,
+            disk_write_per_sec: 999 * PB,
+            net_rx_per_sec: Some(999 * PB),
+            net_tx_per_sec: Some(999 * PB),
+            ..idle.clone()
+        };
+
+        let line = |snapshot: &MetricsSnapshot| snapshot.format().fmt_aligned_line();
+        assert_eq!(
+            line(&idle),
+            "cpu=  0.0% mem=   N/A mem_used=        0B mem_total=        0B swap=        0B/        0B \
+             read=      0B/s write=      0B/s rx=      0B/s tx=      0B/s"
+        );
+        assert_eq!(line(&busy).len(), line(&idle).len(), "{}", line(&busy));
+        assert!(line(&busy).contains("read=   999PB/s "), "{}", line(&busy));
+
+        let binary = FormatOptions { units: Units::Binary, ..Default::default() };
+        assert_eq!(busy.format_with(&binary).fmt_aligned_line().len(), line(&idle).len());
+    }
+
+    #[test]
+    fn test_json_fields_round_trip() {
+        let snapshot = MetricsSnapshot {
+            timestamp: Local::now(),
+            elapsed: Duration::from_secs(1),
+            cpu_usage_percent: 12.5,
+            per_core_usage: vec![10.0, 15.0],
+            memory_used_bytes: 4_100_000_000,
+            memory_total_bytes: 16_000_000_000,
+            swap_used_bytes: 5,
+            swap_total_bytes: 6,
+            disk_read_bytes: 1,
+            disk_write_bytes: 2,
+            disk_read_per_sec: 9,
+            disk_write_per_sec: 10,
+            net_rx_bytes: Some(3),
+            net_tx_bytes: Some(4),
+            net_rx_per_sec: Some(7),
+            net_tx_per_sec: Some(8),
+            ..Default::default()
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+        let timestamp = json["timestamp"].as_str().unwrap();
+        assert_eq!(DateTime::parse_from_rfc3339(timestamp).unwrap(), snapshot.timestamp);
+        assert_eq!(json["elapsed_ms"].as_u64(), Some(1000));
+        assert_eq!(json["cpu_usage_percent"].as_f64(), Some(12.5));
+        assert_eq!(json["per_core_usage"], serde_json::json!([10.0, 15.0]));
+        assert_eq!(json["memory_used_bytes"].as_u64(), Some(4_100_000_000));
+        assert_eq!(json["memory_total_bytes"].as_u64(), Some(16_000_000_000));
+        assert_eq!(json["swap_used_bytes"].as_u64(), Some(5));
+        assert_eq!(json["swap_total_bytes"].as_u64(), Some(6));
+        assert_eq!(json["disk_read_bytes"].as_u64(), Some(1));
+        assert_eq!(json["disk_write_bytes"].as_u64(), Some(2));
+        assert_eq!(json["disk_read_per_sec"].as_u64(), Some(9));
+        assert_eq!(json["disk_write_per_sec"].as_u64(), Some(10));
+        assert_eq!(json["net_rx_bytes"].as_u64(), Some(3));
+        assert_eq!(json["net_tx_bytes"].as_u64(), Some(4));
+        assert_eq!(json["net_rx_per_sec"].as_u64(), Some(7));
+        assert_eq!(json["net_tx_per_sec"].as_u64(), Some(8));
+    }
+
+    #[test]
+    fn test_structured_output_names_its_schema() {
+        let snapshot = MetricsSnapshot::default();
+        let json = snapshot.to_json(&FormatOptions::default()).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        let keys: Vec<&String> = json.as_object().unwrap().keys().take(3).collect();
+        assert_eq!(keys, ["timestamp", "schema_version", "version"]);
+
+        let mut out = Vec::new();
+        write_snapshot(&mut out, &snapshot, &OutputFormat::Csv, &FormatOptions::default()).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        assert!(csv.starts_with(&format!("# schema_version={} system-monitor ", SCHEMA_VERSION)));
+        assert!(csv.lines().nth(1).unwrap().starts_with("timestamp,"));
+    }
+
+    #[test]
+    fn test_csv_row_matches_header() {
+        let snapshot = MetricsSnapshot {
+            cpu_usage_percent: 12.5,
+            memory_used_bytes: 4_100_000_000,
+            memory_total_bytes: 16_000_000_000,
+            disk_write_bytes: 2_370_000,
+            net_rx_bytes: Some(0),
+            net_tx_bytes: Some(0),
+            ..Default::default()
+        };
+        let header = csv_header(&FormatOptions::default());
+        let row = snapshot.to_csv_row(&FormatOptions::default());
+
+        assert_eq!(
+            header,
+            "timestamp,cpu_percent,mem_used,mem_total,swap_used,swap_total,disk_read,disk_write,net_rx,net_tx"
+        );
+        assert_eq!(row.split(',').count(), header.split(',').count());
+        assert!(row.ends_with(",12.5,4100000000,16000000000,0,0,0,2370000,0,0"));
+
+        let skipped = MetricsSnapshot { net_rx_bytes: None, net_tx_bytes: None, ..snapshot };
+        assert!(skipped.to_csv_row(&FormatOptions::default()).ends_with(",2370000,,"));
+    }
+
+    #[test]
+    fn test_color_only_when_enabled() {
+        let snapshot = MetricsSnapshot {
+            cpu_usage_percent: 90.0,
+            per_core_usage: vec![90.0],
+            memory_used_bytes: 10,
+            memory_total_bytes: 100,
+            ..Default::default()
+        };
+
+        let plain = snapshot.format_with(&FormatOptions::default());
+        assert!(!plain.to_string().contains("\x1b["));
+        assert!(!plain.fmt_line().contains("\x1b["));
+
+        let colored = snapshot.format_with(&FormatOptions { color: true, ..Default::default() });
+        assert_eq!(colored.cpu_usage, "90.0%");
+        assert!(colored.to_string().contains("\x1b[31m90.0%\x1b[0m"));
+        assert!(colored.to_string().contains("\x1b[32m10.0%\x1b[0m"));
+    }
+
+    #[test]
+    fn test_watched_process_in_every_format() {
+        let process = WatchedProcess {
+            pid: 42,
+            name: "nginx".to_string(),
+            cpu_usage_percent: 2.5,
+            memory_bytes: 12_000_000,
+            virtual_memory_bytes: 1_200_000_000,
+            disk_write_bytes: 4000,
+            disk_write_per_sec: 4000,
+            ..Default::default()
+        };
+        let snapshot = MetricsSnapshot { process: Some(process), process_requested: true, ..Default::default() };
+        let metrics = snapshot.format();
+
+        assert!(metrics.to_string().contains(
+            "  Process:         nginx (42)  cpu 2.5%  rss 12.0 MB  virt 1.20 GB  read 0 B/s  write 4.00 KB/s\n"
+        ));
+        assert!(metrics.fmt_line().ends_with("pid=42 pcpu=2.5% rss=12.0MB virt=1.20GB pread=0B/s pwrite=4.00KB/s"));
+        assert_eq!(snapshot.to_json(&FormatOptions::default()).unwrap()["process"]["virtual_memory_bytes"], 1_200_000_000);
+
+        let options = FormatOptions { fields: Fields::from_list(&[]), timestamp: None, process: true, ..Default::default() };
+        assert_eq!(csv_header(&options), "process_pid,process_cpu_percent,process_rss,process_virtual,process_disk_read,process_disk_write");
+        assert_eq!(snapshot.to_csv_row(&options), "42,2.5,12000000,1200000000,0,4000");
+
+        let group = WatchedProcess { processes: 4, ..snapshot.process.clone().unwrap() };
+        let grouped = MetricsSnapshot { process: Some(group), process_requested: true, ..Default::default() };
+        assert!(grouped.format().to_string().contains("  Process:         nginx (4 processes)  cpu 2.5%"));
+        assert!(grouped.format().fmt_line().contains(" pid=42 procs=4 pcpu=2.5% "));
+
+        let gone = MetricsSnapshot { process_requested: true, ..Default::default() };
+        assert!(gone.format().to_string().contains("  Process:         not running\n"));
+        assert!(gone.format().fmt_line().ends_with("pid=none"));
+        assert_eq!(gone.to_csv_row(&options), ",,,,,");
+        assert!(!MetricsSnapshot::default().format().to_string().contains("Process:"));
+    }
+
+    #[test]
+    fn test_display_top_processes() {
+        let snapshot = MetricsSnapshot {
+            memory_total_bytes: 1,
+            top_processes: vec![ProcessInfo {
+                pid: 42,
+                name: "cargo".to_string(),
+                cpu_usage_percent: 150.0,
+                memory_bytes: 120_000_000,
+            }],
+            ..Default::default()
+        };
+        let text = snapshot.format().to_string();
+
+        assert!(text.contains("  Top Processes:\n"));
+        assert!(text.contains("         42  150.0%     120 MB  cargo\n"));
+        assert!(!text.contains("by Memory"));
+
+        let by_memory = MetricsSnapshot { top_memory_processes: snapshot.top_processes.clone(), ..snapshot };
+        assert!(by_memory.format().to_string().contains(
+            "  Top Processes by Memory:\n        PID     CPU        MEM  NAME\n         42  150.0%     120 MB  cargo\n"
+        ));
+        assert!(!MetricsSnapshot::default().format().to_string().contains("Top Processes"));
+    }
+
+    #[test]
+    fn test_raw_row() {
+        let snapshot = MetricsSnapshot {
+            cpu_usage_percent: 12.5,
+            memory_used_bytes: 4_100_000_000,
+            memory_total_bytes: 16_000_000_000,
+            net_rx_bytes: Some(1_500),
+            net_tx_bytes: Some(0),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            snapshot.to_raw_row(&Fields::all()),
+            "12.5\t4100000000\t16000000000\t0\t0\t0\t0\t1500\t0"
+        );
+        assert_eq!(
+            snapshot.to_raw_row(&Fields::from_list(&[Field::Net, Field::Cpu])),
+            "12.5\t1500\t0"
+        );
+    }
+
+    #[test]
+    fn test_memory_percent_without_total() {
+        let snapshot = MetricsSnapshot {
+            memory_used_bytes: 1_000,
+            memory_total_bytes: 0,
+            ..Default::default()
+        };
+
+        assert_eq!(snapshot.memory_usage_percent(), None);
+        assert_eq!(snapshot.format().memory_usage_percent, "N/A");
+    }
+
+    #[test]
+    fn test_memory_percent_on_available() {
+        let snapshot = MetricsSnapshot {
+            memory_used_bytes: 9_000,
+            memory_total_bytes: 10_000,
+            memory_available_bytes: 8_000,
+            memory_free_bytes: 1_000,
+            ..Default::default()
+        };
+        let available = FormatOptions { mem_basis: MemBasis::Available, ..Default::default() };
+
+        assert_eq!(snapshot.memory_percent(MemBasis::Used), Some(90.0));
+        assert_eq!(snapshot.memory_percent(MemBasis::Available), Some(20.0));
+        assert_eq!(snapshot.format_with(&available).memory_usage_percent, "20.0%");
+        assert!(snapshot.format_with(&available).to_string().contains("  Available:       8.00 KB (free 1.00 KB)\n"));
+
+        // Nothing to go on: stay with used memory
+        let old = MetricsSnapshot { memory_available_bytes: 0, ..snapshot };
+        assert_eq!(old.memory_percent(MemBasis::Available), Some(90.0));
+    }
+
+    #[test]
+    fn test_swap_percent() {
+        let snapshot = MetricsSnapshot {
+            swap_used_bytes: 250,
+            swap_total_bytes: 1_000,
+            ..Default::default()
+        };
+        let formatted = snapshot.format();
+
+        assert_eq!(formatted.swap_usage_percent, "25.0%");
+        assert!(formatted.to_string().contains("  Swap:            250 B / 1.00 KB (25.0%)\n"));
+
+        let no_swap = MetricsSnapshot::default();
+        assert_eq!(no_swap.format().swap_usage_percent, "N/A");
+    }
+
+    #[test]
+    fn test_display_lists_each_core() {
+        let snapshot = MetricsSnapshot {
+            per_core_usage: vec![12.34, 100.0],
+            memory_total_bytes: 1,
+            ..Default::default()
+        };
+        assert!(!snapshot.format().to_string().contains("Cores:"));
+
+        let per_core = FormatOptions { per_core: true, ..Default::default() };
+        let formatted = snapshot.format_with(&per_core);
+        assert!(formatted.to_string().contains("  Cores:           cpu0 12.3%   cpu1 100.0%\n"));
+        assert!(formatted.fmt_line().starts_with("cpu=0.0% cores=12.3%,100.0% mem="));
+
+        // Too narrow for two cells: one core per line, aligned under the first
+        let narrow = FormatOptions { width: Some(35), ..per_core };
+        let text = snapshot.format_with(&narrow).to_string();
+        assert!(text.contains("  Cores:           cpu0 12.3%\n                   cpu1 100.0%\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_trends_follow_their_rows() {
+        let history: Vec<MetricsSnapshot> = [10.0, 40.0, 80.0]
+            .iter()
+            .map(|&cpu| MetricsSnapshot { cpu_usage_percent: cpu, memory_total_bytes: 1, ..Default::default() })
+            .collect();
+        let mut metrics = history[2].format();
+        metrics.trends = Trends::from_history(&history, 2);
+
+        assert_eq!(metrics.trends.cpu, "▅█");
+        assert!(metrics.to_string().contains("  CPU Usage:       80.0%                     ▅█\n"));
+        assert!(metrics.to_string().contains("  Memory:          0 B / 1 B (0.0%)          ▁▁\n"));
+        assert!(!history[2].format().to_string().contains('▁'));
+    }
+
+    #[test]
+    fn test_deltas_compare_with_the_previous_sample() {
+        let previous = MetricsSnapshot {
+            cpu_usage_percent: 42.1,
+            memory_used_bytes: 500,
+            memory_total_bytes: 1_000,
+            disk_read_per_sec: 1_500_000,
+            net_rx_per_sec: Some(1_000),
+            ..Default::default()
+        };
+        let current = MetricsSnapshot {
+            cpu_usage_percent: 45.2,
+            disk_read_per_sec: 300_000,
+            net_rx_per_sec: Some(1_000),
+            ..previous.clone()
+        };
+        let mut metrics = current.format();
+        metrics.deltas = Deltas::between(&previous, &current, &FormatOptions::default());
+
+        assert_eq!(metrics.deltas.cpu, "+3.1");
+        assert_eq!(metrics.deltas.memory, "±0.0");
+        assert_eq!(metrics.deltas.disk_read, "-1.20 MB/s");
+        assert_eq!(metrics.deltas.net_rx, "±0 B/s");
+        // No rate to compare when the network wasn't collected
+        assert_eq!(metrics.deltas.net_tx, "");
+        let text = metrics.to_string();
+        assert!(text.contains("  CPU Usage:       45.2% (+3.1)\n"), "{text}");
+        assert!(text.contains("  Disk Read:       300 KB/s (-1.20 MB/s)\n"), "{text}");
+        assert!(!current.format().to_string().contains("(+"));
+    }
+
+    #[test]
+    fn test_gpu_shown_only_when_requested() {
+        let gpu = GpuInfo { devices: 1, utilization_percent: 42.0, memory_used_bytes: 2_000_000_000, memory_total_bytes: 8_000_000_000 };
+        let snapshot = MetricsSnapshot { gpu: Some(gpu), gpu_requested: true, ..Default::default() };
+        let metrics = snapshot.format();
+
+        assert!(metrics.to_string().contains("  GPU:             42.0%  VRAM 2.00 GB / 8.00 GB\n"));
+        assert!(metrics.fmt_line().ends_with("gpu=42.0% vram=2.00GB/8.00GB"));
+        assert_eq!(snapshot.to_json(&FormatOptions::default()).unwrap()["gpu"]["utilization_percent"], 42.0);
+
+        let unavailable = MetricsSnapshot { gpu_requested: true, ..Default::default() };
+        assert!(unavailable.format().to_string().contains("  GPU:             unavailable\n"));
+        assert!(unavailable.format().fmt_line().ends_with("gpu=unavailable"));
+        assert!(unavailable.to_json(&FormatOptions::default()).unwrap().get("gpu").is_none());
+
+        assert!(!MetricsSnapshot::default().format().to_string().contains("GPU"));
+    }
+
+    #[test]
+    fn test_load_average_only_when_present() {
+        let snapshot = MetricsSnapshot {
+            load_average: Some((0.42, 0.55, 0.6)),
+            memory_total_bytes: 1,
+            ..Default::default()
+        };
+
+        assert!(snapshot.format().to_string().contains("  Load:            0.42 0.55 0.60\n"));
+        assert_eq!(
+            snapshot.to_json(&FormatOptions::default()).unwrap()["load_average"],
+            serde_json::json!([0.42, 0.55, 0.6])
+        );
+
+        let snapshot = MetricsSnapshot { memory_total_bytes: 1, ..Default::default() };
+        assert!(!snapshot.format().to_string().contains("Load:"));
+        assert!(snapshot.to_json(&FormatOptions::default()).unwrap()["load_average"].is_null());
+    }
+
+    #[test]
+    fn test_display_process_count() {
+        let mut snapshot = MetricsSnapshot { process_count: 312, memory_total_bytes: 1, ..Default::default() };
+        assert!(snapshot.format().to_string().contains("  Processes:       312\n"));
+
+        snapshot.thread_count = Some(1840);
+        assert!(snapshot.format().to_string().contains("  Processes:       312 (1840 threads)\n"));
+        assert_eq!(snapshot.to_json(&FormatOptions::default()).unwrap()["thread_count"], 1840);
+    }
+
+    #[test]
+    fn test_display_temperatures() {
+        let snapshot = MetricsSnapshot {
+            temperatures: vec![("CPU Temp".to_string(), 62.0)],
+            memory_total_bytes: 1,
+            ..Default::default()
+        };
+
+        assert!(snapshot.format().to_string().contains("  Temperatures:\n    CPU Temp: 62.0°C\n"));
+        assert!(!MetricsSnapshot::default().format().to_string().contains("Temperatures"));
+    }
+
+    #[test]
+    fn test_display_per_disk() {
+        let snapshot = MetricsSnapshot {
+            memory_total_bytes: 1,
+            per_disk: vec![DiskInfo {
+                mount_point: "/".to_string(),
+                total_bytes: 500_000_000_000,
+                available_bytes: 120_000_000_000,
+                write_per_sec: 1_200_000,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let text = snapshot.format().to_string();
+
+        assert!(text.contains("  Disks:\n    /              read 0 B/s  write 1.20 MB/s  free 120 GB / 500 GB\n"));
+        assert!(!MetricsSnapshot::default().format().to_string().contains("Disks:"));
+
+        let named = MetricsSnapshot {
+            memory_total_bytes: 1,
+            per_disk: vec![
+                DiskInfo { name: "/dev/sda1".to_string(), mount_point: "/".to_string(), ..Default::default() },
+                DiskInfo { name: "tmpfs".to_string(), mount_point: "/tmp".to_string(), ..Default::default() },
+            ],
+            ..Default::default()
+        };
+        let text = named.format().to_string();
+        assert!(text.contains("    /              /dev/sda1  read 0 B/s"), "{}", text);
+        assert!(text.contains("    /tmp           tmpfs      read 0 B/s"), "{}", text);
+    }
+
+    #[test]
+    fn test_display_per_interface() {
+        let snapshot = MetricsSnapshot {
+            memory_total_bytes: 1,
+            per_interface: vec![
+                InterfaceInfo { name: "eth0".to_string(), rx_per_sec: 1_200_000, ..Default::default() },
+                InterfaceInfo { name: "tun0".to_string(), tx_per_sec: 300_000, ..Default::default() },
+            ],
+            net_rx_bytes: Some(0),
+            ..Default::default()
+        };
+        let text = snapshot.format().to_string();
+
+        assert!(text.contains(
+            "  Network:\n    eth0           rx 1.20 MB/s  tx 0 B/s\n    tun0           rx 0 B/s  tx 300 KB/s\n"
+        ));
+    }
+
+    #[test]
+    fn test_skipped_network_is_left_out() {
+        let snapshot = MetricsSnapshot { memory_total_bytes: 1, ..Default::default() };
+        let formatted = snapshot.format();
+
+        assert!(!formatted.to_string().contains("Network"));
+        assert!(!formatted.fmt_line().contains("rx="));
+        assert!(serde_json::to_value(&snapshot).unwrap().get("net_rx_bytes").is_none());
+    }
+
+    #[test]
+    fn test_collect_options_select_groups() {
+        assert_eq!(CollectOptions::default().fields(), Fields { cpu: true, mem: true, disk: false, net: false });
+        assert_eq!(CollectOptions::all().fields(), Fields::all());
+        assert!(!CollectOptions::default().temperature);
+
+        let snapshot = MetricsSnapshot {
+            collected: CollectOptions::default().fields(),
+            cpu_usage_percent: 12.5,
+            memory_total_bytes: 1,
+            ..Default::default()
+        };
+        let formatted = snapshot.format();
+        assert!(!formatted.to_string().contains("Disk"));
+        assert_eq!(formatted.fmt_line(), "cpu=12.5% mem=0.0% mem_used=0B mem_total=1B swap=0B/0B");
+        assert!(snapshot.to_csv_row(&FormatOptions::default()).ends_with(",12.5,0,1,0,0,,,,"));
+        assert!(snapshot.to_json(&FormatOptions::default()).unwrap().get("disk_read_bytes").is_none());
+    }
+
+    #[test]
+    fn test_fields_limit_every_format() {
+        let fields = Fields::from_list(&[Field::Cpu, Field::Net]);
+        let snapshot = MetricsSnapshot {
+            cpu_usage_percent: 50.0,
+            memory_total_bytes: 1,
+            net_rx_bytes: Some(1_500),
+            net_tx_bytes: Some(0),
+            net_rx_per_sec: Some(1_500),
+            net_tx_per_sec: Some(0),
+            ..Default::default()
+        };
+        let options = FormatOptions { fields, ..Default::default() };
+        let formatted = snapshot.format_with(&options);
+
+        assert_eq!(formatted.fmt_line(), "cpu=50.0% rx=1.50KB/s tx=0B/s");
+        assert!(!formatted.to_string().contains("Memory"));
+        assert_eq!(csv_header(&options), "timestamp,cpu_percent,net_rx,net_tx");
+
+        let json = snapshot.to_json(&options).unwrap();
+        assert!(json.get("timestamp").is_some());
+        assert!(json.get("per_core_usage").is_some());
+        assert!(json.get("net_tx_bytes").is_some());
+        assert!(json.get("memory_used_bytes").is_none());
+        assert!(json.get("swap_used_bytes").is_none());
+        assert!(json.get("disk_read_bytes").is_none());
+    }
 }
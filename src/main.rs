@@ -1,11 +1,19 @@
-use std::error::Error;
+use std::process::ExitCode;
 use crate::run::run;
 
 mod args;
+mod config;
 mod run;
+mod sink;
+#[cfg(feature = "tui")]
+mod tui;
 
-fn main() -> Result<(), Box<dyn Error>>{
-    run()?;
-    
-    Ok(())
+fn main() -> ExitCode {
+    match run() {
+        Ok(status) => ExitCode::from(status.exit_code()),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,642 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use regex::Regex;
+use sysinfo::{
+    Components, Disk, Disks, MINIMUM_CPU_UPDATE_INTERVAL, Networks, Pid, Process, ProcessRefreshKind, ProcessStatus,
+    ProcessesToUpdate, System, ThreadKind,
+};
+
+use crate::{
+    CollectError, CollectOptions, DiskInfo, GpuInfo, InterfaceInfo, MetricsSnapshot, ProcessInfo, ProcessTarget,
+    WatchedProcess,
+};
+
+/// Long-lived sysinfo state for taking sample after sample
+///
+/// CPU usage, disk and network counters are all deltas between two
+/// refreshes. Keeping the `System`, `Disks` and `Networks` around means each
+/// [`sample`](Monitor::sample) needs one refresh instead of two, and sysinfo
+/// keeps the CPU history it needs for accurate figures. Groups left out of
+/// the [`CollectOptions`] are never built or refreshed.
+pub struct Monitor {
+    system: System,
+    disks: Option<Disks>,
+    networks: Option<Networks>,
+    components: Option<Components>,
+    /// Loaded once; `None` without an NVIDIA driver, which leaves the GPU unavailable
+    #[cfg(feature = "gpu")]
+    nvml: Option<nvml_wrapper::Nvml>,
+    options: CollectOptions,
+    last_refresh: Instant,
+    /// The watched process as of the last sample, as "name (pid)"
+    watched: Option<String>,
+    /// Set when the watched process disappeared, until taken
+    exited: Option<String>,
+}
+
+impl Monitor {
+    /// A monitor collecting every metric group
+    pub fn new() -> Self {
+        Monitor::with_options(CollectOptions::all())
+    }
+
+    /// Create a monitor and take the baseline the first sample is measured against
+    pub fn with_options(options: CollectOptions) -> Self {
+        let mut monitor = Monitor {
+            system: System::new(),
+            disks: options.disk.then(Disks::new_with_refreshed_list),
+            networks: options.network.then(Networks::new_with_refreshed_list),
+            components: options.temperature.then(Components::new_with_refreshed_list),
+            #[cfg(feature = "gpu")]
+            nvml: if options.gpu { nvml_wrapper::Nvml::init().ok() } else { None },
+            options,
+            last_refresh: Instant::now(),
+            watched: None,
+            exited: None,
+        };
+        monitor.refresh();
+        let found = monitor.find_processes();
+        monitor.watched = (!found.is_empty()).then(|| describe(&found));
+
+        monitor
+    }
+
+    /// Start the next sample's window now, dropping the deltas since the last refresh
+    ///
+    /// For resuming after a pause: without it the first sample would average
+    /// the whole pause, or report all of its traffic as one burst.
+    pub fn rebaseline(&mut self) {
+        self.refresh();
+    }
+
+    /// Refresh in place and return the deltas since the previous call, or
+    /// since [`Monitor::new`] for the first sample
+    ///
+    /// Callers pace the samples themselves; the window is however long it has
+    /// been since the last refresh. When CPU is collected the window is at
+    /// least [`MINIMUM_CPU_UPDATE_INTERVAL`] (200ms on most platforms), since
+    /// sysinfo can't measure usage over less: a shorter one is waited out
+    /// here, so a sample taken right after [`Monitor::new`] still reports
+    /// real CPU usage at the cost of that much latency.
+    pub fn sample(&mut self) -> Result<MetricsSnapshot, CollectError> {
+        if self.options.cpu {
+            let since = self.last_refresh.elapsed();
+            if since < MINIMUM_CPU_UPDATE_INTERVAL {
+                std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL - since);
+            }
+        }
+        let elapsed = self.refresh();
+        let timestamp = Local::now();
+        // Temperatures are point readings, so they skip the baseline refresh
+        if let Some(components) = &mut self.components {
+            components.refresh(true);
+        }
+        let system = &self.system;
+
+        if self.options.cpu && system.cpus().is_empty() {
+            return Err(CollectError::NoCpus);
+        }
+        if self.options.memory && system.total_memory() == 0 {
+            return Err(CollectError::NoMemory);
+        }
+
+        let (process_count, thread_count) = process_counts(system);
+        let (top_processes, top_memory_processes) = match self.options.top_processes {
+            Some(limit) => {
+                let processes = processes(system);
+                (top_by_cpu(processes.clone(), limit), top_by_memory(processes, limit))
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+
+        let found = self.find_processes();
+        let process = (!found.is_empty()).then(|| watched_process(&found, elapsed));
+        let seen = (!found.is_empty()).then(|| describe(&found));
+        if seen.is_none() && let Some(previous) = self.watched.take() {
+            self.exited = Some(previous);
+        }
+        self.watched = seen;
+
+        let mut disks: Vec<DiskInfo> = self
+            .disks
+            .iter()
+            .flat_map(|disks| disks.iter())
+            .filter(|disk| self.counts_disk(disk))
+            .map(|disk| {
+                let usage = disk.usage();
+                DiskInfo {
+                    name: disk.name().to_string_lossy().into_owned(),
+                    mount_point: disk.mount_point().display().to_string(),
+                    total_bytes: disk.total_space(),
+                    available_bytes: disk.available_space(),
+                    read_bytes: usage.read_bytes,
+                    written_bytes: usage.written_bytes,
+                    read_per_sec: per_second(usage.read_bytes, elapsed),
+                    write_per_sec: per_second(usage.written_bytes, elapsed),
+                }
+            })
+            .collect();
+        // Same order every sample, whatever order the OS lists mounts in
+        disks.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+
+        // Disk I/O (sum all devices) - bytes since the last refresh
+        let (disk_read, disk_write) = dedup_disks(&disks)
+            .iter()
+            .fold((0, 0), |(read, write), disk| (read + disk.read_bytes, write + disk.written_bytes));
+
+        let per_disk = if self.options.per_disk {
+            disks.retain(|disk| !self.options.hide_idle || disk.read_bytes > 0 || disk.written_bytes > 0);
+            disks
+        } else {
+            Vec::new()
+        };
+
+        let per_interface = if self.options.per_interface && let Some(networks) = &self.networks {
+            let mut interfaces: Vec<InterfaceInfo> = networks
+                .iter()
+                .filter(|(name, _)| self.counts_interface(name))
+                .map(|(name, data)| InterfaceInfo {
+                    name: name.clone(),
+                    received_bytes: data.received(),
+                    transmitted_bytes: data.transmitted(),
+                    rx_per_sec: per_second(data.received(), elapsed),
+                    tx_per_sec: per_second(data.transmitted(), elapsed),
+                })
+                .filter(|interface| {
+                    !self.options.hide_idle || interface.received_bytes > 0 || interface.transmitted_bytes > 0
+                })
+                .collect();
+            // The interface map has no stable order between samples
+            interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+            interfaces
+        } else {
+            Vec::new()
+        };
+
+        // Network (sum all interfaces) - bytes since the last refresh
+        let network = self.networks.as_ref().map(|networks| {
+            networks
+                .iter()
+                .filter(|(name, _)| self.counts_interface(name))
+                .fold((0, 0), |(rx, tx), (_, data)| {
+                    (rx + data.received(), tx + data.transmitted())
+                })
+        });
+
+        let temperatures = self
+            .components
+            .iter()
+            .flat_map(|components| components.iter())
+            .filter_map(|component| Some((component.label().to_string(), component.temperature()?)))
+            .collect();
+
+        Ok(MetricsSnapshot {
+            timestamp,
+            collected: self.options.fields(),
+            elapsed,
+            uptime_secs: System::uptime(),
+            boot_time_secs: System::boot_time(),
+            cpu_usage_percent: system.global_cpu_usage(),
+            per_core_usage: system.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+            load_average: load_average(),
+            temperatures,
+            process_count,
+            thread_count,
+            memory_used_bytes: system.used_memory(),
+            memory_total_bytes: system.total_memory(),
+            memory_available_bytes: system.available_memory(),
+            memory_free_bytes: system.free_memory(),
+            swap_used_bytes: system.used_swap(),
+            swap_total_bytes: system.total_swap(),
+            disk_read_bytes: disk_read,
+            disk_write_bytes: disk_write,
+            disk_read_per_sec: per_second(disk_read, elapsed),
+            disk_write_per_sec: per_second(disk_write, elapsed),
+            net_rx_bytes: network.map(|(rx, _)| rx),
+            net_tx_bytes: network.map(|(_, tx)| tx),
+            net_rx_per_sec: network.map(|(rx, _)| per_second(rx, elapsed)),
+            net_tx_per_sec: network.map(|(_, tx)| per_second(tx, elapsed)),
+            per_disk,
+            per_interface,
+            top_processes,
+            top_memory_processes,
+            gpu: if self.options.gpu { self.gpu() } else { None },
+            gpu_requested: self.options.gpu,
+            process,
+            process_requested: self.options.process.is_some(),
+            alerts: Vec::new(),
+        })
+    }
+
+    /// Utilization and VRAM over every NVIDIA device, `None` if any can't be read
+    #[cfg(feature = "gpu")]
+    fn gpu(&self) -> Option<GpuInfo> {
+        let nvml = self.nvml.as_ref()?;
+        let devices = nvml.device_count().ok().filter(|&count| count > 0)?;
+
+        let mut gpu = GpuInfo { devices, ..Default::default() };
+        let mut utilization = 0;
+        for index in 0..devices {
+            let device = nvml.device_by_index(index).ok()?;
+            let memory = device.memory_info().ok()?;
+            utilization += device.utilization_rates().ok()?.gpu;
+            gpu.memory_used_bytes += memory.used;
+            gpu.memory_total_bytes += memory.total;
+        }
+        gpu.utilization_percent = utilization as f32 / devices as f32;
+
+        Some(gpu)
+    }
+
+    #[cfg(not(feature = "gpu"))]
+    fn gpu(&self) -> Option<GpuInfo> {
+        None
+    }
+
+    /// The running processes picked by [`CollectOptions::process`], lowest pid
+    /// first; more than one only for [`ProcessTarget::AllNamed`]
+    ///
+    /// A zombie has exited already; it only waits for its parent to reap it.
+    fn find_processes(&self) -> Vec<&Process> {
+        let running = |process: &&Process| process.status() != ProcessStatus::Zombie;
+        let named = |name: &str| {
+            let mut processes: Vec<&Process> = self
+                .system
+                .processes()
+                .values()
+                .filter(|process| process.thread_kind().is_none() && process.name() == name)
+                .filter(running)
+                .collect();
+            processes.sort_by_key(|process| process.pid());
+            processes
+        };
+
+        match self.options.process.as_ref() {
+            None => Vec::new(),
+            Some(ProcessTarget::Pid(pid)) => self.system.process(Pid::from_u32(*pid)).filter(running).into_iter().collect(),
+            Some(ProcessTarget::Name(name)) => named(name).into_iter().take(1).collect(),
+            Some(ProcessTarget::AllNamed(name)) => named(name),
+        }
+    }
+
+    /// The watched process if it was running during the last sample but is
+    /// gone now, as "name (pid)"; each exit is only reported once
+    pub fn take_exited_process(&mut self) -> Option<String> {
+        self.exited.take()
+    }
+
+    /// [`CollectOptions::process`] when nothing matches it
+    pub fn missing_process(&self) -> Option<&ProcessTarget> {
+        self.options.process.as_ref().filter(|_| self.find_processes().is_empty())
+    }
+
+    /// Names given in [`CollectOptions::interfaces`] that match no interface
+    ///
+    /// Worth a warning: a typo would otherwise just read as zero traffic.
+    pub fn missing_interfaces(&self) -> Vec<String> {
+        let (Some(names), Some(networks)) = (&self.options.interfaces, &self.networks) else {
+            return Vec::new();
+        };
+
+        names
+            .iter()
+            .filter(|name| !networks.contains_key(name.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Patterns in [`CollectOptions::disks`] that match no disk
+    pub fn missing_disks(&self) -> Vec<String> {
+        let (Some(patterns), Some(disks)) = (&self.options.disks, &self.disks) else {
+            return Vec::new();
+        };
+
+        patterns
+            .iter()
+            .filter(|pattern| {
+                !disks.iter().any(|disk| {
+                    disk_matches(std::slice::from_ref(pattern), &disk.name().to_string_lossy(), disk.mount_point())
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn counts_disk(&self, disk: &Disk) -> bool {
+        self.options
+            .disks
+            .as_ref()
+            .is_none_or(|patterns| disk_matches(patterns, &disk.name().to_string_lossy(), disk.mount_point()))
+    }
+
+    fn counts_interface(&self, name: &str) -> bool {
+        interface_counted(name, self.options.interfaces.as_deref(), self.options.ignore_interfaces.as_ref())
+    }
+
+    /// Refresh everything that is sampled and return the time since the last refresh
+    fn refresh(&mut self) -> Duration {
+        if self.options.cpu {
+            self.system.refresh_cpu_all();
+        }
+        if self.options.memory {
+            self.system.refresh_memory();
+        }
+        // Drop disks and interfaces that went away during a long run
+        if let Some(disks) = &mut self.disks {
+            disks.refresh(true);
+        }
+        if let Some(networks) = &mut self.networks {
+            networks.refresh(true);
+        }
+        // Process CPU usage is a delta too, so it needs the baseline refresh;
+        // finding a process by name means looking at all of them
+        let by_name = matches!(self.options.process, Some(ProcessTarget::Name(_) | ProcessTarget::AllNamed(_)));
+        if self.options.top_processes.is_some() || by_name {
+            self.system.refresh_processes(ProcessesToUpdate::All, true);
+        } else {
+            if let Some(ProcessTarget::Pid(pid)) = self.options.process {
+                self.system.refresh_processes(ProcessesToUpdate::Some(&[Pid::from_u32(pid)]), true);
+            }
+            // Counting processes and threads only needs the list, not their stats
+            if self.options.cpu {
+                let list = ProcessRefreshKind::nothing().with_tasks();
+                self.system.refresh_processes_specifics(ProcessesToUpdate::All, true, list);
+            }
+        }
+
+        let now = Instant::now();
+        let elapsed = now - self.last_refresh;
+        self.last_refresh = now;
+
+        elapsed
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Monitor::new()
+    }
+}
+
+/// System load averages, `None` on Windows where sysinfo reports only zeros
+fn load_average() -> Option<(f64, f64, f64)> {
+    if cfg!(windows) {
+        return None;
+    }
+
+    let load = System::load_average();
+    Some((load.one, load.five, load.fifteen))
+}
+
+/// Every process, without the threads Linux lists as tasks
+fn processes(system: &System) -> Vec<ProcessInfo> {
+    system
+        .processes()
+        .values()
+        // On Linux threads show up as tasks; only count whole processes
+        .filter(|process| process.thread_kind().is_none())
+        .map(|process| ProcessInfo {
+            pid: process.pid().as_u32(),
+            name: process.name().to_string_lossy().into_owned(),
+            cpu_usage_percent: process.cpu_usage(),
+            memory_bytes: process.memory(),
+        })
+        .collect()
+}
+
+/// Processes, and threads where sysinfo lists each process's tasks
+fn process_counts(system: &System) -> (usize, Option<usize>) {
+    let mut processes = 0;
+    let mut threads = None;
+    // Kernel threads count as processes, the way ps and top show them
+    let userland_thread = |process: &&Process| process.thread_kind() == Some(ThreadKind::Userland);
+    for process in system.processes().values().filter(|process| !userland_thread(process)) {
+        processes += 1;
+        // sysinfo leaves the main thread out of a process's tasks
+        if let Some(tasks) = process.tasks() {
+            *threads.get_or_insert(0) += 1 + tasks.len();
+        }
+    }
+
+    (processes, threads)
+}
+
+/// "name (pid)", or "name (3 processes)" for a group, how notes about the
+/// watched process refer to it
+fn describe(processes: &[&Process]) -> String {
+    let name = processes[0].name().to_string_lossy();
+    match processes.len() {
+        1 => format!("{} ({})", name, processes[0].pid()),
+        count => format!("{} ({} processes)", name, count),
+    }
+}
+
+/// The figures of `processes` added up, under the lowest pid
+fn watched_process(processes: &[&Process], elapsed: Duration) -> WatchedProcess {
+    let mut watched = WatchedProcess {
+        pid: processes[0].pid().as_u32(),
+        name: processes[0].name().to_string_lossy().into_owned(),
+        processes: processes.len(),
+        ..Default::default()
+    };
+    for process in processes {
+        let usage = process.disk_usage();
+        watched.cpu_usage_percent += process.cpu_usage();
+        watched.memory_bytes += process.memory();
+        watched.virtual_memory_bytes += process.virtual_memory();
+        watched.disk_read_bytes += usage.read_bytes;
+        watched.disk_write_bytes += usage.written_bytes;
+    }
+    watched.disk_read_per_sec = per_second(watched.disk_read_bytes, elapsed);
+    watched.disk_write_per_sec = per_second(watched.disk_write_bytes, elapsed);
+
+    watched
+}
+
+/// The `limit` processes with the highest CPU usage, ties broken by pid
+fn top_by_cpu(mut processes: Vec<ProcessInfo>, limit: usize) -> Vec<ProcessInfo> {
+    processes.sort_by(|a, b| {
+        b.cpu_usage_percent
+            .total_cmp(&a.cpu_usage_percent)
+            .then(a.pid.cmp(&b.pid))
+    });
+    processes.truncate(limit);
+
+    processes
+}
+
+/// The `limit` processes with the most resident memory, ties broken by pid
+fn top_by_memory(mut processes: Vec<ProcessInfo>, limit: usize) -> Vec<ProcessInfo> {
+    processes.sort_by(|a, b| b.memory_bytes.cmp(&a.memory_bytes).then(a.pid.cmp(&b.pid)));
+    processes.truncate(limit);
+
+    processes
+}
+
+/// The first entry for each device in `disks`
+///
+/// Linux lists a device once per mount, so `/` and `/home` on one partition,
+/// or a bind mount, report the same I/O twice. Entries without a device name
+/// are all kept since there is nothing to tell them apart by.
+pub fn dedup_disks(disks: &[DiskInfo]) -> Vec<&DiskInfo> {
+    let mut seen = HashSet::new();
+
+    disks
+        .iter()
+        .filter(|disk| disk.name.is_empty() || seen.insert(disk.name.as_str()))
+        .collect()
+}
+
+/// Whether an interface is counted: named in `include` (when given) and not
+/// matched by `exclude`
+///
+/// The include list comes first, so `--interfaces` narrows the set and
+/// `--ignore-interfaces` then drops from what is left. The regex matches
+/// anywhere in the name unless anchored.
+pub fn interface_counted(name: &str, include: Option<&[String]>, exclude: Option<&Regex>) -> bool {
+    include.is_none_or(|names| names.iter().any(|wanted| wanted == name))
+        && exclude.is_none_or(|pattern| !pattern.is_match(name))
+}
+
+/// Whether a disk is selected by any of `patterns`
+///
+/// A pattern matches the device name, with or without its `/dev/` prefix
+/// ("sda1", "/dev/sda1"), or a mount point prefix by whole components, so
+/// "/home" covers "/home/data" but not "/homework".
+pub fn disk_matches(patterns: &[String], name: &str, mount_point: &Path) -> bool {
+    let device = Path::new(name).file_name().and_then(|base| base.to_str()).unwrap_or(name);
+
+    patterns.iter().any(|pattern| {
+        pattern == name || pattern == device || (pattern.starts_with('/') && mount_point.starts_with(pattern))
+    })
+}
+
+/// Normalize a byte delta to bytes per second over the measured window
+pub(crate) fn per_second(bytes: u64, elapsed: Duration) -> u64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds <= 0.0 {
+        return 0;
+    }
+
+    (bytes as f64 / seconds).round() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_matches() {
+        let disks = [
+            ("/dev/nvme0n1p2", "/"),
+            ("/dev/nvme0n1p3", "/home"),
+            ("/dev/loop4", "/snap/core/123"),
+            ("nas:/export", "/mnt/nas"),
+        ];
+        let selected = |patterns: &[&str]| -> Vec<&str> {
+            let patterns: Vec<String> = patterns.iter().map(|pattern| pattern.to_string()).collect();
+            disks
+                .iter()
+                .filter(|(name, mount_point)| disk_matches(&patterns, name, Path::new(mount_point)))
+                .map(|&(name, _)| name)
+                .collect()
+        };
+
+        assert_eq!(selected(&["nvme0n1p3"]), ["/dev/nvme0n1p3"]);
+        assert_eq!(selected(&["/dev/nvme0n1p2", "/home"]), ["/dev/nvme0n1p2", "/dev/nvme0n1p3"]);
+        assert_eq!(selected(&["/snap"]), ["/dev/loop4"]);
+        // Every mount point is under "/"
+        assert_eq!(selected(&["/"]).len(), 4);
+        assert!(selected(&["/hom"]).is_empty());
+        assert!(selected(&["sdz"]).is_empty());
+    }
+
+    #[test]
+    fn test_interface_counted() {
+        let container_host = Regex::new("^(veth|br-|docker|cni|flannel)").unwrap();
+        for ignored in ["veth3f2a1b9", "br-5c1d0e7a9f2b", "docker0", "cni0", "flannel.1"] {
+            assert!(!interface_counted(ignored, None, Some(&container_host)), "{ignored}");
+        }
+        for kept in ["eth0", "enp3s0", "wlan0", "lo", "tailscale0", "wg-veth"] {
+            assert!(interface_counted(kept, None, Some(&container_host)), "{kept}");
+        }
+
+        // Included first, then excluded
+        let include = ["eth0".to_string(), "docker0".to_string()];
+        assert!(interface_counted("eth0", Some(&include), Some(&container_host)));
+        assert!(!interface_counted("docker0", Some(&include), Some(&container_host)));
+        assert!(!interface_counted("wlan0", Some(&include), Some(&container_host)));
+        assert!(interface_counted("wlan0", None, None));
+    }
+
+    #[test]
+    fn test_cpu_window_is_never_too_short() {
+        let mut monitor = Monitor::with_options(CollectOptions::default());
+        assert!(monitor.sample().unwrap().elapsed >= MINIMUM_CPU_UPDATE_INTERVAL);
+
+        let mut memory_only = Monitor::with_options(CollectOptions { cpu: false, ..CollectOptions::default() });
+        assert!(memory_only.sample().unwrap().elapsed < MINIMUM_CPU_UPDATE_INTERVAL);
+    }
+
+    #[test]
+    fn test_rebaseline_starts_a_new_window() {
+        let mut monitor = Monitor::with_options(CollectOptions { cpu: false, ..CollectOptions::default() });
+        std::thread::sleep(Duration::from_millis(300));
+        monitor.rebaseline();
+        assert!(monitor.sample().unwrap().elapsed < Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_processes_are_counted() {
+        let snapshot = Monitor::with_options(CollectOptions::default()).sample().unwrap();
+        assert!(snapshot.process_count > 0);
+        if cfg!(target_os = "linux") {
+            assert!(snapshot.thread_count.unwrap() >= snapshot.process_count);
+        }
+    }
+
+    #[test]
+    fn test_top_lists_break_ties_by_pid() {
+        let process = |pid, cpu_usage_percent, memory_bytes| ProcessInfo {
+            pid,
+            name: format!("p{}", pid),
+            cpu_usage_percent,
+            memory_bytes,
+        };
+        let processes = vec![process(30, 5.0, 100), process(10, 50.0, 100), process(20, 5.0, 900)];
+        let pids = |list: Vec<ProcessInfo>| -> Vec<u32> { list.iter().map(|process| process.pid).collect() };
+
+        assert_eq!(pids(top_by_cpu(processes.clone(), 3)), [10, 20, 30]);
+        assert_eq!(pids(top_by_memory(processes.clone(), 3)), [20, 10, 30]);
+        assert_eq!(pids(top_by_memory(processes, 1)), [20]);
+    }
+
+    #[test]
+    fn test_dedup_disks_counts_each_device_once() {
+        let disk = |name: &str, mount_point: &str| DiskInfo {
+            name: name.to_string(),
+            mount_point: mount_point.to_string(),
+            read_bytes: 1_000,
+            written_bytes: 500,
+            ..Default::default()
+        };
+        let disks = [disk("/dev/sda1", "/"), disk("/dev/sda1", "/home"), disk("/dev/sdb1", "/data")];
+        let unique = dedup_disks(&disks);
+
+        let mounts: Vec<&str> = unique.iter().map(|disk| disk.mount_point.as_str()).collect();
+        assert_eq!(mounts, ["/", "/data"]);
+        assert_eq!(unique.iter().map(|disk| disk.read_bytes).sum::<u64>(), 2_000);
+        assert_eq!(unique.iter().map(|disk| disk.written_bytes).sum::<u64>(), 1_000);
+        assert_eq!(dedup_disks(&[disk("", "/a"), disk("", "/b")]).len(), 2);
+    }
+
+    #[test]
+    fn test_per_second() {
+        assert_eq!(per_second(5_000, Duration::from_secs(5)), 1_000);
+        assert_eq!(per_second(1_000, Duration::from_millis(500)), 2_000);
+        assert_eq!(per_second(1_000, Duration::ZERO), 0);
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::MetricsSnapshot;
+use crate::monitor::per_second;
+
+/// Disk and network rates averaged over a longer window than one sample
+///
+/// Keeps the byte counts of the most recent samples that together cover
+/// `window`, so the display can refresh quickly while the rates stay
+/// steady. Until a full window has been seen the rates cover every sample
+/// so far. Only the `*_per_sec` figures change; byte counts stay those of
+/// the latest sample.
+#[derive(Debug, Clone)]
+pub struct RateTracker {
+    window: Duration,
+    samples: VecDeque<Counts>,
+}
+
+/// Byte counts of one sample; the breakdowns are keyed by mount point and interface name
+#[derive(Debug, Clone)]
+struct Counts {
+    elapsed: Duration,
+    disk: (u64, u64),
+    network: Option<(u64, u64)>,
+    disks: HashMap<String, (u64, u64)>,
+    interfaces: HashMap<String, (u64, u64)>,
+}
+
+impl Counts {
+    fn of(snapshot: &MetricsSnapshot) -> Counts {
+        Counts {
+            elapsed: snapshot.elapsed,
+            disk: (snapshot.disk_read_bytes, snapshot.disk_write_bytes),
+            network: snapshot.net_rx_bytes.zip(snapshot.net_tx_bytes),
+            disks: snapshot
+                .per_disk
+                .iter()
+                .map(|disk| (disk.mount_point.clone(), (disk.read_bytes, disk.written_bytes)))
+                .collect(),
+            interfaces: snapshot
+                .per_interface
+                .iter()
+                .map(|interface| (interface.name.clone(), (interface.received_bytes, interface.transmitted_bytes)))
+                .collect(),
+        }
+    }
+}
+
+impl RateTracker {
+    pub fn new(window: Duration) -> Self {
+        RateTracker { window, samples: VecDeque::new() }
+    }
+
+    /// Add `snapshot` and replace its rates with the averages over the window
+    pub fn apply(&mut self, snapshot: &mut MetricsSnapshot) {
+        self.samples.push_back(Counts::of(snapshot));
+        while self.samples.len() > 1 && self.span() - self.samples[0].elapsed >= self.window {
+            self.samples.pop_front();
+        }
+
+        (snapshot.disk_read_per_sec, snapshot.disk_write_per_sec) = self.average(|counts| Some(counts.disk));
+        if snapshot.net_rx_bytes.is_some() {
+            let (rx, tx) = self.average(|counts| counts.network);
+            snapshot.net_rx_per_sec = Some(rx);
+            snapshot.net_tx_per_sec = Some(tx);
+        }
+        for disk in &mut snapshot.per_disk {
+            (disk.read_per_sec, disk.write_per_sec) = self.average(|counts| counts.disks.get(&disk.mount_point).copied());
+        }
+        for interface in &mut snapshot.per_interface {
+            (interface.rx_per_sec, interface.tx_per_sec) =
+                self.average(|counts| counts.interfaces.get(&interface.name).copied());
+        }
+    }
+
+    /// Time covered by the samples kept
+    fn span(&self) -> Duration {
+        self.samples.iter().map(|counts| counts.elapsed).sum()
+    }
+
+    /// Both rates of a pair of counters, over only the samples that have it,
+    /// so a disk mounted mid-window isn't diluted by the time before
+    fn average(&self, pick: impl Fn(&Counts) -> Option<(u64, u64)>) -> (u64, u64) {
+        let (elapsed, first, second) = self
+            .samples
+            .iter()
+            .filter_map(|counts| Some((counts.elapsed, pick(counts)?)))
+            .fold((Duration::ZERO, 0, 0), |(elapsed, first, second), (window, (a, b))| {
+                (elapsed + window, first + a, second + b)
+            });
+
+        (per_second(first, elapsed), per_second(second, elapsed))
+    }
+}
+
+/// Mean of the last few values, as `--smooth N` shows them
+///
+/// A fixed-size ring of values with a running total, so adding one is
+/// O(1) however many are kept. Until it is full the mean covers every
+/// value so far.
+#[derive(Debug, Clone)]
+pub struct RollingAverage {
+    values: Vec<f64>,
+    capacity: usize,
+    next: usize,
+    total: f64,
+}
+
+impl RollingAverage {
+    /// Average over the last `capacity` values; at least one is always kept
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        RollingAverage { values: Vec::with_capacity(capacity), capacity, next: 0, total: 0.0 }
+    }
+
+    /// Add `value`, dropping the oldest one once full, and return the new mean
+    pub fn add(&mut self, value: f64) -> f64 {
+        if self.values.len() < self.capacity {
+            self.values.push(value);
+        } else {
+            self.total -= self.values[self.next];
+            self.values[self.next] = value;
+        }
+        self.total += value;
+        self.next = (self.next + 1) % self.capacity;
+
+        self.mean()
+    }
+
+    /// Mean of the values kept, 0 before the first
+    pub fn mean(&self) -> f64 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        self.total / self.values.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_average_keeps_the_last_values() {
+        let mut average = RollingAverage::new(3);
+        assert_eq!(average.mean(), 0.0);
+
+        // Not full yet: the mean covers what there is
+        assert_eq!(average.add(3.0), 3.0);
+        assert_eq!(average.add(6.0), 4.5);
+        assert_eq!(average.add(9.0), 6.0);
+        // 3 and then 6 drop out
+        assert_eq!(average.add(0.0), 5.0);
+        assert_eq!(average.add(0.0), 3.0);
+        assert_eq!(average.values.len(), 3);
+    }
+
+    #[test]
+    fn test_rolling_average_of_one_is_the_raw_value() {
+        let mut average = RollingAverage::new(0);
+        assert_eq!(average.add(40.0), 40.0);
+        assert_eq!(average.add(10.0), 10.0);
+    }
+
+    fn sample(elapsed_ms: u64, read_bytes: u64) -> MetricsSnapshot {
+        MetricsSnapshot {
+            elapsed: Duration::from_millis(elapsed_ms),
+            disk_read_bytes: read_bytes,
+            disk_read_per_sec: per_second(read_bytes, Duration::from_millis(elapsed_ms)),
+            net_rx_bytes: Some(read_bytes * 2),
+            net_tx_bytes: Some(0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rates_average_over_the_window() {
+        let mut tracker = RateTracker::new(Duration::from_secs(1));
+        let rates = |tracker: &mut RateTracker, snapshot: MetricsSnapshot| {
+            let mut snapshot = snapshot;
+            tracker.apply(&mut snapshot);
+            (snapshot.disk_read_per_sec, snapshot.net_rx_per_sec)
+        };
+
+        // Not full yet: the average covers what there is
+        assert_eq!(rates(&mut tracker, sample(250, 1000)), (4000, Some(8000)));
+        assert_eq!(rates(&mut tracker, sample(250, 0)), (2000, Some(4000)));
+        assert_eq!(rates(&mut tracker, sample(250, 0)), (1333, Some(2667)));
+        assert_eq!(rates(&mut tracker, sample(250, 0)), (1000, Some(2000)));
+        // The first sample has left the window
+        assert_eq!(rates(&mut tracker, sample(250, 0)), (0, Some(0)));
+        assert_eq!(tracker.samples.len(), 4);
+    }
+
+    #[test]
+    fn test_network_left_out_stays_out() {
+        let mut tracker = RateTracker::new(Duration::from_secs(5));
+        let mut snapshot = MetricsSnapshot { elapsed: Duration::from_secs(1), disk_read_bytes: 10, ..Default::default() };
+        tracker.apply(&mut snapshot);
+
+        assert_eq!(snapshot.disk_read_per_sec, 10);
+        assert_eq!(snapshot.net_rx_per_sec, None);
+    }
+}
@@ -0,0 +1,131 @@
+//! The `--record` file format, read back by `replay`
+//!
+//! Each line is one JSON object holding a [`MetricsSnapshot`] along with the
+//! bookkeeping its JSON output leaves out, so a replayed sample renders just
+//! like the original did.
+
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::{Field, Fields, MetricsSnapshot};
+
+/// Written into every record; bumped whenever older readers would misread a line
+pub const VERSION: u32 = 1;
+
+/// One recorded sample
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct Record {
+    pub version: u32,
+    /// Groups the sample collected, see [`MetricsSnapshot::collected`]
+    pub collected: Vec<Field>,
+    /// Length of the sampling window, in milliseconds
+    ///
+    /// The snapshot carries it too now, but recordings made before it did
+    /// only have it here.
+    pub elapsed_ms: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub gpu_requested: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub process_requested: bool,
+    pub snapshot: MetricsSnapshot,
+}
+
+impl Record {
+    pub fn new(snapshot: &MetricsSnapshot) -> Record {
+        Record {
+            version: VERSION,
+            collected: snapshot.collected.to_list(),
+            elapsed_ms: snapshot.elapsed.as_millis() as u64,
+            gpu_requested: snapshot.gpu_requested,
+            process_requested: snapshot.process_requested,
+            snapshot: snapshot.clone(),
+        }
+    }
+
+    /// The record as one line of JSON, without the newline
+    pub fn to_line(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Read one line written by [`Record::to_line`]
+    #[cfg(feature = "serde")]
+    pub fn parse(line: &str) -> Result<Record, String> {
+        let record: Record = serde_json::from_str(line).map_err(|err| err.to_string())?;
+        if record.version > VERSION {
+            return Err(format!(
+                "recorded in format version {}, this build only reads up to {}",
+                record.version, VERSION
+            ));
+        }
+
+        Ok(record)
+    }
+
+    /// The snapshot with its bookkeeping put back
+    pub fn into_snapshot(self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            collected: Fields::from_list(&self.collected),
+            elapsed: Duration::from_millis(self.elapsed_ms),
+            gpu_requested: self.gpu_requested,
+            process_requested: self.process_requested,
+            ..self.snapshot
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::{FormatOptions, OutputFormat, write_sample};
+
+    #[test]
+    fn test_recorded_snapshots_replay_identically() {
+        let snapshots = [
+            MetricsSnapshot {
+                collected: Fields::from_list(&[Field::Cpu, Field::Mem]),
+                elapsed: Duration::from_millis(1000),
+                cpu_usage_percent: 12.5,
+                memory_used_bytes: 4_000_000_000,
+                memory_total_bytes: 16_000_000_000,
+                ..Default::default()
+            },
+            MetricsSnapshot {
+                elapsed: Duration::from_millis(500),
+                disk_read_per_sec: 2_500_000,
+                net_rx_bytes: Some(1000),
+                net_rx_per_sec: Some(2000),
+                net_tx_bytes: Some(0),
+                net_tx_per_sec: Some(0),
+                gpu_requested: true,
+                ..Default::default()
+            },
+        ];
+        let lines: Vec<String> =
+            snapshots.iter().map(|snapshot| Record::new(snapshot).to_line().unwrap()).collect();
+        assert!(lines.iter().all(|line| !line.contains('\n') && line.starts_with("{\"version\":1,")));
+
+        let options = FormatOptions::default();
+        let render = |snapshot: &MetricsSnapshot| {
+            let mut out = Vec::new();
+            write_sample(&mut out, snapshot, &OutputFormat::Text, &options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        for (snapshot, line) in snapshots.iter().zip(&lines) {
+            let replayed = Record::parse(line).unwrap().into_snapshot();
+            assert_eq!(replayed.elapsed, snapshot.elapsed);
+            assert_eq!(render(&replayed), render(snapshot));
+        }
+    }
+
+    #[test]
+    fn test_newer_versions_are_rejected() {
+        let line = Record::new(&MetricsSnapshot::default()).to_line().unwrap().replacen("\"version\":1", "\"version\":2", 1);
+
+        assert!(Record::parse(&line).unwrap_err().contains("version 2"));
+        assert!(Record::parse("not json").is_err());
+    }
+}
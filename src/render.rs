@@ -0,0 +1,528 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::{BYTES_WIDTH, Field, FormattedMetrics, PERCENT_WIDTH};
+use crate::color::{Color, paint};
+
+/// Border characters for [`table`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    /// Box-drawing characters
+    #[default]
+    Unicode,
+    /// Plain `+`, `-` and `|` for terminals and logs without UTF-8
+    Ascii,
+}
+
+struct Borders {
+    horizontal: char,
+    vertical: char,
+    /// Corners and joints of the top, middle and bottom rules, left to right
+    top: [char; 3],
+    middle: [char; 3],
+    bottom: [char; 3],
+}
+
+impl TableStyle {
+    fn borders(&self) -> Borders {
+        match self {
+            TableStyle::Unicode => Borders {
+                horizontal: '─',
+                vertical: '│',
+                top: ['┌', '┬', '┐'],
+                middle: ['├', '┼', '┤'],
+                bottom: ['└', '┴', '┘'],
+            },
+            TableStyle::Ascii => Borders {
+                horizontal: '-',
+                vertical: '|',
+                top: ['+'; 3],
+                middle: ['+'; 3],
+                bottom: ['+'; 3],
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+}
+
+struct Cell<'a> {
+    text: &'a str,
+    color: Option<Color>,
+    align: Align,
+}
+
+impl<'a> Cell<'a> {
+    fn label(text: &'a str) -> Self {
+        Cell { text, color: None, align: Align::Left }
+    }
+
+    fn value(text: &'a str) -> Self {
+        Cell { text, color: None, align: Align::Right }
+    }
+
+    fn painted(text: &'a str, color: Option<Color>) -> Self {
+        Cell { text, color, align: Align::Right }
+    }
+}
+
+/// Block characters of a [`sparkline`], lowest to highest
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The last `width` of `values` as a line of block characters
+///
+/// Values are scaled from zero to the largest one shown, so the line shows
+/// the shape of the trend rather than absolute levels. All zeros (or
+/// negative values) stay at the lowest block.
+pub fn sparkline(values: &[f64], width: usize) -> String {
+    let shown = &values[values.len().saturating_sub(width)..];
+    let max = shown.iter().copied().fold(0.0, f64::max);
+
+    shown
+        .iter()
+        .map(|&value| {
+            let level = if max > 0.0 { (value.max(0.0) / max * 7.0).round() as usize } else { 0 };
+            SPARKS[level.min(7)]
+        })
+        .collect()
+}
+
+/// Render the metrics as an aligned two-column table
+///
+/// Column widths come from the formatted strings of this frame, so a value
+/// growing from "9.9%" to "100.0%" widens the table instead of breaking it.
+/// With [`deltas`](FormattedMetrics::deltas) a column after the values
+/// carries the changes, and with [`trends`](FormattedMetrics::trends) one
+/// after that the sparklines. Top processes, when present, follow in a second table with a
+/// header row.
+pub fn table(metrics: &FormattedMetrics, style: TableStyle) -> String {
+    let core_labels: Vec<String> =
+        (0..metrics.per_core_usage.len()).map(|core| format!("Core {}", core)).collect();
+    let memory = format!(
+        "{} / {} ({})",
+        metrics.memory_used, metrics.memory_total, metrics.memory_usage_percent
+    );
+    let available = format!("{} (free {})", metrics.memory_available, metrics.memory_free);
+    let swap = format!("{} / {} ({})", metrics.swap_used, metrics.swap_total, metrics.swap_usage_percent);
+
+    let disk_labels: Vec<String> = metrics
+        .per_disk
+        .iter()
+        .map(|disk| match disk.name.as_str() {
+            "" => disk.mount_point.clone(),
+            name => format!("{} ({})", disk.mount_point, name),
+        })
+        .collect();
+    let disk_summaries: Vec<String> = metrics.per_disk.iter().map(|disk| disk.summary()).collect();
+    let interface_summaries: Vec<String> =
+        metrics.per_interface.iter().map(|interface| interface.summary()).collect();
+
+    let alerts = metrics.alerts.join(", ");
+    let mut rows = Vec::new();
+    if !alerts.is_empty() {
+        rows.push(vec![Cell::label("ALERT"), Cell::value(&alerts)]);
+    }
+    rows.push(vec![Cell::label("Uptime"), Cell::value(&metrics.uptime)]);
+    let mut trend_rows = Vec::new();
+    let mut delta_rows = Vec::new();
+    if metrics.fields.cpu {
+        trend_rows.push((rows.len(), &metrics.trends.cpu));
+        delta_rows.push((rows.len(), &metrics.deltas.cpu));
+        rows.push(vec![Cell::label("CPU"), Cell::painted(&metrics.cpu_usage, metrics.cpu_color)]);
+        for (core, usage) in metrics.per_core_usage.iter().enumerate() {
+            let color = metrics.per_core_colors.get(core).copied().flatten();
+            rows.push(vec![Cell::label(&core_labels[core]), Cell::painted(usage, color)]);
+        }
+        if let Some(load) = &metrics.load_average {
+            rows.push(vec![Cell::label("Load"), Cell::value(load)]);
+        }
+        rows.push(vec![Cell::label("Processes"), Cell::value(&metrics.processes)]);
+        for (label, reading) in &metrics.temperatures {
+            rows.push(vec![Cell::label(label), Cell::value(reading)]);
+        }
+    }
+    if metrics.fields.mem {
+        trend_rows.push((rows.len(), &metrics.trends.memory));
+        delta_rows.push((rows.len(), &metrics.deltas.memory));
+        rows.push(vec![Cell::label("Memory"), Cell::painted(&memory, metrics.memory_color)]);
+        rows.push(vec![Cell::label("Available"), Cell::value(&available)]);
+        rows.push(vec![Cell::label("Swap"), Cell::value(&swap)]);
+    }
+    if metrics.fields.disk {
+        delta_rows.push((rows.len(), &metrics.deltas.disk_read));
+        rows.push(vec![Cell::label("Disk Read"), Cell::value(&metrics.disk_read)]);
+        delta_rows.push((rows.len(), &metrics.deltas.disk_write));
+        rows.push(vec![Cell::label("Disk Write"), Cell::value(&metrics.disk_write)]);
+        for (label, summary) in disk_labels.iter().zip(&disk_summaries) {
+            rows.push(vec![Cell::label(label), Cell::value(summary)]);
+        }
+    }
+    if metrics.fields.net {
+        trend_rows.push((rows.len(), &metrics.trends.net_rx));
+        delta_rows.push((rows.len(), &metrics.deltas.net_rx));
+        rows.push(vec![Cell::label("Network RX"), Cell::value(&metrics.net_rx)]);
+        trend_rows.push((rows.len(), &metrics.trends.net_tx));
+        delta_rows.push((rows.len(), &metrics.deltas.net_tx));
+        rows.push(vec![Cell::label("Network TX"), Cell::value(&metrics.net_tx)]);
+        for (interface, summary) in metrics.per_interface.iter().zip(&interface_summaries) {
+            rows.push(vec![Cell::label(&interface.name), Cell::value(summary)]);
+        }
+    }
+    let gpu = metrics.gpu_summary();
+    if metrics.gpu_requested {
+        rows.push(vec![Cell::label("GPU"), Cell::value(&gpu)]);
+    }
+    let process = metrics.process_summary();
+    if metrics.process_requested {
+        rows.push(vec![Cell::label("Process"), Cell::value(&process)]);
+    }
+    if !metrics.deltas.is_empty() {
+        for row in &mut rows {
+            row.push(Cell::label(""));
+        }
+        for (row, delta) in delta_rows {
+            rows[row][2] = Cell::value(delta);
+        }
+    }
+    if !metrics.trends.is_empty() {
+        let column = rows[0].len();
+        for row in &mut rows {
+            row.push(Cell::label(""));
+        }
+        for (row, trend) in trend_rows {
+            rows[row][column] = Cell::label(trend);
+        }
+    }
+
+    let mut out = draw(None, &rows, style);
+
+    if !metrics.top_processes.is_empty() {
+        let header = vec![Cell::value("PID"), Cell::value("CPU"), Cell::value("MEM"), Cell::label("NAME")];
+        let processes: Vec<Vec<Cell>> = metrics
+            .top_processes
+            .iter()
+            .map(|process| {
+                vec![
+                    Cell::value(&process.pid),
+                    Cell::value(&process.cpu_usage),
+                    Cell::value(&process.memory),
+                    Cell::label(&process.name),
+                ]
+            })
+            .collect();
+        out.push_str(&draw(Some(&header), &processes, style));
+    }
+    // The sort key leads, so the two process tables can be told apart
+    if !metrics.top_memory_processes.is_empty() {
+        let header = vec![Cell::value("PID"), Cell::value("MEM"), Cell::value("CPU"), Cell::label("NAME")];
+        let processes: Vec<Vec<Cell>> = metrics
+            .top_memory_processes
+            .iter()
+            .map(|process| {
+                vec![
+                    Cell::value(&process.pid),
+                    Cell::value(&process.memory),
+                    Cell::value(&process.cpu_usage),
+                    Cell::label(&process.name),
+                ]
+            })
+            .collect();
+        out.push_str(&draw(Some(&header), &processes, style));
+    }
+
+    out
+}
+
+/// Left-aligned columns without borders under an upper-case header, like `ps`
+///
+/// Columns are two spaces apart and sized to their widest cell; the last
+/// one isn't padded, so lines carry no trailing spaces.
+pub fn columns(header: &[&str], rows: impl IntoIterator<Item = Vec<String>>) -> String {
+    let rows: Vec<Vec<String>> = rows.into_iter().collect();
+    let mut widths: Vec<usize> = header.iter().map(|name| name.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        let mut line = String::new();
+        for (column, (cell, &width)) in cells.iter().zip(&widths).enumerate() {
+            if column + 1 == cells.len() {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{:<width$}  ", cell));
+            }
+        }
+        line.push('\n');
+        line
+    };
+
+    let mut out = line(header.to_vec());
+    for row in &rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+/// Names and widths of the [`column_row`] figures
+///
+/// Adaptive rates take at most four digits and a five character unit, as in
+/// "1023 TiB/s", so rates well past TB/s still line up under their names.
+const COLUMNS: [(&str, usize); 6] = [
+    ("cpu", PERCENT_WIDTH),
+    ("mem%", PERCENT_WIDTH),
+    ("dsk-r", BYTES_WIDTH),
+    ("dsk-w", BYTES_WIDTH),
+    ("net-rx", BYTES_WIDTH),
+    ("net-tx", BYTES_WIDTH),
+];
+
+/// The names over [`column_row`]s, after a `time` column `time_width` wide when there is one
+pub fn column_header(time_width: Option<usize>) -> String {
+    let time = time_width.map(|width| format!("{:<width$}", "time"));
+    column_line(time.as_deref(), COLUMNS.map(|(name, _)| name))
+}
+
+/// One sample as a line of fixed-width columns, like `vmstat`
+///
+/// Figures of groups that weren't collected or selected show as "-", so
+/// every line has the same columns whatever the sample holds.
+pub fn column_row(metrics: &FormattedMetrics, time: Option<&str>) -> String {
+    let values = [
+        (Field::Cpu, metrics.cpu_usage.as_str()),
+        (Field::Mem, metrics.memory_usage_percent.as_str()),
+        (Field::Disk, metrics.disk_read.as_str()),
+        (Field::Disk, metrics.disk_write.as_str()),
+        (Field::Net, metrics.net_rx.as_str()),
+        (Field::Net, metrics.net_tx.as_str()),
+    ]
+    .map(|(field, value)| if metrics.fields.contains(field) && !value.is_empty() { value } else { "-" });
+    column_line(time, values)
+}
+
+fn column_line(time: Option<&str>, values: [&str; 6]) -> String {
+    let mut cells: Vec<String> = time.map(str::to_string).into_iter().collect();
+    cells.extend(COLUMNS.iter().zip(values).map(|(&(_, width), value)| format!("{:>width$}", value)));
+    cells.join("  ")
+}
+
+/// Draw `rows` with borders, sizing every column to its widest cell
+fn draw(header: Option<&[Cell]>, rows: &[Vec<Cell>], style: TableStyle) -> String {
+    let borders = style.borders();
+    let columns = header.map_or_else(|| rows.first().map_or(0, Vec::len), <[Cell]>::len);
+    let mut widths = vec![0; columns];
+    for row in header.into_iter().chain(rows.iter().map(Vec::as_slice)) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.text.chars().count());
+        }
+    }
+
+    let rule = |[left, joint, right]: [char; 3]| {
+        let segments: Vec<String> = widths
+            .iter()
+            .map(|width| borders.horizontal.to_string().repeat(width + 2))
+            .collect();
+        format!("{}{}{}\n", left, segments.join(&joint.to_string()), right)
+    };
+    let line = |row: &[Cell]| {
+        let mut line = borders.vertical.to_string();
+        for (cell, &width) in row.iter().zip(&widths) {
+            // Pad before painting so escape codes don't count towards the width
+            let padded = match cell.align {
+                Align::Left => format!("{:<width$}", cell.text),
+                Align::Right => format!("{:>width$}", cell.text),
+            };
+            line.push_str(&format!(" {} {}", paint(&padded, cell.color), borders.vertical));
+        }
+        line.push('\n');
+        line
+    };
+
+    let mut out = rule(borders.top);
+    if let Some(header) = header {
+        out.push_str(&line(header));
+        out.push_str(&rule(borders.middle));
+    }
+    for row in rows {
+        out.push_str(&line(row));
+    }
+    out.push_str(&rule(borders.bottom));
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fields, FormattedProcess};
+
+    fn metrics(cpu_usage: &str) -> FormattedMetrics {
+        FormattedMetrics {
+            fields: Fields::from_list(&[crate::Field::Cpu, crate::Field::Mem]),
+            uptime: "3d 4h 12m".to_string(),
+            cpu_usage: cpu_usage.to_string(),
+            per_core_usage: Vec::new(),
+            load_average: None,
+            temperatures: Vec::new(),
+            processes: "312".to_string(),
+            memory_used: "4.10 GB".to_string(),
+            memory_total: "16.0 GB".to_string(),
+            memory_available: "11.2 GB".to_string(),
+            memory_free: "2.10 GB".to_string(),
+            memory_usage_percent: "25.6%".to_string(),
+            swap_used: "0 B".to_string(),
+            swap_total: "0 B".to_string(),
+            swap_usage_percent: "N/A".to_string(),
+            disk_read: String::new(),
+            disk_write: String::new(),
+            net_rx: String::new(),
+            net_tx: String::new(),
+            cpu_color: None,
+            per_core_colors: Vec::new(),
+            memory_color: None,
+            per_disk: Vec::new(),
+            per_interface: Vec::new(),
+            top_processes: Vec::new(),
+            top_memory_processes: Vec::new(),
+            gpu: None,
+            gpu_requested: false,
+            process: None,
+            process_requested: false,
+            trends: Default::default(),
+            deltas: Default::default(),
+            alerts: Vec::new(),
+            width: 80,
+        }
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0.0; 5], 10), "▁▁▁▁▁");
+        assert_eq!(sparkline(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0], 10), "▁▂▃▄▅▆▇█");
+        assert_eq!(sparkline(&[0.0, 0.0, 50.0, 0.0], 10), "▁▁█▁");
+        // Only the most recent values fit
+        assert_eq!(sparkline(&[100.0, 1.0, 2.0], 2), "▅█");
+        assert_eq!(sparkline(&[], 10), "");
+    }
+
+    #[test]
+    fn test_trends_get_their_own_column() {
+        let mut trending = metrics("9.9%");
+        trending.trends.cpu = "▁▅█".to_string();
+        trending.trends.memory = "▁▁▁".to_string();
+
+        assert_eq!(
+            table(&trending, TableStyle::Unicode),
+            "┌───────────┬───────────────────────────┬─────┐\n\
+             │ Uptime    │                 3d 4h 12m │     │\n\
+             │ CPU       │                      9.9% │ ▁▅█ │\n\
+             │ Processes │                       312 │     │\n\
+             │ Memory    │ 4.10 GB / 16.0 GB (25.6%) │ ▁▁▁ │\n\
+             │ Available │    11.2 GB (free 2.10 GB) │     │\n\
+             │ Swap      │           0 B / 0 B (N/A) │     │\n\
+             └───────────┴───────────────────────────┴─────┘\n"
+        );
+    }
+
+    #[test]
+    fn test_deltas_come_before_trends() {
+        let mut changing = metrics("9.9%");
+        changing.deltas.cpu = "+3.1".to_string();
+        changing.deltas.memory = "-0.4".to_string();
+        changing.trends.cpu = "▁▅█".to_string();
+
+        assert_eq!(
+            table(&changing, TableStyle::Ascii),
+            "+-----------+---------------------------+------+-----+\n\
+             | Uptime    |                 3d 4h 12m |      |     |\n\
+             | CPU       |                      9.9% | +3.1 | ▁▅█ |\n\
+             | Processes |                       312 |      |     |\n\
+             | Memory    | 4.10 GB / 16.0 GB (25.6%) | -0.4 |     |\n\
+             | Available |    11.2 GB (free 2.10 GB) |      |     |\n\
+             | Swap      |           0 B / 0 B (N/A) |      |     |\n\
+             +-----------+---------------------------+------+-----+\n"
+        );
+    }
+
+    #[test]
+    fn test_ascii_table() {
+        assert_eq!(
+            table(&metrics("9.9%"), TableStyle::Ascii),
+            "+-----------+---------------------------+\n\
+             | Uptime    |                 3d 4h 12m |\n\
+             | CPU       |                      9.9% |\n\
+             | Processes |                       312 |\n\
+             | Memory    | 4.10 GB / 16.0 GB (25.6%) |\n\
+             | Available |    11.2 GB (free 2.10 GB) |\n\
+             | Swap      |           0 B / 0 B (N/A) |\n\
+             +-----------+---------------------------+\n"
+        );
+    }
+
+    #[test]
+    fn test_columns_follow_widest_value() {
+        let mut wide = metrics("100.0%");
+        wide.memory_used = "15.99 GB".to_string();
+        let text = table(&wide, TableStyle::Unicode);
+
+        let widths: Vec<usize> = text.lines().map(|line| line.chars().count()).collect();
+        assert!(widths.iter().all(|&width| width == widths[0]), "{}", text);
+        assert!(text.starts_with("┌───────────┬"));
+    }
+
+    #[test]
+    fn test_colors_do_not_shift_borders() {
+        let mut colored = metrics("90.0%");
+        colored.cpu_color = Some(Color::Red);
+        let text = table(&colored, TableStyle::Ascii);
+
+        assert!(text.contains("| CPU       | \x1b[31m                    90.0%\x1b[0m |\n"));
+    }
+
+    #[test]
+    fn test_process_table_has_header() {
+        let mut with_processes = metrics("1.0%");
+        with_processes.top_processes = vec![FormattedProcess {
+            pid: "42".to_string(),
+            name: "cargo".to_string(),
+            cpu_usage: "12.5%".to_string(),
+            memory: "1.00 MB".to_string(),
+        }];
+        let text = table(&with_processes, TableStyle::Ascii);
+
+        assert!(text.contains("| PID |   CPU |     MEM | NAME  |\n"));
+        assert!(text.contains("|  42 | 12.5% | 1.00 MB | cargo |\n"));
+    }
+
+    #[test]
+    fn test_columns_stay_aligned() {
+        let mut busy = metrics("100.0%");
+        busy.fields = Fields::all();
+        busy.disk_read = "1023 TiB/s".to_string();
+        busy.disk_write = "0 B/s".to_string();
+        busy.net_rx = "12.3 MB/s".to_string();
+        busy.net_tx = "980 KB/s".to_string();
+
+        let lines = [column_header(None), column_row(&metrics("3.2%"), None), column_row(&busy, None)];
+        assert_eq!(
+            lines,
+            [
+                "   cpu    mem%       dsk-r       dsk-w      net-rx      net-tx",
+                "  3.2%   25.6%           -           -           -           -",
+                "100.0%   25.6%  1023 TiB/s       0 B/s   12.3 MB/s    980 KB/s",
+            ]
+        );
+
+        let timed = [column_header(Some(8)), column_row(&busy, Some("12:00:01"))];
+        assert!(timed[0].starts_with("time         cpu"), "{:?}", timed);
+        assert!(timed[1].starts_with("12:00:01  100.0%"), "{:?}", timed);
+        assert!(timed.iter().all(|line| line.len() == timed[0].len()), "{:?}", timed);
+    }
+}
@@ -1,14 +1,1197 @@
+use std::collections::VecDeque;
 use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::args::parse_args;
-use system_monitor::print_once;
+#[cfg(feature = "serde")]
+use chrono::{DateTime, Local};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+#[cfg(feature = "serve")]
+use tiny_http::{Header, Method, Request, Response, Server};
 
-pub fn run() -> Result<(), Box<dyn Error>> {
-    let args = parse_args();
+#[cfg(feature = "serde")]
+use crate::args::ReplayArgs;
+use crate::args::{
+    AlertArgs, Command, DisplayArgs, Invocation, ListArgs, Listing, LiveArgs, LogArgs, ScheduleArgs, Smooth, SnapshotArgs,
+    StatsdArgs, write_completions,
+};
+use crate::sink::RotatingFile;
+#[cfg(feature = "syslog")]
+use crate::sink::SyslogWriter;
+#[cfg(feature = "tui")]
+use crate::tui;
+#[cfg(feature = "serve")]
+use system_monitor::format::prometheus;
+use system_monitor::devices;
+use system_monitor::rate::{RateTracker, RollingAverage};
+use system_monitor::record::Record;
+use system_monitor::summary::Summary;
+use system_monitor::threshold::{Limits, Status, alerts, evaluate};
+use system_monitor::render::TableStyle;
+use system_monitor::{
+    CollectOptions, Deltas, FormatOptions, MetricsSnapshot, Monitor, OutputFormat, Trends, columns_header, csv_header,
+    csv_schema_line, text_block, write_sample, write_snapshot,
+};
 
-    if !args.live && !args.log {
-        print_once()?;
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+const CLEAR_TO_END: &str = "\x1b[J";
+/// Samples the live sparklines reach back
+const HISTORY: usize = 60;
+/// Narrowest sparkline drawn however small the terminal is
+const MIN_SPARKLINE: usize = 10;
+/// Columns taken by a row's label and value before its sparkline
+const SPARKLINE_OFFSET: usize = 45;
+
+/// Run the requested command and report the threshold status of its sample
+///
+/// `snapshot` checks its sample against the `--*-warn`/`--*-crit` limits;
+/// live and log end with [`Status::Warning`] when `--exit-on-alert` stops
+/// them, and everything else finishes with [`Status::Ok`].
+pub fn run() -> Result<Status, Box<dyn Error>> {
+    let invocation = Invocation::parse();
+    match invocation.resolve()?.into_command() {
+        Command::Snapshot(args) => run_snapshot(&args),
+        Command::Live(args) => {
+            let mut sinks = Sinks::open(&args.statsd, args.record.as_deref())?;
+            #[cfg(feature = "tui")]
+            if args.tui {
+                let options = FormatOptions { table: args.table, ..args.display.format_options(None) };
+                return run_tui(
+                    schedule(&args.schedule, &args.display),
+                    &args.alerts,
+                    &mut sinks,
+                    &args.display.collect_options(),
+                    &options,
+                );
+            }
+            run_live(
+                schedule(&args.schedule, &args.display),
+                &mut sinks,
+                Smoothing { smooth: args.smooth, window: args.window },
+                args.diff,
+                Settings::live(&args),
+                &invocation,
+            )
+        }
+        Command::Log(args) => {
+            let options = args.format_options();
+            let format = args.output_format();
+            let header =
+                (format == OutputFormat::Csv).then(|| format!("{}\n{}", csv_schema_line(), csv_header(&options)));
+            let continues_csv = match &header {
+                Some(header) if !args.truncate => continues_csv(args.output.as_deref(), header),
+                _ => false,
+            };
+            let mut out = open_log_output(&args, header.clone())?;
+            if let Some(header) = header
+                && !continues_csv
+            {
+                writeln!(out, "{}", header)?;
+            }
+            let mut sinks = Sinks::open(&args.statsd, args.record.as_deref())?;
+            run_log(&mut out, schedule(&args.schedule, &args.display), &mut sinks, format, Settings::log(&args), &invocation)
+        }
+        Command::Export(args) => {
+            #[cfg(feature = "serve")]
+            if let Some(addr) = &args.serve {
+                run_serve(addr, args.interval, &args.display.collect_options())?;
+                return Ok(Status::Ok);
+            }
+            let mut out = open_output(args.output.as_deref(), false)?;
+            let options = args.display.format_options(args.output.as_deref());
+            let snapshot = sample_once(args.interval, &args.display.collect_options())?;
+            write_snapshot(&mut out, &snapshot, &args.format, &options)?;
+            Ok(Status::Ok)
+        }
+        #[cfg(feature = "serde")]
+        Command::Replay(args) => run_replay(&args),
+        Command::List(args) => {
+            print_list(&args)?;
+            Ok(Status::Ok)
+        }
+        Command::Completions(args) => {
+            write_completions(args.shell, &mut io::stdout());
+            Ok(Status::Ok)
+        }
+    }
+}
+
+/// Print what `list` asked for, as a table or a JSON array
+fn print_list(args: &ListArgs) -> serde_json::Result<()> {
+    let text = match (args.what, args.json) {
+        (Listing::Interfaces, false) => devices::interface_table(&devices::interfaces()),
+        (Listing::Interfaces, true) => serde_json::to_string_pretty(&devices::interfaces())?,
+        (Listing::Disks, false) => devices::disk_table(&devices::disks()),
+        (Listing::Disks, true) => serde_json::to_string_pretty(&devices::disks())?,
+        (Listing::Sensors, false) => devices::sensor_table(&devices::sensors()),
+        (Listing::Sensors, true) => serde_json::to_string_pretty(&devices::sensors())?,
+    };
+    println!("{}", text.trim_end());
+
+    Ok(())
+}
+
+/// Take one sample, print it and check it against the thresholds
+fn run_snapshot(args: &SnapshotArgs) -> Result<Status, Box<dyn Error>> {
+    let mut out = open_output(args.output.as_deref(), false)?;
+    let options = FormatOptions {
+        table: args.table,
+        ..args.display.format_options(args.output.as_deref())
+    };
+
+    let snapshot = sample_once(args.interval, &args.display.collect_options())?;
+    write_snapshot(&mut out, &snapshot, &args.quiet.output_format(&args.format), &options)?;
+
+    Ok(evaluate(&snapshot, &args.limits.limits(options.mem_basis)))
+}
+
+/// Create a monitor, warning once about `--interfaces`, `--disks` and a
+/// `--pid` or `--pid-name` that match nothing
+///
+/// A missing process is an error instead when the run would stop with it,
+/// since it can't be waited for.
+fn start_monitor(collect: &CollectOptions, process_required: bool) -> Result<Monitor, String> {
+    let monitor = Monitor::with_options(collect.clone());
+    if process_required && let Some(target) = monitor.missing_process() {
+        return Err(format!("no process {}", target));
+    }
+    for name in monitor.missing_interfaces() {
+        eprintln!("warning: no network interface named '{}'", name);
+    }
+    for pattern in monitor.missing_disks() {
+        eprintln!("warning: no disk matches '{}'", pattern);
+    }
+    if let Some(target) = monitor.missing_process() {
+        eprintln!("warning: no process {}", target);
+    }
+
+    Ok(monitor)
+}
+
+/// Take a single sample over `interval`
+fn sample_once(interval: Duration, collect: &CollectOptions) -> Result<MetricsSnapshot, Box<dyn Error>> {
+    let mut monitor = start_monitor(collect, false)?;
+    thread::sleep(interval);
+
+    Ok(monitor.sample()?)
+}
+
+/// `--watch-process` always stops with its process
+fn schedule(args: &ScheduleArgs, display: &DisplayArgs) -> Schedule {
+    let with_process = args.exit_with_process || display.watch_process.is_some();
+    Schedule { with_process, ..Schedule::new(args.interval, args.count, args.duration) }
+}
+
+/// Open the `--output` target, falling back to stdout for no path or "-"
+///
+/// Files are appended to unless `truncate` asks for a fresh one.
+fn open_output(path: Option<&Path>, truncate: bool) -> Result<Box<dyn Write>, Box<dyn Error>> {
+    match path {
+        None => Ok(Box::new(io::stdout())),
+        Some(path) if path == Path::new("-") => Ok(Box::new(io::stdout())),
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(!truncate)
+                .write(true)
+                .truncate(truncate)
+                .open(path)
+                .map_err(|err| output_error(path, &err))?;
+
+            Ok(Box::new(BufWriter::new(file)))
+        }
+    }
+}
+
+/// Why `path` couldn't be opened, naming a missing directory rather than
+/// leaving "No such file or directory" to suggest the file should exist
+fn output_error(path: &Path, err: &io::Error) -> String {
+    if err.kind() == io::ErrorKind::NotFound
+        && let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty())
+        && !parent.is_dir()
+    {
+        return format!("cannot open output file {}: directory {} does not exist", path.display(), parent.display());
+    }
+
+    format!("cannot open output file {}: {}", path.display(), err)
+}
+
+/// Where `log` writes: syslog with `--syslog`, a [`RotatingFile`] with
+/// `--max-file-size`, otherwise [`open_output`]
+///
+/// A rotating file repeats `csv_header` at the top of each new file.
+fn open_log_output(args: &LogArgs, csv_header: Option<String>) -> Result<Box<dyn Write>, Box<dyn Error>> {
+    #[cfg(feature = "syslog")]
+    if args.syslog {
+        return Ok(Box::new(SyslogWriter::connect(args.syslog_facility)?));
+    }
+
+    if let Some(max_size) = args.max_file_size {
+        let path = match args.output.as_deref() {
+            Some(path) if path != Path::new("-") => path,
+            _ => return Err("--max-file-size needs an --output file".into()),
+        };
+        let mut file = RotatingFile::open(path, max_size, args.max_files, args.truncate)
+            .map_err(|err| output_error(path, &err))?;
+        if let Some(header) = csv_header {
+            file = file.with_header(header);
+        }
+        return Ok(Box::new(file));
+    }
+
+    open_output(args.output.as_deref(), args.truncate)
+}
+
+/// Whether `path` is a CSV file that already starts with `header`, so
+/// appending to it must not repeat the header
+///
+/// Only as many lines as the header has are read. A file that starts with a
+/// different schema or different columns gets a warning and a fresh header
+/// below its old rows.
+fn continues_csv(path: Option<&Path>, header: &str) -> bool {
+    let Some(path) = path.filter(|path| *path != Path::new("-")) else {
+        return false;
+    };
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+
+    let start: Vec<String> = BufReader::new(file).lines().take(header.lines().count()).map_while(Result::ok).collect();
+    if start.is_empty() {
+        return false;
+    }
+    if start.iter().map(String::as_str).eq(header.lines()) {
+        return true;
+    }
+
+    eprintln!("warning: {} starts with a different schema or columns; adding a new CSV header", path.display());
+    false
+}
+
+/// Install a handler for Ctrl-C, SIGTERM and SIGHUP and return the flag it clears
+///
+/// The handler runs on its own thread and only clears the flag and wakes the
+/// thread that installed it, so a [`wait_while_running`] there ends at once.
+/// With `reload`, SIGHUP sets that flag instead of stopping.
+fn running_flag(reload: Option<&Arc<AtomicBool>>) -> Result<Arc<AtomicBool>, Box<dyn Error>> {
+    let reload = reload.map(Arc::clone);
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_flag = Arc::clone(&running);
+    let sampler = thread::current();
+
+    #[cfg(unix)]
+    {
+        use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+        let mut signals = signal_hook::iterator::Signals::new([SIGINT, SIGTERM, SIGHUP])?;
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                match &reload {
+                    Some(reload) if signal == SIGHUP => reload.store(true, Ordering::SeqCst),
+                    _ => handler_flag.store(false, Ordering::SeqCst),
+                }
+                sampler.unpark();
+            }
+        });
+    }
+    // No SIGHUP to reload on, and ctrlc covers the console's close events too
+    #[cfg(not(unix))]
+    {
+        drop(reload);
+        ctrlc::set_handler(move || {
+            handler_flag.store(false, Ordering::SeqCst);
+            sampler.unpark();
+        })?;
+    }
+
+    Ok(running)
+}
+
+/// Sleep for `window`, cut short by Ctrl-C; false when interrupted
+///
+/// Stopping at once rather than at the end of the window means the last
+/// complete sample is what stays on screen, with no partial one after it.
+fn wait_while_running(window: Duration, running: &AtomicBool) -> bool {
+    let deadline = Instant::now() + window;
+    while running.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        // May wake early for no reason; the loop sleeps again
+        thread::park_timeout(deadline - now);
+    }
+
+    false
+}
+
+/// A flag that SIGUSR1 sets and SIGUSR2 clears, for pausing the sampling loop
+///
+/// See [`watch_pause_signals`]; `kill -USR1` pauses a run in the background
+/// without stopping it.
+fn pause_flag() -> Result<Arc<AtomicBool>, Box<dyn Error>> {
+    let paused = Arc::new(AtomicBool::new(false));
+    watch_pause_signals(Arc::clone(&paused))?;
+
+    Ok(paused)
+}
+
+/// Set `paused` on SIGUSR1 and clear it on SIGUSR2
+///
+/// Like the Ctrl-C handler, the signal thread wakes the thread that started
+/// it, so a resume ends [`hold_while_paused`] at once. Other platforms have no
+/// such signals and only pause with `p`; see [`watch_keys`].
+fn watch_pause_signals(paused: Arc<AtomicBool>) -> Result<(), Box<dyn Error>> {
+    #[cfg(unix)]
+    {
+        use signal_hook::consts::{SIGUSR1, SIGUSR2};
+
+        let mut signals = signal_hook::iterator::Signals::new([SIGUSR1, SIGUSR2])?;
+        let sampler = thread::current();
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                paused.store(signal == SIGUSR1, Ordering::SeqCst);
+                sampler.unpark();
+            }
+        });
     }
+    #[cfg(not(unix))]
+    let _ = paused;
 
     Ok(())
 }
+
+/// Toggle `paused` on `p` and clear `running` on Ctrl-C, read from the terminal
+///
+/// Only for a terminal in raw mode, where keys arrive without Enter and
+/// Ctrl-C is a key rather than SIGINT. Like the signal threads, it wakes the
+/// thread that started it.
+fn watch_keys(paused: Arc<AtomicBool>, running: Arc<AtomicBool>) {
+    let sampler = thread::current();
+    thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            let Event::Key(key) = event else { continue };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('p') => {
+                    paused.fetch_xor(true, Ordering::SeqCst);
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    running.store(false, Ordering::SeqCst);
+                }
+                _ => continue,
+            }
+            sampler.unpark();
+        }
+    });
+}
+
+/// Sit out a pause until it is resumed or Ctrl-C; false when not paused
+///
+/// `note` is shown once on the way in. On the way out the monitor is
+/// rebaselined, so the next sample covers a window after the resume instead
+/// of the whole pause, whose deltas would show as one spike.
+fn hold_while_paused(
+    paused: &AtomicBool,
+    running: &AtomicBool,
+    monitor: &mut Monitor,
+    note: impl FnOnce() -> io::Result<()>,
+) -> io::Result<bool> {
+    if !paused.load(Ordering::SeqCst) {
+        return Ok(false);
+    }
+
+    note()?;
+    while paused.load(Ordering::SeqCst) && running.load(Ordering::SeqCst) {
+        thread::park();
+    }
+    monitor.rebaseline();
+
+    Ok(true)
+}
+
+/// What a SIGHUP can change while `live` or `log` runs
+///
+/// These are the settings looked at sample by sample. The output, its format,
+/// the run's length and live's smoothing stay as they started.
+struct Settings {
+    interval: Duration,
+    limits: Limits,
+    exit_on_alert: bool,
+    collect: CollectOptions,
+    options: FormatOptions,
+}
+
+impl Settings {
+    fn live(args: &LiveArgs) -> Settings {
+        let options = FormatOptions { table: args.table, ..args.display.format_options(None) };
+        Settings::new(&args.schedule, &args.alerts, &args.display, options)
+    }
+
+    fn log(args: &LogArgs) -> Settings {
+        Settings::new(&args.schedule, &args.alerts, &args.display, args.format_options())
+    }
+
+    fn new(schedule: &ScheduleArgs, alert: &AlertArgs, display: &DisplayArgs, options: FormatOptions) -> Settings {
+        Settings {
+            interval: schedule.interval,
+            limits: alert.limits(options.mem_basis),
+            exit_on_alert: alert.exit_on_alert,
+            collect: display.collect_options(),
+            options,
+        }
+    }
+}
+
+/// Re-read the config file after a SIGHUP and switch to what it says now;
+/// true when the new settings took over
+///
+/// The file is merged with the original command line again, so flags given
+/// there still win. An invalid file leaves the old settings in place.
+fn reload(invocation: &Invocation, settings: &mut Settings, schedule: &mut Schedule) -> bool {
+    let reloaded = invocation.resolve().map(|args| match args.into_command() {
+        Command::Live(args) => Some(Settings::live(&args)),
+        Command::Log(args) => Some(Settings::log(&args)),
+        _ => None,
+    });
+
+    match reloaded {
+        Ok(Some(new)) => {
+            schedule.interval = new.interval;
+            *settings = new;
+            eprintln!("note: reloaded the configuration");
+            true
+        }
+        Ok(None) => false,
+        Err(err) => {
+            eprintln!("warning: keeping the old settings: {}", err);
+            false
+        }
+    }
+}
+
+/// How `live` steadies the figures it shows; the summary and sinks still get the raw samples
+struct Smoothing {
+    /// `--smooth`, for CPU and memory usage, and with a sample count also the total rates
+    smooth: Option<Smooth>,
+    /// `--window`, for disk and network rates
+    window: Option<Duration>,
+}
+
+/// Exponential moving average for `--smooth`, starting at the first value
+struct MovingAverage {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl MovingAverage {
+    fn new(alpha: f64) -> Self {
+        MovingAverage { alpha, value: None }
+    }
+
+    fn add(&mut self, sample: f64) -> f64 {
+        let value = match self.value {
+            Some(value) => value + self.alpha * (sample - value),
+            None => sample,
+        };
+        self.value = Some(value);
+
+        value
+    }
+}
+
+/// Either kind of `--smooth` average
+enum Average {
+    Exponential(MovingAverage),
+    Rolling(RollingAverage),
+}
+
+impl Average {
+    fn new(smooth: Smooth) -> Self {
+        match smooth {
+            Smooth::Exponential(alpha) => Average::Exponential(MovingAverage::new(alpha)),
+            Smooth::Rolling(samples) => Average::Rolling(RollingAverage::new(samples)),
+        }
+    }
+
+    fn add(&mut self, sample: f64) -> f64 {
+        match self {
+            Average::Exponential(average) => average.add(sample),
+            Average::Rolling(average) => average.add(sample),
+        }
+    }
+}
+
+/// Everywhere a sample goes besides the output: `--statsd-host` and the `--record` file
+struct Sinks {
+    statsd: Option<StatsdSink>,
+    record: Option<BufWriter<File>>,
+}
+
+impl Sinks {
+    fn open(statsd: &StatsdArgs, record: Option<&Path>) -> Result<Sinks, Box<dyn Error>> {
+        let record = match record {
+            Some(path) => Some(BufWriter::new(
+                File::create(path).map_err(|err| format!("cannot create recording {}: {}", path.display(), err))?,
+            )),
+            None => None,
+        };
+
+        Ok(Sinks { statsd: StatsdSink::connect(statsd)?, record })
+    }
+
+    /// Send `snapshot` on; the recording is flushed so an interrupted run keeps every sample
+    fn send(&mut self, snapshot: &MetricsSnapshot) -> io::Result<()> {
+        if let Some(statsd) = &self.statsd {
+            statsd.send(snapshot);
+        }
+        if let Some(record) = &mut self.record {
+            writeln!(record, "{}", Record::new(snapshot).to_line()?)?;
+            record.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Pushes every sample to `--statsd-host` as one datagram of gauges
+struct StatsdSink {
+    socket: UdpSocket,
+    prefix: String,
+    tags: Vec<String>,
+}
+
+impl StatsdSink {
+    /// `None` without `--statsd-host`
+    fn connect(args: &StatsdArgs) -> Result<Option<StatsdSink>, Box<dyn Error>> {
+        let Some(host) = &args.statsd_host else {
+            return Ok(None);
+        };
+        let addr = host
+            .to_socket_addrs()
+            .map_err(|err| format!("cannot resolve StatsD host {}: {}", host, err))?
+            .next()
+            .ok_or_else(|| format!("StatsD host {} has no address", host))?;
+        let socket = UdpSocket::bind(if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+        socket.connect(addr)?;
+
+        Ok(Some(StatsdSink { socket, prefix: args.statsd_prefix.clone(), tags: args.statsd_tags.clone() }))
+    }
+
+    /// Send `snapshot`; a collector that is down or restarting only costs a warning
+    fn send(&self, snapshot: &MetricsSnapshot) {
+        let payload = snapshot.to_statsd(&self.prefix, &self.tags).join("\n");
+        if let Err(err) = self.socket.send(payload.as_bytes()) {
+            eprintln!("warning: cannot send to StatsD: {}", err);
+        }
+    }
+}
+
+fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+}
+
+/// Paces the live and log loops and ends bounded runs
+///
+/// A run stops once `--count` samples were taken or `--duration` has passed,
+/// whichever comes first; with neither it goes on until interrupted.
+/// `--exit-with-process` also ends it when the watched process exits.
+struct Schedule {
+    interval: Duration,
+    count: Option<u64>,
+    duration: Option<Duration>,
+    with_process: bool,
+    started: Instant,
+    taken: u64,
+}
+
+impl Schedule {
+    fn new(interval: Duration, count: Option<u64>, duration: Option<Duration>) -> Self {
+        Schedule { interval, count, duration, with_process: false, started: Instant::now(), taken: 0 }
+    }
+
+    /// Sampling window for the next sample, or `None` when the run is over
+    ///
+    /// Near the end of `--duration` the window shrinks to whatever is left,
+    /// as long as that is still at least half an interval.
+    fn next_window(&self) -> Option<Duration> {
+        if self.count.is_some_and(|count| self.taken >= count) {
+            return None;
+        }
+
+        let Some(duration) = self.duration else {
+            return Some(self.interval);
+        };
+        let remaining = duration.saturating_sub(self.started.elapsed());
+
+        if remaining >= self.interval {
+            Some(self.interval)
+        } else if remaining >= self.interval / 2 && !remaining.is_zero() {
+            Some(remaining)
+        } else {
+            None
+        }
+    }
+
+    fn record_sample(&mut self) {
+        self.taken += 1;
+    }
+
+    /// Whether `--count`, `--duration` or `--exit-with-process` ends the run on its own
+    fn is_bounded(&self) -> bool {
+        self.count.is_some() || self.duration.is_some() || self.with_process
+    }
+
+    /// Note the watched process exiting; true when that ends the run
+    fn process_exited(&self, monitor: &mut Monitor) -> bool {
+        let Some(process) = monitor.take_exited_process() else {
+            return false;
+        };
+
+        if self.with_process {
+            eprintln!("note: process {} exited, stopping", process);
+        } else {
+            eprintln!("note: process {} exited", process);
+        }
+        self.with_process
+    }
+}
+
+/// Hides the cursor while alive and shows it again on drop, even on early return
+struct CursorGuard;
+
+impl CursorGuard {
+    fn hide() -> io::Result<Self> {
+        let mut stdout = io::stdout();
+        write!(stdout, "{}", HIDE_CURSOR)?;
+        stdout.flush()?;
+
+        Ok(CursorGuard)
+    }
+}
+
+impl Drop for CursorGuard {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "{}", SHOW_CURSOR);
+        let _ = stdout.flush();
+    }
+}
+
+/// Keeps the terminal in raw mode while alive and restores it on drop
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn enable() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+
+        Ok(RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Where `live` draws: the metrics block redrawn in place on a terminal
+///
+/// When stdout is not a terminal the escape codes would only corrupt the
+/// output, so each sample is printed as a plain block instead. CPU, memory
+/// and network rows carry sparklines of the last [`HISTORY`] samples, except
+/// in ASCII tables, and with `--diff` their changes since the previous one.
+///
+/// When stdin is a terminal too, it is put in raw mode so `p` can be read;
+/// raw mode leaves line endings alone, so lines end in `\r\n` until [`finish`](Self::finish).
+struct LiveScreen {
+    stdout: io::Stdout,
+    in_place: bool,
+    diff: bool,
+    _cursor: Option<CursorGuard>,
+    raw_mode: Option<RawModeGuard>,
+    drawn_lines: usize,
+    history: VecDeque<MetricsSnapshot>,
+}
+
+impl LiveScreen {
+    fn new(diff: bool) -> io::Result<Self> {
+        let stdout = io::stdout();
+        let in_place = stdout.is_terminal();
+        let cursor = if in_place { Some(CursorGuard::hide()?) } else { None };
+        let raw_mode = if in_place && io::stdin().is_terminal() { Some(RawModeGuard::enable()?) } else { None };
+
+        let history = VecDeque::with_capacity(HISTORY + 1);
+        Ok(LiveScreen { stdout, in_place, diff, _cursor: cursor, raw_mode, drawn_lines: 0, history })
+    }
+
+    /// Whether keys can be read, for [`watch_keys`]
+    fn reads_keys(&self) -> bool {
+        self.raw_mode.is_some()
+    }
+
+    fn write_text(&mut self, text: &str) -> io::Result<()> {
+        if self.reads_keys() {
+            write!(self.stdout, "{}", text.replace('\n', "\r\n"))
+        } else {
+            write!(self.stdout, "{}", text)
+        }
+    }
+
+    /// Replace the previous block with `snapshot`'s
+    fn draw(&mut self, snapshot: &MetricsSnapshot, options: &FormatOptions) -> io::Result<()> {
+        self.history.push_back(snapshot.clone());
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+        // Follow terminal resizes between frames
+        let frame = FormatOptions { width: terminal_width(), ..options.clone() };
+        let mut formatted = snapshot.format_with(&frame);
+        if frame.table != Some(TableStyle::Ascii) {
+            let width = frame.width.unwrap_or(80).saturating_sub(SPARKLINE_OFFSET).clamp(MIN_SPARKLINE, HISTORY);
+            formatted.trends = Trends::from_history(self.history.make_contiguous(), width);
+        }
+        if self.diff && let [.., previous, current] = self.history.make_contiguous() {
+            formatted.deltas = Deltas::between(previous, current, &frame);
+        }
+        let block = formatted.block(frame.table);
+
+        if self.in_place && self.drawn_lines > 0 {
+            // Jump back to the first line of the previous block and wipe it
+            write!(self.stdout, "\x1b[{}A\r{}", self.drawn_lines, CLEAR_TO_END)?;
+        }
+        self.write_text(&block)?;
+        self.drawn_lines = block.lines().count();
+
+        self.stdout.flush()
+    }
+
+    /// Note the pause under the last block; the next draw replaces it with the block
+    fn show_paused(&mut self) -> io::Result<()> {
+        let resume = if self.reads_keys() { "p or SIGUSR2" } else { "SIGUSR2" };
+        self.write_text(&format!("PAUSED ({} resumes)\n", resume))?;
+        self.drawn_lines += 1;
+
+        self.stdout.flush()
+    }
+
+    /// Print `summary` below the last block, after replacing that with `last`'s when given
+    ///
+    /// An interrupted run passes its last raw sample, so what stays on screen is
+    /// that reading rather than a smoothed one.
+    fn finish(&mut self, last: Option<&MetricsSnapshot>, summary: &Summary, options: &FormatOptions) -> io::Result<()> {
+        self.raw_mode = None;
+        if let Some(last) = last {
+            if self.in_place && self.drawn_lines > 0 {
+                write!(self.stdout, "\x1b[{}A\r{}", self.drawn_lines, CLEAR_TO_END)?;
+            }
+            write!(self.stdout, "{}", text_block(last, options))?;
+        }
+        write!(self.stdout, "\n{}", summary.render(options))?;
+        self.stdout.flush()
+    }
+}
+
+/// Redraw the metrics block in place until interrupted
+///
+/// See [`LiveScreen`] for what is drawn. A bounded or interrupted run ends
+/// with a summary of all samples. SIGUSR1 or `p` pauses sampling, SIGUSR2 or
+/// `p` again resumes it and SIGHUP reloads the config file; see [`Settings`].
+fn run_live(
+    mut schedule: Schedule,
+    sinks: &mut Sinks,
+    smoothing: Smoothing,
+    diff: bool,
+    mut settings: Settings,
+    invocation: &Invocation,
+) -> Result<Status, Box<dyn Error>> {
+    let reloads = Arc::new(AtomicBool::new(false));
+    let running = running_flag(Some(&reloads))?;
+    let paused = pause_flag()?;
+    let mut monitor = start_monitor(&settings.collect, schedule.with_process)?;
+    let mut screen = LiveScreen::new(diff)?;
+    if screen.reads_keys() {
+        watch_keys(Arc::clone(&paused), Arc::clone(&running));
+    }
+    let mut summary = Summary::default();
+    let mut cpu_average = smoothing.smooth.map(Average::new);
+    let mut memory_average = smoothing.smooth.map(Average::new);
+    // Disk read and write, network received and sent
+    let mut rate_averages = match smoothing.smooth {
+        Some(Smooth::Rolling(samples)) => Some([(); 4].map(|_| RollingAverage::new(samples))),
+        _ => None,
+    };
+    let mut rates = smoothing.window.map(RateTracker::new);
+    // Shown once more after Ctrl-C, raw
+    let mut last = None;
+
+    while let Some(window) = schedule.next_window() && wait_while_running(window, &running) {
+        if hold_while_paused(&paused, &running, &mut monitor, || screen.show_paused())? {
+            continue;
+        }
+        if reloads.swap(false, Ordering::SeqCst) && reload(invocation, &mut settings, &mut schedule) {
+            // What is collected may have changed; the new monitor's first window starts now
+            monitor = start_monitor(&settings.collect, schedule.with_process)?;
+            continue;
+        }
+        // Alerts go by the raw values; smoothing only changes what is shown
+        let mut raw = monitor.sample()?;
+        raw.alerts = alerts(&raw, &settings.limits);
+        let mut snapshot = raw.clone();
+        if let Some(average) = &mut cpu_average {
+            snapshot.cpu_usage_percent = average.add(raw.cpu_usage_percent as f64) as f32;
+        }
+        if let Some(average) = &mut memory_average {
+            snapshot.memory_used_bytes = average.add(raw.memory_used_bytes as f64).round() as u64;
+        }
+        if let Some(rates) = &mut rates {
+            rates.apply(&mut snapshot);
+        }
+        if let Some([read, write, rx, tx]) = &mut rate_averages {
+            let steady = |average: &mut RollingAverage, rate: u64| average.add(rate as f64).round() as u64;
+            snapshot.disk_read_per_sec = steady(read, snapshot.disk_read_per_sec);
+            snapshot.disk_write_per_sec = steady(write, snapshot.disk_write_per_sec);
+            snapshot.net_rx_per_sec = snapshot.net_rx_per_sec.map(|rate| steady(rx, rate));
+            snapshot.net_tx_per_sec = snapshot.net_tx_per_sec.map(|rate| steady(tx, rate));
+        }
+
+        // A sample may finish after Ctrl-C; don't draw over the shell prompt
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        screen.draw(&snapshot, &settings.options)?;
+        sinks.send(&raw)?;
+        summary.add(&raw);
+        schedule.record_sample();
+
+        if settings.exit_on_alert && !raw.alerts.is_empty() {
+            return Ok(Status::Warning);
+        }
+        last = Some(raw);
+        if schedule.process_exited(&mut monitor) {
+            break;
+        }
+    }
+
+    if schedule.is_bounded() || !running.load(Ordering::SeqCst) {
+        let interrupted = !running.load(Ordering::SeqCst);
+        screen.finish(last.as_ref().filter(|_| interrupted), &summary, &settings.options)?;
+    }
+
+    Ok(Status::Ok)
+}
+
+/// Show the samples in the full-screen dashboard until the user quits
+///
+/// The disk and interface panels need the breakdowns, so they are collected
+/// for every group that is. The summary follows once the terminal is back.
+#[cfg(feature = "tui")]
+fn run_tui(
+    mut schedule: Schedule,
+    alert: &AlertArgs,
+    sinks: &mut Sinks,
+    collect: &CollectOptions,
+    options: &FormatOptions,
+) -> Result<Status, Box<dyn Error>> {
+    let running = running_flag(None)?;
+    let collect = CollectOptions { per_disk: collect.disk, per_interface: collect.network, ..collect.clone() };
+    let mut monitor = start_monitor(&collect, schedule.with_process)?;
+    let mut summary = Summary::default();
+    let mut app = tui::App::new(options);
+    watch_pause_signals(app.paused())?;
+    let mut was_paused = false;
+    let mut status = Status::Ok;
+    // Notes written over the dashboard would be drawn over right away
+    let mut exited = None;
+    // Leaving the alternate screen takes the dashboard with it, so this is printed after
+    let mut last = None;
+
+    {
+        let mut screen = tui::Screen::enter()?;
+        screen.draw(&app)?;
+        while running.load(Ordering::SeqCst) && let Some(window) = schedule.next_window() {
+            if !screen.wait(window, &mut app)? {
+                break;
+            }
+            // Keys still work while paused, so this waits out windows rather than parking
+            let paused = app.paused().load(Ordering::SeqCst);
+            if paused || was_paused {
+                if !paused {
+                    monitor.rebaseline();
+                }
+                was_paused = paused;
+                screen.draw(&app)?;
+                continue;
+            }
+            let mut snapshot = monitor.sample()?;
+            snapshot.alerts = alerts(&snapshot, &alert.limits(options.mem_basis));
+            sinks.send(&snapshot)?;
+            summary.add(&snapshot);
+            schedule.record_sample();
+
+            let stop = alert.exit_on_alert && !snapshot.alerts.is_empty();
+            last = Some(snapshot.clone());
+            app.push(snapshot);
+            screen.draw(&app)?;
+            if stop {
+                status = Status::Warning;
+                break;
+            }
+            exited = monitor.take_exited_process().or(exited);
+            if exited.is_some() && schedule.with_process {
+                break;
+            }
+        }
+    }
+
+    if let Some(process) = exited {
+        eprintln!("note: process {} exited", process);
+    }
+    if let Some(last) = &last {
+        println!("{}", text_block(last, options));
+    }
+    print!("{}", summary.render(options));
+    Ok(status)
+}
+
+/// Print one timestamped line per sample until interrupted
+///
+/// A bounded or interrupted run ends with a summary on stderr, as one JSON
+/// object when the samples are JSON; after Ctrl-C the last sample comes
+/// first there as a text block, unless the samples are JSON. SIGUSR1 pauses sampling, SIGUSR2
+/// resumes it and SIGHUP reloads the config file; see [`Settings`]. A pause is
+/// marked in the samples; see [`write_pause_marker`]. CSV keeps the format
+/// options it started with, since its header is already written.
+fn run_log(
+    out: &mut dyn Write,
+    mut schedule: Schedule,
+    sinks: &mut Sinks,
+    format: OutputFormat,
+    mut settings: Settings,
+    invocation: &Invocation,
+) -> Result<Status, Box<dyn Error>> {
+    let reloads = Arc::new(AtomicBool::new(false));
+    let running = running_flag(Some(&reloads))?;
+    let paused = pause_flag()?;
+    let mut monitor = start_monitor(&settings.collect, schedule.with_process)?;
+    let csv_options = (format == OutputFormat::Csv).then(|| settings.options.clone());
+    // Shown as a block after Ctrl-C
+    let mut last = None;
+    let mut summary = Summary::default();
+
+    while let Some(window) = schedule.next_window() && wait_while_running(window, &running) {
+        if hold_while_paused(&paused, &running, &mut monitor, || write_pause_marker(out, &format))? {
+            continue;
+        }
+        if reloads.swap(false, Ordering::SeqCst) && reload(invocation, &mut settings, &mut schedule) {
+            // What is collected may have changed; the new monitor's first window starts now
+            monitor = start_monitor(&settings.collect, schedule.with_process)?;
+            if let Some(options) = &csv_options {
+                settings.options = options.clone();
+            }
+            continue;
+        }
+        let mut snapshot = monitor.sample()?;
+        snapshot.alerts = alerts(&snapshot, &settings.limits);
+
+        // Collecting takes a moment too; drop a sample Ctrl-C interrupted
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        write_column_header(out, &snapshot, &format, &settings.options, summary.samples)?;
+        write_sample(out, &snapshot, &format, &settings.options)?;
+        sinks.send(&snapshot)?;
+        summary.add(&snapshot);
+        schedule.record_sample();
+
+        if settings.exit_on_alert && !snapshot.alerts.is_empty() {
+            return Ok(Status::Warning);
+        }
+        last = Some(snapshot);
+        if schedule.process_exited(&mut monitor) {
+            break;
+        }
+    }
+
+    // With the summary on stderr, away from the sample stream; a JSON summary stays parseable
+    let json = matches!(format, OutputFormat::Json | OutputFormat::Ndjson);
+    if !running.load(Ordering::SeqCst) && !json && let Some(last) = &last {
+        eprintln!("{}", text_block(last, &settings.options));
+    }
+    if schedule.is_bounded() || !running.load(Ordering::SeqCst) {
+        print_log_summary(&summary, &format, &settings.options)?;
+    }
+
+    Ok(Status::Ok)
+}
+
+/// Mark a pause among the samples, in a form their readers skip or can pick out
+///
+/// JSON gets an `{"event":"paused"}` object and CSV and columns a `#` comment
+/// line, like the schema line. Formats with no room for one get a note on
+/// stderr instead, like the summary.
+fn write_pause_marker(out: &mut dyn Write, format: &OutputFormat) -> io::Result<()> {
+    match format {
+        OutputFormat::Json | OutputFormat::Ndjson => writeln!(out, r#"{{"event":"paused"}}"#)?,
+        OutputFormat::Csv | OutputFormat::Columns { .. } => writeln!(out, "# paused until SIGUSR2")?,
+        _ => return writeln!(io::stderr(), "note: paused until SIGUSR2"),
+    }
+    out.flush()
+}
+
+/// Write the columns header ahead of the first row and then every `header_every` rows,
+/// like vmstat; other formats have no header here
+fn write_column_header(
+    out: &mut dyn Write,
+    snapshot: &MetricsSnapshot,
+    format: &OutputFormat,
+    options: &FormatOptions,
+    rows: usize,
+) -> io::Result<()> {
+    if let OutputFormat::Columns { header_every } = *format
+        // Only 0 is a multiple of 0, so that prints the header once
+        && rows.is_multiple_of(header_every)
+    {
+        writeln!(out, "{}", columns_header(snapshot, options))?;
+    }
+
+    Ok(())
+}
+
+/// The summary goes to stderr to keep the sample stream machine readable;
+/// JSON samples get a JSON summary
+fn print_log_summary(summary: &Summary, format: &OutputFormat, options: &FormatOptions) -> serde_json::Result<()> {
+    if matches!(format, OutputFormat::Json | OutputFormat::Ndjson) {
+        eprintln!("{}", summary.to_json()?);
+    } else {
+        eprint!("{}", summary.render(options));
+    }
+
+    Ok(())
+}
+
+/// Play a `--record` file back through the log output, or the live one with `--live`
+///
+/// Samples keep their recorded spacing, shortened by `--speed`, and alerts
+/// are worked out again from the thresholds given now.
+#[cfg(feature = "serde")]
+fn run_replay(args: &ReplayArgs) -> Result<Status, Box<dyn Error>> {
+    let file = File::open(&args.file)
+        .map_err(|err| format!("cannot open recording {}: {}", args.file.display(), err))?;
+    let running = running_flag(None)?;
+    let options = FormatOptions { table: args.table, ..args.display.format_options(None) };
+    let format = args.format.output_format();
+    let mut screen = if args.live { Some(LiveScreen::new(false)?) } else { None };
+    let mut out = io::stdout();
+    let mut summary = Summary::default();
+    let mut previous: Option<DateTime<Local>> = None;
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = Record::parse(&line)
+            .map_err(|err| format!("{}, line {}: {}", args.file.display(), number + 1, err))?;
+        let mut snapshot = record.into_snapshot();
+
+        if let Some(previous) = previous
+            && let Ok(gap) = (snapshot.timestamp - previous).to_std()
+            && !wait_while_running(gap.div_f64(args.speed), &running)
+        {
+            break;
+        }
+        previous = Some(snapshot.timestamp);
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        snapshot.alerts = alerts(&snapshot, &args.alerts.limits(options.mem_basis));
+        match &mut screen {
+            Some(screen) => screen.draw(&snapshot, &options)?,
+            None => {
+                write_column_header(&mut out, &snapshot, &format, &options, summary.samples)?;
+                write_sample(&mut out, &snapshot, &format, &options)?
+            }
+        }
+        summary.add(&snapshot);
+
+        if args.alerts.exit_on_alert && !snapshot.alerts.is_empty() {
+            return Ok(Status::Warning);
+        }
+    }
+
+    match &mut screen {
+        Some(screen) => screen.finish(None, &summary, &options)?,
+        None => print_log_summary(&summary, &format, &options)?,
+    }
+
+    Ok(Status::Ok)
+}
+
+/// Answer `GET /metrics` with Prometheus text until interrupted
+///
+/// A sample is reused until it is `ttl` old, so a scrape normally doesn't
+/// wait for a sampling window: the next one simply covers the time since the
+/// previous sample. Collection errors are reported as 503.
+#[cfg(feature = "serve")]
+fn run_serve(addr: &str, ttl: Duration, collect: &CollectOptions) -> Result<(), Box<dyn Error>> {
+    let running = running_flag(None)?;
+    let server = Server::http(addr).map_err(|err| format!("cannot listen on {}: {}", addr, err))?;
+    let mut monitor = start_monitor(collect, false)?;
+    let mut totals = prometheus::Totals::default();
+    let mut scrape = |monitor: &mut Monitor| {
+        monitor.sample().map(|snapshot| {
+            totals.add(&snapshot);
+            prometheus::render_scrape(&snapshot, &totals)
+        })
+    };
+
+    // Prime the cache so the first scrape has a full window behind it
+    thread::sleep(ttl);
+    let mut cached = (Instant::now(), scrape(&mut monitor));
+
+    while running.load(Ordering::SeqCst) {
+        // Wake up regularly to notice Ctrl-C
+        let Some(request) = server.recv_timeout(Duration::from_millis(200))? else {
+            continue;
+        };
+
+        if request.url() != "/metrics" {
+            respond(request, 404, "not found\n")?;
+            continue;
+        }
+        if *request.method() != Method::Get {
+            respond(request, 405, "only GET is supported\n")?;
+            continue;
+        }
+
+        if cached.0.elapsed() >= ttl {
+            cached = (Instant::now(), scrape(&mut monitor));
+        }
+        match &cached.1 {
+            Ok(body) => respond(request, 200, body)?,
+            Err(err) => respond(request, 503, &format!("collection failed: {}\n", err))?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "serve")]
+fn respond(request: Request, status: u16, body: &str) -> io::Result<()> {
+    let content_type = Header::from_bytes("Content-Type", prometheus::CONTENT_TYPE).expect("valid header");
+    request.respond(Response::from_string(body).with_status_code(status).with_header(content_type))
+}
@@ -0,0 +1,270 @@
+//! Destinations for the log command's samples besides stdout and plain files
+//!
+//! Each sink is a [`Write`], so samples go through the same formatting as
+//! every other output and only the transport differs.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "syslog")]
+pub use syslog::{Facility, SyslogWriter};
+
+/// An `--output` file that moves aside once it grows past `--max-file-size`
+///
+/// The full file is renamed to `<path>.1`, older ones shift up to `.2`,
+/// `.3` and so on, and anything past `max_files` is deleted. Rotation only
+/// happens on [`flush`](Write::flush), which the log loop calls once per
+/// sample, so a sample is never split across two files.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    header: Option<String>,
+    file: BufWriter<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Append to `path`, counting what is already there toward the limit,
+    /// or empty it first with `truncate`; rotated files are left alone either way
+    pub fn open(path: &Path, max_size: u64, max_files: usize, truncate: bool) -> io::Result<RotatingFile> {
+        let file = OpenOptions::new().create(true).append(!truncate).write(true).truncate(truncate).open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(RotatingFile { path: path.to_path_buf(), max_size, max_files, header: None, file: BufWriter::new(file), size })
+    }
+
+    /// Lines written at the top of every new file, e.g. the CSV header
+    pub fn with_header(mut self, header: String) -> RotatingFile {
+        self.header = Some(header);
+        self
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let oldest = self.rotated(self.max_files.max(1));
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    fs::rename(from, self.rotated(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+
+        self.file = BufWriter::new(File::create(&self.path)?);
+        self.size = 0;
+        if let Some(header) = &self.header {
+            writeln!(self.file, "{}", header)?;
+            self.size = header.len() as u64 + 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.size >= self.max_size {
+            self.rotate()?;
+        }
+
+        self.file.flush()
+    }
+}
+
+#[cfg(feature = "syslog")]
+mod syslog {
+    use std::error::Error;
+    use std::io::{self, Write};
+    #[cfg(unix)]
+    use std::os::unix::net::UnixDatagram;
+    use std::path::Path;
+    use std::process;
+
+    use clap::ValueEnum;
+
+    /// Sockets the local syslog daemon listens on: Linux, then macOS and the BSDs
+    const SOCKETS: [&str; 2] = ["/dev/log", "/var/run/syslog"];
+    /// Severity of every message; samples are routine information
+    const INFO: u8 = 6;
+
+    /// Syslog facility the samples are filed under
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+    pub enum Facility {
+        #[default]
+        User,
+        Daemon,
+        Local0,
+        Local1,
+        Local2,
+        Local3,
+        Local4,
+        Local5,
+        Local6,
+        Local7,
+    }
+
+    impl Facility {
+        fn code(&self) -> u8 {
+            match self {
+                Facility::User => 1,
+                Facility::Daemon => 3,
+                Facility::Local0 => 16,
+                Facility::Local1 => 17,
+                Facility::Local2 => 18,
+                Facility::Local3 => 19,
+                Facility::Local4 => 20,
+                Facility::Local5 => 21,
+                Facility::Local6 => 22,
+                Facility::Local7 => 23,
+            }
+        }
+    }
+
+    /// Sends every line written to it as one INFO message to the local syslog
+    ///
+    /// A message that can't be delivered (the daemon restarting, a full
+    /// queue) is reported on stderr and dropped, so a long run keeps going.
+    pub struct SyslogWriter {
+        #[cfg(unix)]
+        socket: UnixDatagram,
+        priority: u8,
+        pending: Vec<u8>,
+    }
+
+    impl SyslogWriter {
+        pub fn connect(facility: Facility) -> Result<SyslogWriter, Box<dyn Error>> {
+            let path = SOCKETS
+                .iter()
+                .map(Path::new)
+                .find(|path| path.exists())
+                .ok_or("no syslog socket found at /dev/log or /var/run/syslog")?;
+
+            SyslogWriter::connect_to(path, facility)
+        }
+
+        #[cfg(unix)]
+        pub fn connect_to(path: &Path, facility: Facility) -> Result<SyslogWriter, Box<dyn Error>> {
+            let socket = UnixDatagram::unbound()?;
+            socket
+                .connect(path)
+                .map_err(|err| format!("cannot connect to syslog at {}: {}", path.display(), err))?;
+
+            Ok(SyslogWriter { socket, priority: facility.code() * 8 + INFO, pending: Vec::new() })
+        }
+
+        #[cfg(not(unix))]
+        pub fn connect_to(_path: &Path, _facility: Facility) -> Result<SyslogWriter, Box<dyn Error>> {
+            Err("--syslog is only supported on Unix".into())
+        }
+
+        #[cfg_attr(not(unix), allow(unused_variables))]
+        fn send(&self, line: &[u8]) {
+            let message = format!(
+                "<{}>system-monitor[{}]: {}",
+                self.priority,
+                process::id(),
+                String::from_utf8_lossy(line)
+            );
+            #[cfg(unix)]
+            if let Err(err) = self.socket.send(message.as_bytes()) {
+                eprintln!("warning: cannot send to syslog: {}", err);
+            }
+        }
+    }
+
+    impl Write for SyslogWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+            while let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=end).collect();
+                self.send(&line[..end]);
+            }
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(all(test, unix))]
+    mod tests {
+        use std::fs;
+
+        use super::*;
+
+        #[test]
+        fn test_lines_become_messages() {
+            let path = std::env::temp_dir().join(format!("system-monitor-syslog-{}.sock", process::id()));
+            let _ = fs::remove_file(&path);
+            let daemon = UnixDatagram::bind(&path).unwrap();
+
+            let mut writer = SyslogWriter::connect_to(&path, Facility::Local3).unwrap();
+            write!(writer, "cpu=1.0%\ncpu=").unwrap();
+            writeln!(writer, "2.0%").unwrap();
+
+            let prefix = format!("<158>system-monitor[{}]: ", process::id());
+            let mut buffer = [0; 256];
+            for expected in ["cpu=1.0%", "cpu=2.0%"] {
+                let len = daemon.recv(&mut buffer).unwrap();
+                assert_eq!(String::from_utf8_lossy(&buffer[..len]), format!("{prefix}{expected}"));
+            }
+            fs::remove_file(&path).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    #[test]
+    fn test_full_files_are_rotated_and_pruned() {
+        let dir = std::env::temp_dir().join(format!("system-monitor-rotate-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("samples.csv");
+
+        let mut file = RotatingFile::open(&path, 20, 2, false).unwrap().with_header("cpu,mem".to_string());
+        writeln!(file, "cpu,mem").unwrap();
+        for sample in 1..=6 {
+            // Written in pieces, as write_sample does, to check it isn't split
+            write!(file, "{}.0,", sample).unwrap();
+            writeln!(file, "{}00", sample).unwrap();
+            file.flush().unwrap();
+        }
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(path.clone()), "cpu,mem\n");
+        assert_eq!(read(dir.join("samples.csv.1")), "cpu,mem\n5.0,500\n6.0,600\n");
+        assert_eq!(read(dir.join("samples.csv.2")), "cpu,mem\n3.0,300\n4.0,400\n");
+        assert!(!dir.join("samples.csv.3").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,328 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{FormatOptions, MetricsSnapshot, SCHEMA_VERSION, format_percent};
+
+/// Minimum, maximum and mean of a series, kept without the values themselves
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    total: f64,
+}
+
+impl Stats {
+    pub fn add(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.total += value;
+        self.count += 1;
+    }
+
+    /// The mean, `None` before the first value
+    pub fn avg(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total / self.count as f64)
+    }
+
+    /// Minimum, mean and maximum, each passed through `convert`
+    fn range<T>(&self, convert: impl Fn(f64) -> T) -> Range<T> {
+        Range { min: convert(self.min), avg: convert(self.avg().unwrap_or_default()), max: convert(self.max) }
+    }
+}
+
+/// What the summary shows of a [`Stats`], in text and in JSON
+#[derive(Serialize)]
+struct Range<T> {
+    min: T,
+    avg: T,
+    max: T,
+}
+
+/// Totals and extremes over all samples of a run
+///
+/// Built up one sample at a time with [`add`](Summary::add) so long runs
+/// don't have to keep every snapshot around. Groups a run didn't collect
+/// (see [`CollectOptions`](crate::CollectOptions)) are left out of the report.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub samples: usize,
+    pub cpu_percent: Stats,
+    /// Memory in use, in bytes
+    pub memory_used: Stats,
+    /// Share of memory in use; samples without a memory total are left out
+    pub memory_percent: Stats,
+    pub read_per_sec: Stats,
+    pub write_per_sec: Stats,
+    pub total_read_bytes: u64,
+    pub total_written_bytes: u64,
+    pub rx_per_sec: Stats,
+    pub tx_per_sec: Stats,
+    pub total_rx_bytes: u64,
+    pub total_tx_bytes: u64,
+    has_cpu: bool,
+    has_memory: bool,
+    has_disk: bool,
+    has_network: bool,
+}
+
+impl Summary {
+    pub fn from_samples(samples: &[MetricsSnapshot]) -> Self {
+        let mut summary = Summary::default();
+        for snapshot in samples {
+            summary.add(snapshot);
+        }
+
+        summary
+    }
+
+    pub fn add(&mut self, snapshot: &MetricsSnapshot) {
+        self.cpu_percent.add(snapshot.cpu_usage_percent as f64);
+        self.memory_used.add(snapshot.memory_used_bytes as f64);
+        if let Some(percent) = snapshot.memory_usage_percent() {
+            self.memory_percent.add(percent);
+        }
+        self.samples += 1;
+
+        self.has_cpu |= snapshot.collected.cpu;
+        self.has_memory |= snapshot.collected.mem;
+        self.has_disk |= snapshot.collected.disk;
+        self.read_per_sec.add(snapshot.disk_read_per_sec as f64);
+        self.write_per_sec.add(snapshot.disk_write_per_sec as f64);
+        self.total_read_bytes += snapshot.disk_read_bytes;
+        self.total_written_bytes += snapshot.disk_write_bytes;
+
+        self.has_network |= snapshot.net_rx_bytes.is_some();
+        if let Some(rate) = snapshot.net_rx_per_sec {
+            self.rx_per_sec.add(rate as f64);
+        }
+        if let Some(rate) = snapshot.net_tx_per_sec {
+            self.tx_per_sec.add(rate as f64);
+        }
+        self.total_rx_bytes += snapshot.net_rx_bytes.unwrap_or(0);
+        self.total_tx_bytes += snapshot.net_tx_bytes.unwrap_or(0);
+    }
+
+    /// Multi-line report, printed when a run ends
+    pub fn render(&self, options: &FormatOptions) -> String {
+        let bytes = |value: u64| options.units.format_bytes_with(value, options.precision);
+        let range = |stats: &Stats, format: &dyn Fn(f64) -> String| {
+            let Range { min, avg, max } = stats.range(format);
+            format!("min {min}  avg {avg}  max {max}\n")
+        };
+        let percent = |value: f64| format_percent(value as f32);
+        let memory = |value: f64| bytes(value.round() as u64);
+        let disk_rate = |value: f64| format!("{}/s", bytes(value.round() as u64));
+        let net_rate = |value: f64| options.format_net_speed(value.round() as u64);
+        if self.samples == 0 {
+            return "Summary: no samples\n".to_string();
+        }
+
+        let mut text = format!(
+            "Summary: {} sample{}\n",
+            self.samples,
+            if self.samples == 1 { "" } else { "s" }
+        );
+        if self.has_cpu {
+            text.push_str(&format!("  CPU:             {}", range(&self.cpu_percent, &percent)));
+        }
+        if self.has_memory {
+            text.push_str(&format!("  Memory:          {}", range(&self.memory_used, &memory)));
+            if self.memory_percent.count > 0 {
+                text.push_str(&format!("  Memory in use:   {}", range(&self.memory_percent, &percent)));
+            }
+        }
+        if self.has_disk {
+            text.push_str(&format!("  Disk read:       {}", range(&self.read_per_sec, &disk_rate)));
+            text.push_str(&format!("  Disk write:      {}", range(&self.write_per_sec, &disk_rate)));
+            text.push_str(&format!("  Total read:      {}\n", bytes(self.total_read_bytes)));
+            text.push_str(&format!("  Total written:   {}\n", bytes(self.total_written_bytes)));
+        }
+        if self.has_network {
+            text.push_str(&format!("  RX:              {}", range(&self.rx_per_sec, &net_rate)));
+            text.push_str(&format!("  TX:              {}", range(&self.tx_per_sec, &net_rate)));
+            text.push_str(&format!("  Total RX:        {}\n", bytes(self.total_rx_bytes)));
+            text.push_str(&format!("  Total TX:        {}\n", bytes(self.total_tx_bytes)));
+        }
+
+        text
+    }
+
+    /// The report as one JSON object with raw numbers, for `--json` runs
+    ///
+    /// Like the samples, it starts with `schema_version` and `version`.
+    /// Without samples only those and `"samples": 0` are present.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        #[derive(Serialize)]
+        struct Report {
+            schema_version: u32,
+            version: &'static str,
+            samples: usize,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            cpu_percent: Option<Range<f32>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            memory_used_bytes: Option<Range<u64>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            memory_percent: Option<Range<f64>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            disk_read_bytes: Option<u64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            disk_write_bytes: Option<u64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            disk_read_per_sec: Option<Range<u64>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            disk_write_per_sec: Option<Range<u64>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            net_rx_per_sec: Option<Range<u64>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            net_tx_per_sec: Option<Range<u64>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            net_rx_bytes: Option<u64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            net_tx_bytes: Option<u64>,
+        }
+
+        let sampled = self.samples > 0;
+        let disk = |value: u64| (sampled && self.has_disk).then_some(value);
+        let network = |value: u64| (sampled && self.has_network).then_some(value);
+        let rates = |stats: &Stats| stats.range(|value| value.round() as u64);
+        let report = Report {
+            schema_version: SCHEMA_VERSION,
+            version: env!("CARGO_PKG_VERSION"),
+            samples: self.samples,
+            cpu_percent: (sampled && self.has_cpu).then(|| self.cpu_percent.range(|value| value as f32)),
+            memory_used_bytes: (sampled && self.has_memory).then(|| rates(&self.memory_used)),
+            memory_percent: (self.memory_percent.count > 0 && self.has_memory).then(|| self.memory_percent.range(|value| value)),
+            disk_read_bytes: disk(self.total_read_bytes),
+            disk_write_bytes: disk(self.total_written_bytes),
+            disk_read_per_sec: (sampled && self.has_disk).then(|| rates(&self.read_per_sec)),
+            disk_write_per_sec: (sampled && self.has_disk).then(|| rates(&self.write_per_sec)),
+            net_rx_per_sec: (sampled && self.has_network).then(|| rates(&self.rx_per_sec)),
+            net_tx_per_sec: (sampled && self.has_network).then(|| rates(&self.tx_per_sec)),
+            net_rx_bytes: network(self.total_rx_bytes),
+            net_tx_bytes: network(self.total_tx_bytes),
+        };
+
+        serde_json::to_string(&report)
+    }
+}
+
+/// The report with default units and precision, see [`Summary::render`]
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(&FormatOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(cpu: f32, rx_bytes: u64, rx_per_sec: u64) -> MetricsSnapshot {
+        MetricsSnapshot {
+            cpu_usage_percent: cpu,
+            memory_used_bytes: rx_bytes * 2,
+            disk_read_bytes: rx_bytes / 2,
+            net_rx_bytes: Some(rx_bytes),
+            net_rx_per_sec: Some(rx_per_sec),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_summary_over_samples() {
+        let summary = Summary::from_samples(&[
+            sample(10.0, 1_000, 1_000),
+            sample(30.0, 5_000, 5_000),
+            sample(20.0, 2_000, 2_000),
+        ]);
+
+        assert_eq!(summary.samples, 3);
+        assert_eq!((summary.cpu_percent.min, summary.cpu_percent.max, summary.cpu_percent.avg()), (10.0, 30.0, Some(20.0)));
+        assert_eq!(summary.rx_per_sec.max, 5_000.0);
+        assert_eq!((summary.rx_per_sec.min, summary.rx_per_sec.avg()), (1_000.0, Some(8_000.0 / 3.0)));
+        assert_eq!(summary.total_rx_bytes, 8_000);
+        assert_eq!(summary.memory_used.min, 2_000.0);
+        assert_eq!(summary.memory_used.max, 10_000.0);
+        assert_eq!(summary.memory_used.avg().map(f64::round), Some(5_333.0));
+        assert_eq!(summary.total_read_bytes, 4_000);
+    }
+
+    #[test]
+    fn test_summary_skips_missing_rates() {
+        let first = MetricsSnapshot { net_rx_per_sec: None, ..sample(10.0, 1_000, 0) };
+        let summary = Summary::from_samples(&[first, sample(20.0, 3_000, 2_000), sample(30.0, 7_000, 4_000)]);
+
+        assert_eq!(summary.rx_per_sec.count, 2);
+        assert_eq!((summary.rx_per_sec.min, summary.rx_per_sec.avg()), (2_000.0, Some(3_000.0)));
+        assert_eq!(summary.total_rx_bytes, 11_000);
+    }
+
+    #[test]
+    fn test_render() {
+        let text = Summary::from_samples(&[sample(12.0, 1_500_000, 1_500_000)]).render(&FormatOptions::default());
+
+        assert!(text.starts_with("Summary: 1 sample\n"));
+        assert!(text.contains("  CPU:             min 12.0%  avg 12.0%  max 12.0%\n"));
+        assert!(text.contains("  RX:              min 1.50 MB/s  avg 1.50 MB/s  max 1.50 MB/s\n"));
+        assert!(text.contains("  Total RX:        1.50 MB\n"));
+        assert!(text.contains("  Memory:          min 3.00 MB  avg 3.00 MB  max 3.00 MB\n"));
+        assert!(text.contains("  Total read:      750 KB\n"));
+
+        assert_eq!(Summary::default().to_string(), "Summary: no samples\n");
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.avg(), None);
+        for value in [4.0, -1.0, 3.0] {
+            stats.add(value);
+        }
+        assert_eq!((stats.count, stats.min, stats.max, stats.avg()), (3, -1.0, 4.0, Some(2.0)));
+    }
+
+    #[test]
+    fn test_memory_share_and_rates_get_ranges() {
+        let samples: Vec<MetricsSnapshot> = [(1_000, 0), (3_000, 500)]
+            .into_iter()
+            .map(|(used, read)| MetricsSnapshot {
+                collected: crate::Fields::all(),
+                memory_used_bytes: used,
+                memory_total_bytes: 10_000,
+                disk_read_per_sec: read,
+                ..Default::default()
+            })
+            .collect();
+        let summary = Summary::from_samples(&samples);
+
+        let text = summary.render(&FormatOptions::default());
+        assert!(text.contains("  Memory in use:   min 10.0%  avg 20.0%  max 30.0%\n"), "{}", text);
+        assert!(text.contains("  Disk read:       min 0 B/s  avg 250 B/s  max 500 B/s\n"), "{}", text);
+
+        let json: serde_json::Value = serde_json::from_str(&summary.to_json().unwrap()).unwrap();
+        assert_eq!(json["memory_percent"]["avg"], 20.0);
+        assert_eq!(json["disk_read_per_sec"], serde_json::json!({"min": 0, "avg": 250, "max": 500}));
+    }
+
+    #[test]
+    fn test_json_report() {
+        let json = Summary::from_samples(&[sample(10.0, 100, 100), sample(20.0, 300, 300)]).to_json().unwrap();
+        let versions = format!(r#"{{"schema_version":{},"version":"{}","#, SCHEMA_VERSION, env!("CARGO_PKG_VERSION"));
+
+        assert!(json.starts_with(&format!(
+            r#"{}"samples":2,"cpu_percent":{{"min":10.0,"avg":15.0,"max":20.0}},"memory_used_bytes":{{"min":200,"avg":400,"max":600}},"disk_read_bytes":200,"#,
+            versions
+        )));
+        assert!(json.contains(r#""disk_write_bytes":0,"disk_read_per_sec":{"min":0,"avg":0,"max":0},"#));
+        assert!(json.ends_with(r#""net_tx_per_sec":{"min":0,"avg":0,"max":0},"net_rx_bytes":400,"net_tx_bytes":0}"#));
+        assert!(!json.contains("peak_"));
+        assert_eq!(Summary::default().to_json().unwrap(), format!(r#"{}"samples":0}}"#, versions));
+    }
+}
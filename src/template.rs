@@ -0,0 +1,259 @@
+use std::fmt;
+
+use crate::{FormatOptions, MetricsSnapshot};
+
+/// A user supplied output line such as `cpu={cpu} rx={net_rx}`
+///
+/// Placeholders are validated when the template is parsed, so a typo fails
+/// at startup instead of printing garbage every interval. `{{` and `}}`
+/// produce literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// Every value a template can refer to, formatted or raw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    Timestamp,
+    Uptime,
+    UptimeSecs,
+    Cpu,
+    CpuPercent,
+    MemUsed,
+    MemTotal,
+    MemPercent,
+    MemUsedBytes,
+    MemTotalBytes,
+    SwapUsed,
+    SwapTotal,
+    SwapPercent,
+    SwapUsedBytes,
+    SwapTotalBytes,
+    DiskRead,
+    DiskWrite,
+    DiskReadBytes,
+    DiskWriteBytes,
+    DiskReadPerSec,
+    DiskWritePerSec,
+    NetRx,
+    NetTx,
+    NetRxBytes,
+    NetTxBytes,
+    NetRxPerSec,
+    NetTxPerSec,
+}
+
+const PLACEHOLDERS: &[(&str, Placeholder)] = &[
+    ("timestamp", Placeholder::Timestamp),
+    ("uptime", Placeholder::Uptime),
+    ("uptime_secs", Placeholder::UptimeSecs),
+    ("cpu", Placeholder::Cpu),
+    ("cpu_percent", Placeholder::CpuPercent),
+    ("mem_used", Placeholder::MemUsed),
+    ("mem_total", Placeholder::MemTotal),
+    ("mem_percent", Placeholder::MemPercent),
+    ("mem_used_bytes", Placeholder::MemUsedBytes),
+    ("mem_total_bytes", Placeholder::MemTotalBytes),
+    ("swap_used", Placeholder::SwapUsed),
+    ("swap_total", Placeholder::SwapTotal),
+    ("swap_percent", Placeholder::SwapPercent),
+    ("swap_used_bytes", Placeholder::SwapUsedBytes),
+    ("swap_total_bytes", Placeholder::SwapTotalBytes),
+    ("disk_read", Placeholder::DiskRead),
+    ("disk_write", Placeholder::DiskWrite),
+    ("disk_read_bytes", Placeholder::DiskReadBytes),
+    ("disk_write_bytes", Placeholder::DiskWriteBytes),
+    ("disk_read_per_sec", Placeholder::DiskReadPerSec),
+    ("disk_write_per_sec", Placeholder::DiskWritePerSec),
+    ("net_rx", Placeholder::NetRx),
+    ("net_tx", Placeholder::NetTx),
+    ("net_rx_bytes", Placeholder::NetRxBytes),
+    ("net_tx_bytes", Placeholder::NetTxBytes),
+    ("net_rx_per_sec", Placeholder::NetRxPerSec),
+    ("net_tx_per_sec", Placeholder::NetTxPerSec),
+];
+
+impl Placeholder {
+    fn from_name(name: &str) -> Option<Self> {
+        PLACEHOLDERS
+            .iter()
+            .find(|(candidate, _)| *candidate == name)
+            .map(|&(_, placeholder)| placeholder)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    UnknownPlaceholder(String),
+    UnclosedPlaceholder,
+    UnmatchedClosingBrace,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownPlaceholder(name) => {
+                let known: Vec<&str> = PLACEHOLDERS.iter().map(|(name, _)| *name).collect();
+                write!(f, "unknown placeholder {{{}}}, expected one of: {}", name, known.join(", "))
+            }
+            TemplateError::UnclosedPlaceholder => write!(f, "unclosed '{{' in template"),
+            TemplateError::UnmatchedClosingBrace => {
+                write!(f, "unmatched '}}' in template, use '}}}}' for a literal brace")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl Template {
+    pub fn parse(input: &str) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = input.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(TemplateError::UnclosedPlaceholder),
+                        }
+                    }
+
+                    let placeholder = Placeholder::from_name(name.trim())
+                        .ok_or(TemplateError::UnknownPlaceholder(name))?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(placeholder));
+                }
+                '}' => return Err(TemplateError::UnmatchedClosingBrace),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Template { segments })
+    }
+
+    /// Substitute every placeholder from `snapshot`
+    pub fn render(&self, snapshot: &MetricsSnapshot, options: &FormatOptions) -> String {
+        // Templates are often written to files; keep escape codes out of them
+        let options = FormatOptions { color: false, ..options.clone() };
+        let formatted = snapshot.format_with(&options);
+        let mut line = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => line.push_str(text),
+                Segment::Placeholder(placeholder) => {
+                    let value = match placeholder {
+                        Placeholder::Timestamp => match &options.timestamp {
+                            Some(format) => format.format(&snapshot.timestamp),
+                            None => String::new(),
+                        },
+                        Placeholder::Uptime => formatted.uptime.clone(),
+                        Placeholder::UptimeSecs => snapshot.uptime_secs.to_string(),
+                        Placeholder::Cpu => formatted.cpu_usage.clone(),
+                        Placeholder::CpuPercent => snapshot.cpu_usage_percent.to_string(),
+                        Placeholder::MemUsed => formatted.memory_used.clone(),
+                        Placeholder::MemTotal => formatted.memory_total.clone(),
+                        Placeholder::MemPercent => formatted.memory_usage_percent.clone(),
+                        Placeholder::MemUsedBytes => snapshot.memory_used_bytes.to_string(),
+                        Placeholder::MemTotalBytes => snapshot.memory_total_bytes.to_string(),
+                        Placeholder::SwapUsed => formatted.swap_used.clone(),
+                        Placeholder::SwapTotal => formatted.swap_total.clone(),
+                        Placeholder::SwapPercent => formatted.swap_usage_percent.clone(),
+                        Placeholder::SwapUsedBytes => snapshot.swap_used_bytes.to_string(),
+                        Placeholder::SwapTotalBytes => snapshot.swap_total_bytes.to_string(),
+                        Placeholder::DiskRead => formatted.disk_read.clone(),
+                        Placeholder::DiskWrite => formatted.disk_write.clone(),
+                        Placeholder::DiskReadBytes => snapshot.disk_read_bytes.to_string(),
+                        Placeholder::DiskWriteBytes => snapshot.disk_write_bytes.to_string(),
+                        Placeholder::DiskReadPerSec => snapshot.disk_read_per_sec.to_string(),
+                        Placeholder::DiskWritePerSec => snapshot.disk_write_per_sec.to_string(),
+                        Placeholder::NetRx => formatted.net_rx.clone(),
+                        Placeholder::NetTx => formatted.net_tx.clone(),
+                        Placeholder::NetRxBytes => optional(snapshot.net_rx_bytes),
+                        Placeholder::NetTxBytes => optional(snapshot.net_tx_bytes),
+                        Placeholder::NetRxPerSec => optional(snapshot.net_rx_per_sec),
+                        Placeholder::NetTxPerSec => optional(snapshot.net_tx_per_sec),
+                    };
+                    line.push_str(&value);
+                }
+            }
+        }
+
+        line
+    }
+}
+
+/// A raw value that may not have been collected, empty when it wasn't
+fn optional(value: Option<u64>) -> String {
+    value.map_or(String::new(), |value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> MetricsSnapshot {
+        MetricsSnapshot {
+            cpu_usage_percent: 12.5,
+            memory_used_bytes: 4_100_000_000,
+            memory_total_bytes: 16_000_000_000,
+            net_rx_per_sec: Some(1_500_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_formatted_and_raw() {
+        let template = Template::parse("cpu={cpu} mem={mem_used}/{mem_total} raw={mem_used_bytes} rx={net_rx}").unwrap();
+
+        assert_eq!(
+            template.render(&snapshot(), &FormatOptions::default()),
+            "cpu=12.5% mem=4.10 GB/16.0 GB raw=4100000000 rx=1.50 MB/s"
+        );
+    }
+
+    #[test]
+    fn test_escaped_braces() {
+        let template = Template::parse("{{cpu}} is {cpu_percent} }}").unwrap();
+
+        assert_eq!(template.render(&snapshot(), &FormatOptions::default()), "{cpu} is 12.5 }");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Template::parse("cpu={cpuu}"),
+            Err(TemplateError::UnknownPlaceholder("cpuu".to_string()))
+        );
+        assert_eq!(Template::parse("cpu={cpu"), Err(TemplateError::UnclosedPlaceholder));
+        assert_eq!(Template::parse("cpu}"), Err(TemplateError::UnmatchedClosingBrace));
+        assert!(Template::parse("bad {nope}").unwrap_err().to_string().contains("{nope}"));
+    }
+}
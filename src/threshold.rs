@@ -0,0 +1,146 @@
+use crate::{MemBasis, MetricsSnapshot};
+
+/// Outcome of checking a sample against [`Limits`], ordered by severity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    #[default]
+    Ok,
+    Warning,
+    Critical,
+}
+
+impl Status {
+    /// Process exit code in the usual monitoring-plugin convention: 0, 1 or 2
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Status::Ok => 0,
+            Status::Warning => 1,
+            Status::Critical => 2,
+        }
+    }
+}
+
+/// Usage percentages at which a sample counts as warning or critical
+///
+/// A limit is crossed once usage reaches it; unset limits are never crossed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    pub cpu_warn: Option<f64>,
+    pub cpu_crit: Option<f64>,
+    pub mem_warn: Option<f64>,
+    pub mem_crit: Option<f64>,
+    /// What memory usage is counted on
+    pub mem_basis: MemBasis,
+}
+
+/// The worst status any metric in `snapshot` reaches under `limits`
+///
+/// Memory is checked on [`Limits::mem_basis`]; a snapshot without a memory
+/// total is never flagged for memory.
+pub fn evaluate(snapshot: &MetricsSnapshot, limits: &Limits) -> Status {
+    let cpu = level(Some(snapshot.cpu_usage_percent as f64), limits.cpu_warn, limits.cpu_crit);
+    let mem = level(snapshot.memory_percent(limits.mem_basis), limits.mem_warn, limits.mem_crit);
+
+    cpu.max(mem)
+}
+
+/// Names of the metrics in `snapshot` that reach any of their `limits`, e.g. `["cpu"]`
+pub fn alerts(snapshot: &MetricsSnapshot, limits: &Limits) -> Vec<String> {
+    let cpu = level(Some(snapshot.cpu_usage_percent as f64), limits.cpu_warn, limits.cpu_crit);
+    let mem = level(snapshot.memory_percent(limits.mem_basis), limits.mem_warn, limits.mem_crit);
+
+    [("cpu", cpu), ("mem", mem)]
+        .into_iter()
+        .filter(|&(_, status)| status != Status::Ok)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// How severe `value` is against its `warn` and `crit` limits
+///
+/// Reaching a limit counts: a value exactly at `crit` is critical. Unset
+/// limits are never reached. Alerts, exit codes and value colors all go by
+/// this.
+pub fn severity(value: f64, warn: Option<f64>, crit: Option<f64>) -> Status {
+    if crit.is_some_and(|crit| value >= crit) {
+        Status::Critical
+    } else if warn.is_some_and(|warn| value >= warn) {
+        Status::Warning
+    } else {
+        Status::Ok
+    }
+}
+
+fn level(percent: Option<f64>, warn: Option<f64>, crit: Option<f64>) -> Status {
+    percent.map_or(Status::Ok, |percent| severity(percent, warn, crit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(cpu: f32, memory_used: u64, memory_total: u64) -> MetricsSnapshot {
+        MetricsSnapshot {
+            cpu_usage_percent: cpu,
+            memory_used_bytes: memory_used,
+            memory_total_bytes: memory_total,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_severity_at_the_boundary_is_the_higher_one() {
+        assert_eq!(severity(69.9, Some(70.0), Some(90.0)), Status::Ok);
+        assert_eq!(severity(70.0, Some(70.0), Some(90.0)), Status::Warning);
+        assert_eq!(severity(89.9, Some(70.0), Some(90.0)), Status::Warning);
+        assert_eq!(severity(90.0, Some(70.0), Some(90.0)), Status::Critical);
+        // Without a warning level it goes straight from ok to critical
+        assert_eq!(severity(80.0, None, Some(90.0)), Status::Ok);
+        assert_eq!(severity(100.0, None, None), Status::Ok);
+    }
+
+    #[test]
+    fn test_worst_status_wins() {
+        let limits = Limits { cpu_warn: Some(80.0), cpu_crit: Some(95.0), mem_crit: Some(90.0), ..Default::default() };
+
+        assert_eq!(evaluate(&snapshot(10.0, 10, 100), &limits), Status::Ok);
+        assert_eq!(evaluate(&snapshot(80.0, 10, 100), &limits), Status::Warning);
+        assert_eq!(evaluate(&snapshot(96.0, 10, 100), &limits), Status::Critical);
+        assert_eq!(evaluate(&snapshot(85.0, 95, 100), &limits), Status::Critical);
+    }
+
+    #[test]
+    fn test_memory_without_total_is_ok() {
+        let limits = Limits { mem_warn: Some(0.0), ..Default::default() };
+
+        assert_eq!(evaluate(&snapshot(0.0, 0, 0), &limits), Status::Ok);
+        assert_eq!(evaluate(&snapshot(0.0, 0, 100), &limits), Status::Warning);
+    }
+
+    #[test]
+    fn test_memory_can_count_available() {
+        let cached = MetricsSnapshot { memory_available_bytes: 70, ..snapshot(0.0, 90, 100) };
+        let used = Limits { mem_warn: Some(80.0), ..Default::default() };
+        let available = Limits { mem_basis: MemBasis::Available, ..used };
+
+        assert_eq!(evaluate(&cached, &used), Status::Warning);
+        assert_eq!(evaluate(&cached, &available), Status::Ok);
+    }
+
+    #[test]
+    fn test_alerts_name_each_metric() {
+        let limits = Limits { cpu_warn: Some(50.0), mem_warn: Some(50.0), ..Default::default() };
+
+        assert!(alerts(&snapshot(10.0, 10, 100), &limits).is_empty());
+        assert_eq!(alerts(&snapshot(50.0, 10, 100), &limits), ["cpu"]);
+        assert_eq!(alerts(&snapshot(90.0, 60, 100), &limits), ["cpu", "mem"]);
+        assert!(alerts(&snapshot(90.0, 60, 100), &Limits::default()).is_empty());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(Status::Ok.exit_code(), 0);
+        assert_eq!(Status::Warning.exit_code(), 1);
+        assert_eq!(Status::Critical.exit_code(), 2);
+    }
+}
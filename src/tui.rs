@@ -0,0 +1,372 @@
+//! Full-screen dashboard for `live --tui`, built on ratatui
+//!
+//! Only drawing and key handling live here. Samples come from the same
+//! [`Monitor`](system_monitor::Monitor) loop as plain live mode, and every
+//! panel shows the usual [`MetricsSnapshot`] figures.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+
+use system_monitor::{FormatOptions, FormattedMetrics, MetricsSnapshot};
+
+/// Samples the history graph keeps, more than a terminal is usually wide
+const HISTORY: usize = 512;
+
+/// The four panels, laid out as a 2x2 grid in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Cpu,
+    Memory,
+    Disks,
+    Network,
+}
+
+impl Panel {
+    const ALL: [Panel; 4] = [Panel::Cpu, Panel::Memory, Panel::Disks, Panel::Network];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Panel::Cpu => "CPU",
+            Panel::Memory => "Memory",
+            Panel::Disks => "Disks",
+            Panel::Network => "Network",
+        }
+    }
+
+    /// The neighbour an arrow key moves to; keys pointing off the grid stay put
+    fn moved(self, key: KeyCode) -> Panel {
+        let index = Panel::ALL.iter().position(|&panel| panel == self).unwrap_or(0);
+        let (row, column) = (index / 2, index % 2);
+        let (row, column) = match key {
+            KeyCode::Left => (row, 0),
+            KeyCode::Right => (row, 1),
+            KeyCode::Up => (0, column),
+            KeyCode::Down => (1, column),
+            KeyCode::Tab => return Panel::ALL[(index + 1) % 4],
+            _ => (row, column),
+        };
+
+        Panel::ALL[row * 2 + column]
+    }
+}
+
+/// Recent samples and the focused panel
+pub struct App {
+    options: FormatOptions,
+    history: VecDeque<MetricsSnapshot>,
+    focus: Panel,
+    /// Set by `p` here and by SIGUSR1 and SIGUSR2 from outside
+    paused: Arc<AtomicBool>,
+}
+
+impl App {
+    pub fn new(options: &FormatOptions) -> Self {
+        App {
+            options: FormatOptions { per_core: true, color: false, ..options.clone() },
+            history: VecDeque::with_capacity(HISTORY + 1),
+            focus: Panel::Cpu,
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn push(&mut self, snapshot: MetricsSnapshot) {
+        self.history.push_back(snapshot);
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+    }
+
+    /// Whether sampling is paused, shared with the pause signals
+    pub fn paused(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.paused)
+    }
+
+    /// Apply a key press; `false` once it asks to quit
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => false,
+            // Raw mode turns Ctrl-C into a key press instead of SIGINT
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => false,
+            KeyCode::Char('p') => {
+                self.paused.fetch_xor(true, Ordering::SeqCst);
+                true
+            }
+            code => {
+                self.focus = self.focus.moved(code);
+                true
+            }
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let [panels, history, help] =
+            Layout::vertical([Constraint::Percentage(70), Constraint::Min(5), Constraint::Length(1)]).areas(frame.area());
+        let help_line = if self.paused.load(Ordering::SeqCst) {
+            Line::from("PAUSED  p resume  q quit").style(Style::new().fg(Color::Yellow))
+        } else {
+            Line::from("q quit  p pause  ←↑↓→/tab switch panel").style(Style::new().fg(Color::DarkGray))
+        };
+        frame.render_widget(help_line, help);
+
+        let Some(latest) = self.history.back() else {
+            frame.render_widget(Paragraph::new("Waiting for the first sample...").block(Block::bordered()), panels);
+            return;
+        };
+        let metrics = latest.format_with(&self.options);
+
+        let [top, bottom] = Layout::vertical([Constraint::Percentage(50); 2]).areas(panels);
+        let [cpu, memory] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(top);
+        let [disks, network] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(bottom);
+        self.draw_cpu(frame, cpu, latest, &metrics);
+        self.draw_memory(frame, memory, latest, &metrics);
+        self.draw_disks(frame, disks, latest, &metrics);
+        self.draw_network(frame, network, latest, &metrics);
+        self.draw_history(frame, history);
+    }
+
+    fn block(&self, panel: Panel) -> Block<'static> {
+        let block = Block::bordered().title(panel.title());
+        if panel == self.focus { block.border_style(Style::new().fg(Color::Cyan)) } else { block }
+    }
+
+    fn draw_cpu(&self, frame: &mut Frame, area: Rect, snapshot: &MetricsSnapshot, metrics: &FormattedMetrics) {
+        if !snapshot.collected.cpu {
+            frame.render_widget(Paragraph::new("not collected").block(self.block(Panel::Cpu)), area);
+            return;
+        }
+
+        let mut lines = vec![Line::from(match &metrics.load_average {
+            Some(load) => format!("total {}  load {}", metrics.cpu_usage, load),
+            None => format!("total {}", metrics.cpu_usage),
+        })];
+        // Label, bar and percentage share the inner width
+        let bar_width = (area.width as usize).saturating_sub(2 + 7 + 8);
+        for (core, (&usage, label)) in snapshot.per_core_usage.iter().zip(&metrics.per_core_usage).enumerate() {
+            let filled = ((usage as f64 / 100.0) * bar_width as f64).round() as usize;
+            let bar = format!("{}{}", "█".repeat(filled.min(bar_width)), "░".repeat(bar_width.saturating_sub(filled)));
+            lines.push(Line::from(format!("{:<6} {} {:>7}", format!("cpu{}", core), bar, label)));
+        }
+        frame.render_widget(Paragraph::new(lines).block(self.block(Panel::Cpu)), area);
+    }
+
+    fn draw_memory(&self, frame: &mut Frame, area: Rect, snapshot: &MetricsSnapshot, metrics: &FormattedMetrics) {
+        let block = self.block(Panel::Memory);
+        if !snapshot.collected.mem {
+            frame.render_widget(Paragraph::new("not collected").block(block), area);
+            return;
+        }
+
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let [ram, swap] = Layout::vertical([Constraint::Length(2); 2]).areas(inner);
+        let ratio = |used: u64, total: u64| if total == 0 { 0.0 } else { (used as f64 / total as f64).min(1.0) };
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::new().title("RAM"))
+                .gauge_style(Style::new().fg(Color::Green))
+                .ratio(ratio(snapshot.memory_used_bytes, snapshot.memory_total_bytes))
+                .label(format!("{} / {} ({})", metrics.memory_used, metrics.memory_total, metrics.memory_usage_percent)),
+            ram,
+        );
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::new().title("Swap"))
+                .gauge_style(Style::new().fg(Color::Yellow))
+                .ratio(ratio(snapshot.swap_used_bytes, snapshot.swap_total_bytes))
+                .label(format!("{} / {} ({})", metrics.swap_used, metrics.swap_total, metrics.swap_usage_percent)),
+            swap,
+        );
+    }
+
+    fn draw_disks(&self, frame: &mut Frame, area: Rect, snapshot: &MetricsSnapshot, metrics: &FormattedMetrics) {
+        if !snapshot.collected.disk {
+            frame.render_widget(Paragraph::new("not collected").block(self.block(Panel::Disks)), area);
+            return;
+        }
+
+        let mut rows = vec![Row::new(vec!["all".to_string(), metrics.disk_read.clone(), metrics.disk_write.clone(), String::new()])];
+        rows.extend(metrics.per_disk.iter().map(|disk| {
+            Row::new(vec![
+                disk.mount_point.clone(),
+                disk.read.clone(),
+                disk.write.clone(),
+                format!("{} / {}", disk.available, disk.total),
+            ])
+        }));
+        let table = Table::new(rows, [Constraint::Fill(1), Constraint::Length(11), Constraint::Length(11), Constraint::Length(21)])
+            .header(Row::new(["MOUNT", "READ", "WRITE", "FREE"]).style(Style::new().fg(Color::DarkGray)))
+            .block(self.block(Panel::Disks));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_network(&self, frame: &mut Frame, area: Rect, snapshot: &MetricsSnapshot, metrics: &FormattedMetrics) {
+        if snapshot.net_rx_bytes.is_none() {
+            frame.render_widget(Paragraph::new("not collected").block(self.block(Panel::Network)), area);
+            return;
+        }
+
+        let mut rows = vec![Row::new(vec!["all".to_string(), metrics.net_rx.clone(), metrics.net_tx.clone()])];
+        rows.extend(
+            metrics
+                .per_interface
+                .iter()
+                .map(|interface| Row::new(vec![interface.name.clone(), interface.rx.clone(), interface.tx.clone()])),
+        );
+        let table = Table::new(rows, [Constraint::Fill(1), Constraint::Length(12), Constraint::Length(12)])
+            .header(Row::new(["INTERFACE", "RX", "TX"]).style(Style::new().fg(Color::DarkGray)))
+            .block(self.block(Panel::Network));
+        frame.render_widget(table, area);
+    }
+
+    /// The focused panel's headline figure over time, newest on the right
+    fn draw_history(&self, frame: &mut Frame, area: Rect) {
+        let (title, max, value): (_, _, fn(&MetricsSnapshot) -> u64) = match self.focus {
+            // Tenths of a percent keep the graph from looking stepped
+            Panel::Cpu => ("CPU usage", Some(1000), |snapshot| (snapshot.cpu_usage_percent as f64 * 10.0) as u64),
+            Panel::Memory => ("Memory usage", Some(1000), |snapshot| {
+                (snapshot.memory_usage_percent().unwrap_or(0.0) * 10.0) as u64
+            }),
+            Panel::Disks => ("Disk read + write", None, |snapshot| snapshot.disk_read_per_sec + snapshot.disk_write_per_sec),
+            Panel::Network => ("Network rx + tx", None, |snapshot| {
+                snapshot.net_rx_per_sec.unwrap_or(0) + snapshot.net_tx_per_sec.unwrap_or(0)
+            }),
+        };
+
+        let shown = (area.width as usize).saturating_sub(2);
+        let data: Vec<u64> = self.history.iter().skip(self.history.len().saturating_sub(shown)).map(value).collect();
+        let mut sparkline = Sparkline::default()
+            .block(Block::bordered().title(format!("History: {}", title)))
+            .style(Style::new().fg(Color::Cyan))
+            .data(&data);
+        if let Some(max) = max {
+            sparkline = sparkline.max(max);
+        }
+        frame.render_widget(sparkline, area);
+    }
+}
+
+/// The terminal in raw mode on the alternate screen, restored on drop
+///
+/// ratatui's init also installs a panic hook that restores the terminal
+/// before the panic message is printed.
+pub struct Screen {
+    terminal: DefaultTerminal,
+}
+
+impl Screen {
+    pub fn enter() -> io::Result<Self> {
+        Ok(Screen { terminal: ratatui::try_init()? })
+    }
+
+    pub fn draw(&mut self, app: &App) -> io::Result<()> {
+        self.terminal.draw(|frame| app.draw(frame))?;
+        Ok(())
+    }
+
+    /// Handle key presses and resizes for `timeout`; `false` when the user quit
+    pub fn wait(&mut self, timeout: Duration, app: &mut App) -> io::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() || !event::poll(left)? {
+                return Ok(true);
+            }
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if !app.handle_key(key) {
+                        return Ok(false);
+                    }
+                    self.draw(app)?;
+                }
+                Event::Resize(..) => self.draw(app)?,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    fn screen_text(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn test_panels_show_the_latest_sample() {
+        let mut app = App::new(&FormatOptions::default());
+        assert!(screen_text(&app).contains("Waiting for the first sample"));
+
+        app.push(MetricsSnapshot {
+            cpu_usage_percent: 12.5,
+            per_core_usage: vec![10.0, 15.0],
+            memory_used_bytes: 1_000_000,
+            memory_total_bytes: 4_000_000,
+            net_rx_bytes: Some(0),
+            net_rx_per_sec: Some(2_000),
+            ..Default::default()
+        });
+        let text = screen_text(&app);
+
+        assert!(text.contains("total 12.5%"));
+        assert!(text.contains("cpu1"));
+        assert!(text.contains("1.00 MB / 4.00 MB (25.0%)"));
+        assert!(text.contains("2.00 KB/s"));
+        assert!(text.contains("History: CPU usage"));
+    }
+
+    #[test]
+    fn test_keys_move_focus_and_quit() {
+        let mut app = App::new(&FormatOptions::default());
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        assert!(app.handle_key(press(KeyCode::Right)));
+        assert_eq!(app.focus, Panel::Memory);
+        assert!(app.handle_key(press(KeyCode::Down)));
+        assert_eq!(app.focus, Panel::Network);
+        assert!(app.handle_key(press(KeyCode::Left)));
+        assert_eq!(app.focus, Panel::Disks);
+        assert!(app.handle_key(press(KeyCode::Tab)));
+        assert_eq!(app.focus, Panel::Network);
+        app.push(MetricsSnapshot::default());
+        assert!(screen_text(&app).contains("History: Network rx + tx"));
+
+        assert!(!app.handle_key(press(KeyCode::Char('q'))));
+        assert!(!app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+    }
+
+    #[test]
+    fn test_p_toggles_the_pause() {
+        let mut app = App::new(&FormatOptions::default());
+        let pause = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE);
+
+        assert!(app.handle_key(pause));
+        assert!(app.paused().load(Ordering::SeqCst));
+        assert!(screen_text(&app).contains("PAUSED"));
+        assert!(app.handle_key(pause));
+        assert!(!app.paused().load(Ordering::SeqCst));
+        assert!(!screen_text(&app).contains("PAUSED"));
+    }
+}
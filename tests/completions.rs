@@ -0,0 +1,85 @@
+use std::process::Command;
+
+#[test]
+fn test_bash_completions_list_current_flags() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["completions", "bash"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout).expect("completions are valid UTF-8");
+
+    for flag in [
+        "--interval",
+        "--count",
+        "--duration",
+        "--format",
+        "--json",
+        "--ndjson",
+        "--csv",
+        "--raw",
+        "--influx",
+        "--columns",
+        "--header-every",
+        "--quiet",
+        "--metric",
+        "--output",
+        "--truncate",
+        "--max-file-size",
+        "--max-files",
+        "--table",
+        "--smooth",
+        "--window",
+        "--diff",
+        "--fields",
+        "--units",
+        "--net-unit",
+        "--mem-basis",
+        "--precision",
+        "--color",
+        "--warn",
+        "--crit",
+        "--top",
+        "--per-core",
+        "--per-disk",
+        "--hide-idle",
+        "--per-interface",
+        "--no-network",
+        "--no-cpu",
+        "--no-disk",
+        "--gpu",
+        "--pid",
+        "--pid-name",
+        "--exit-with-process",
+        "--watch-process",
+        "--all-matches",
+        "--interfaces",
+        "--ignore-interfaces",
+        "--disks",
+        "--timestamp-format",
+        "--no-timestamp",
+        "--align",
+        "--cpu-warn",
+        "--mem-crit",
+        "--cpu-threshold",
+        "--exit-on-alert",
+        "--statsd-host",
+        "--record",
+        "--config",
+    ] {
+        assert!(script.contains(flag), "bash completions are missing {flag}");
+    }
+    if cfg!(feature = "serve") {
+        assert!(script.contains("--prometheus-listen"), "bash completions are missing --prometheus-listen");
+    }
+    if cfg!(feature = "serde") {
+        assert!(script.contains("replay") && script.contains("--speed"), "bash completions are missing replay");
+    }
+    if cfg!(feature = "tui") {
+        assert!(script.contains("--tui"), "bash completions are missing --tui");
+    }
+    for command in ["snapshot", "live", "log", "export", "list"] {
+        assert!(script.contains(command), "bash completions are missing {command}");
+    }
+}
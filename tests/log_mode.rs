@@ -0,0 +1,579 @@
+use std::fs;
+use std::net::UdpSocket;
+use std::process::Command;
+use std::time::Duration;
+
+use chrono::DateTime;
+
+#[test]
+fn test_log_mode_prints_a_line_per_sample() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--count", "2", "--interval", "1"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    for line in &lines {
+        let (timestamp, fields) = line.split_once(' ').expect("timestamp prefix");
+        assert!(DateTime::parse_from_rfc3339(timestamp).is_ok(), "bad timestamp: {timestamp}");
+        assert!(fields.starts_with("cpu="));
+        assert!(fields.contains(" mem="));
+        assert!(fields.contains(" rx="));
+        assert!(fields.contains(" tx="));
+    }
+}
+
+#[test]
+fn test_count_zero_is_rejected() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--count", "0"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--count"), "unexpected error: {stderr}");
+}
+
+#[test]
+fn test_smoothing_factor_outside_range_is_rejected() {
+    for alpha in ["0", "1.5", "-0.2"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+            .args(["live", "--count", "1", "--smooth", alpha])
+            .output()
+            .expect("failed to run system-monitor");
+
+        assert!(!output.status.success(), "--smooth {alpha} was accepted");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("--smooth"), "unexpected error: {stderr}");
+    }
+}
+
+#[test]
+fn test_max_file_size_needs_an_output_file() {
+    for args in [&["log", "--count", "1", "--max-file-size", "10M"][..], &["log", "--max-file-size", "10Q"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+            .args(args)
+            .output()
+            .expect("failed to run system-monitor");
+
+        assert!(!output.status.success(), "{args:?} was accepted");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("--max-file-size"), "unexpected error: {stderr}");
+    }
+}
+
+#[test]
+fn test_output_in_a_missing_directory_names_the_directory() {
+    let dir = std::env::temp_dir().join(format!("system-monitor-missing-{}", std::process::id()));
+    let path = dir.join("samples.log");
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--count", "1", "--output"])
+        .arg(&path)
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("directory {} does not exist", dir.display())), "unexpected error: {stderr}");
+}
+
+#[test]
+fn test_quiet_prints_only_the_metric() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--count", "2", "--interval", "0.2", "--quiet", "--metric", "cpu"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    for line in stdout.lines() {
+        let percent = line.strip_suffix('%').unwrap_or_else(|| panic!("not a bare percentage: {line}"));
+        assert!(percent.parse::<f32>().is_ok(), "not a bare percentage: {line}");
+    }
+    assert_eq!(stdout.lines().count(), 2);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--quiet", "--metric", "cpu-pct"])
+        .output()
+        .expect("failed to run system-monitor");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("mem-pct"), "valid metrics aren't listed: {stderr}");
+}
+
+#[test]
+fn test_piped_output_has_no_color() {
+    for args in [&["--interval", "1"][..], &["log", "--count", "1"][..]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+            .args(args)
+            .output()
+            .expect("failed to run system-monitor");
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+        assert!(!stdout.contains("\x1b["), "escape codes in {args:?}: {stdout:?}");
+    }
+}
+
+#[test]
+fn test_breakpoints_color_only_colored_output() {
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_system-monitor")).args(args).output().expect("failed to run system-monitor");
+        assert!(output.status.success(), "{args:?} failed");
+        String::from_utf8(output.stdout).expect("stdout is valid UTF-8")
+    };
+    let line = ["log", "--count", "1", "--interval", "0.2", "--no-timestamp", "--fields", "cpu,mem"];
+
+    // Everything reaches 0%, so every percentage is red
+    let colored = run(&[&line[..], &["--color", "always", "--warn", "0", "--crit", "mem=0,cpu=0"]].concat());
+    assert!(colored.starts_with("cpu=\x1b[31m"), "{colored:?}");
+    assert!(colored.contains("mem=\x1b[31m"), "{colored:?}");
+
+    let piped = run(&[&line[..], &["--warn", "0", "--crit", "0"]].concat());
+    assert!(!piped.contains("\x1b["), "escape codes when piped: {piped:?}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--count", "1", "--warn", "disk=70"])
+        .output()
+        .expect("failed to run system-monitor");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected cpu or mem"));
+}
+
+#[test]
+fn test_csv_header_printed_once() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--format", "csv", "--count", "2", "--interval", "1"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("# schema_version="));
+    assert!(lines[1].starts_with("timestamp,"));
+    assert_eq!(lines.iter().filter(|line| line.starts_with("timestamp,")).count(), 1);
+    for row in &lines[2..] {
+        assert_eq!(row.split(',').count(), lines[1].split(',').count());
+    }
+}
+
+#[test]
+fn test_csv_output_file_appends_or_truncates() {
+    let dir = std::env::temp_dir().join(format!("system-monitor-append-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let path = dir.join("samples.csv");
+    let log = |extra: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+            .args(["log", "--csv", "--count", "1", "--interval", "0.1", "--output"])
+            .arg(&path)
+            .args(extra)
+            .output()
+            .expect("failed to run system-monitor");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        fs::read_to_string(&path).unwrap()
+    };
+    let headers = |text: &str| text.lines().filter(|line| line.starts_with("timestamp,")).count();
+
+    fs::write(&path, "").unwrap();
+    let text = log(&[]);
+    assert_eq!((text.lines().count(), headers(&text)), (3, 1));
+    assert!(text.starts_with("# schema_version="));
+
+    let text = log(&[]);
+    assert_eq!((text.lines().count(), headers(&text)), (4, 1));
+
+    let text = log(&["--truncate"]);
+    assert_eq!((text.lines().count(), headers(&text)), (3, 1));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_json_log_is_one_object_per_line() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--json", "--count", "2", "--interval", "0.2"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    for line in lines {
+        assert!(line.starts_with("{\"timestamp\":"), "timestamp isn't first: {line}");
+        let sample: serde_json::Value = serde_json::from_str(line).expect("each line is a JSON object");
+        assert!(sample.is_object());
+    }
+
+    // The bounded run's summary goes to stderr in the same format
+    let summary: serde_json::Value = serde_json::from_slice(&output.stderr).expect("summary is JSON");
+    assert_eq!(summary["samples"], 2);
+    assert!(summary["cpu_percent"]["max"].is_number());
+}
+
+#[test]
+fn test_columns_repeat_their_header() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--columns", "--header-every", "2", "--no-timestamp", "--count", "3", "--interval", "0.2"])
+        .output()
+        .expect("failed to run system-monitor");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 5, "{stdout}");
+    for (number, line) in lines.iter().enumerate() {
+        assert_eq!(line.len(), lines[0].len(), "misaligned: {stdout}");
+        assert_eq!(line.trim_start().starts_with("cpu"), number % 3 == 0, "unexpected header placement: {stdout}");
+    }
+}
+
+#[test]
+fn test_ndjson_lines_parse_on_their_own() {
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_system-monitor")).args(args).output().expect("failed to run system-monitor");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).expect("stdout is valid UTF-8")
+    };
+
+    let stdout = run(&["log", "--ndjson", "--fields", "cpu,net", "--per-interface", "--count", "2", "--interval", "0.2"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    for line in lines {
+        let sample: serde_json::Value = serde_json::from_str(line).expect("each line is a JSON object");
+        assert!(sample["cpu_usage_percent"].is_number());
+        // Groups left out by --fields are dropped, not written as null
+        assert!(sample.get("memory_used_bytes").is_none(), "memory wasn't left out: {line}");
+        assert!(sample.get("disk_read_bytes").is_none(), "disk wasn't left out: {line}");
+    }
+
+    // A snapshot is a single line too, where --json would pretty-print it
+    let stdout = run(&["--ndjson"]);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(serde_json::from_str::<serde_json::Value>(&stdout).unwrap().is_object());
+}
+
+#[test]
+fn test_thresholds_set_exit_code() {
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+            .args(args)
+            .output()
+            .expect("failed to run system-monitor")
+    };
+
+    // Some memory is always in use, so a 0% limit is always reached
+    assert_eq!(run(&["--mem-warn", "0"]).status.code(), Some(1));
+    assert_eq!(run(&["--mem-warn", "0", "--mem-crit", "0"]).status.code(), Some(2));
+    assert_eq!(run(&["--mem-crit", "100", "--raw"]).status.code(), Some(0));
+    assert!(!run(&["--cpu-warn", "101"]).status.success());
+}
+
+#[test]
+fn test_exit_on_alert_stops_after_marked_sample() {
+    // Without --count this would run forever unless the alert stops it
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--interval", "200ms", "--mem-threshold", "0", "--exit-on-alert"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.trim_end().ends_with(" [ALERT mem]"), "unexpected line: {stdout}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pid_is_followed_until_it_exits() {
+    let mut sleeper = Command::new("sleep").arg("1").spawn().expect("failed to run sleep");
+    let pid = sleeper.id().to_string();
+
+    // Without --count this would run forever unless the process exit stops it
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--csv", "--interval", "400ms", "--pid", &pid, "--exit-with-process"])
+        .output()
+        .expect("failed to run system-monitor");
+    sleeper.wait().unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[1].ends_with(",process_pid,process_cpu_percent,process_rss,process_virtual,process_disk_read,process_disk_write"));
+    assert!(lines[2].contains(&format!(",{},", pid)), "unexpected line: {}", lines[2]);
+    // The last sample was taken after the process was gone
+    assert!(lines.last().unwrap().ends_with(",,,,,,"), "unexpected output: {stdout}");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("note: process sleep ({}) exited, stopping", pid)), "unexpected stderr: {stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch_process_shows_only_the_process() {
+    let mut sleeper = Command::new("sleep").arg("1").spawn().expect("failed to run sleep");
+    let pid = sleeper.id().to_string();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--interval", "400ms", "--no-timestamp", "--watch-process", &pid])
+        .output()
+        .expect("failed to run system-monitor");
+    sleeper.wait().unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].starts_with(&format!("pid={} pcpu=", pid)), "unexpected line: {}", lines[0]);
+    assert_eq!(lines.last(), Some(&"pid=none"));
+
+    // A process that isn't there can't be waited for
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--watch-process", "no-such-process-name"])
+        .output()
+        .expect("failed to run system-monitor");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no process named 'no-such-process-name'"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_recorded_log_replays_sample_for_sample() {
+    let path = std::env::temp_dir().join(format!("system-monitor-record-{}.jsonl", std::process::id()));
+    let recorded = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--count", "3", "--interval", "300ms", "--fields", "cpu,mem", "--record"])
+        .arg(&path)
+        .output()
+        .expect("failed to run system-monitor");
+    assert!(recorded.status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+    let replayed = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["replay", "--speed", "100x", "--fields", "cpu,mem"])
+        .arg(&path)
+        .output()
+        .expect("failed to run system-monitor");
+    fs::remove_file(&path).unwrap();
+
+    assert!(replayed.status.success());
+    assert_eq!(String::from_utf8_lossy(&replayed.stdout), String::from_utf8_lossy(&recorded.stdout));
+    assert!(String::from_utf8_lossy(&replayed.stderr).starts_with("Summary: 3 samples"));
+}
+
+#[test]
+fn test_smoothed_live_records_raw_alerts() {
+    let path = std::env::temp_dir().join(format!("system-monitor-live-record-{}.jsonl", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["live", "--count", "2", "--interval", "200ms", "--smooth", "0.5", "--mem-threshold", "0", "--record"])
+        .arg(&path)
+        .output()
+        .expect("failed to run system-monitor");
+    let recorded = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(recorded.lines().count(), 2);
+    for line in recorded.lines() {
+        let record: serde_json::Value = serde_json::from_str(line).expect("each line is a JSON object");
+        assert_eq!(record["snapshot"]["alerts"], serde_json::json!(["mem"]), "{line}");
+    }
+}
+
+#[test]
+fn test_unknown_interfaces_warn_and_count_nothing() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["--interfaces", "no-such-if0", "--per-interface", "--json", "--interval", "200ms"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("no-such-if0").count(), 1, "unexpected stderr: {stderr}");
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid JSON");
+    assert_eq!(json["net_rx_bytes"], 0);
+    assert!(json.get("per_interface").is_none());
+}
+
+#[test]
+fn test_hide_idle_lists_only_active_interfaces() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["--per-interface", "--hide-idle", "--json", "--interval", "200ms"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid JSON");
+    for interface in json["per_interface"].as_array().into_iter().flatten() {
+        let traffic = interface["received_bytes"].as_u64().unwrap() + interface["transmitted_bytes"].as_u64().unwrap();
+        assert!(traffic > 0, "idle interface listed: {interface}");
+    }
+}
+
+#[test]
+fn test_no_network_leaves_network_out() {
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["--no-network", "--interval", "200ms"])
+        .output()
+        .expect("failed to run system-monitor");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("CPU Usage"), "unexpected output: {stdout}");
+    assert!(!stdout.contains("Network"), "unexpected output: {stdout}");
+}
+
+#[test]
+fn test_log_pushes_samples_to_statsd() {
+    let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+    collector.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let host = collector.local_addr().unwrap().to_string();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--count", "1", "--interval", "200ms", "--statsd-host", &host, "--statsd-tags", "env:test"])
+        .output()
+        .expect("failed to run system-monitor");
+    assert!(output.status.success());
+
+    let mut buffer = [0; 4096];
+    let len = collector.recv(&mut buffer).expect("a StatsD datagram");
+    let payload = String::from_utf8_lossy(&buffer[..len]);
+    assert!(payload.starts_with("system.cpu.usage:"), "unexpected payload: {payload}");
+    assert!(payload.lines().all(|line| line.ends_with("|g|#env:test")), "unexpected payload: {payload}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sigterm_prints_summary_and_exits_cleanly() {
+    use std::process::Stdio;
+    use std::thread;
+    use std::time::Duration;
+
+    let child = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--interval", "1"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run system-monitor");
+
+    thread::sleep(Duration::from_millis(2500));
+    let status = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .expect("failed to run kill");
+    assert!(status.success());
+
+    let output = child.wait_with_output().expect("system-monitor did not exit");
+    assert!(output.status.success());
+
+    // The last reading as a block, then the summary
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let (block, summary) = stderr.split_once("Summary: ").expect("a summary");
+    assert!(block.starts_with("System Metrics:"), "unexpected stderr: {stderr}");
+    assert!(summary.contains("  CPU:"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ctrl_c_stops_without_waiting_out_the_interval() {
+    use std::process::Stdio;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let child = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--interval", "30"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run system-monitor");
+
+    thread::sleep(Duration::from_millis(1000));
+    let interrupted = Instant::now();
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("failed to run kill");
+    assert!(status.success());
+
+    let output = child.wait_with_output().expect("system-monitor did not exit");
+    assert!(interrupted.elapsed() < Duration::from_secs(10), "took {:?} to stop", interrupted.elapsed());
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty(), "a partial sample was written");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Summary: "));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sigusr1_pauses_until_sigusr2() {
+    use std::process::Stdio;
+    use std::thread;
+    use std::time::Instant;
+
+    let started = Instant::now();
+    let child = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["log", "--ndjson", "--count", "2", "--interval", "1"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run system-monitor");
+    let signal = |name: &str| {
+        let status = Command::new("kill").args([name, &child.id().to_string()]).status().expect("failed to run kill");
+        assert!(status.success());
+    };
+
+    thread::sleep(Duration::from_millis(500));
+    signal("-USR1");
+    thread::sleep(Duration::from_millis(2500));
+    signal("-USR2");
+
+    let output = child.wait_with_output().expect("system-monitor did not exit");
+    assert!(output.status.success());
+    assert!(started.elapsed() >= Duration::from_secs(4), "only took {:?}", started.elapsed());
+
+    // The pause is marked in the stream, ahead of both samples
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some(r#"{"event":"paused"}"#), "{stdout}");
+    let samples: Vec<serde_json::Value> = lines.map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(samples.len(), 2);
+    // The first sample after resuming covers one interval, not the pause
+    for sample in &samples {
+        assert!(sample["elapsed_ms"].as_u64().unwrap() < 1500, "{sample}");
+    }
+}
+
+#[test]
+fn test_ctrl_c_leaves_the_last_reading() {
+    use std::process::Stdio;
+    use std::thread;
+
+    let child = Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+        .args(["live", "--interval", "2", "--fields", "cpu,mem"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run system-monitor");
+
+    // One sample in, partway through the next window
+    thread::sleep(Duration::from_millis(3000));
+    let status = Command::new("kill").args(["-INT", &child.id().to_string()]).status().expect("failed to run kill");
+    assert!(status.success());
+
+    let output = child.wait_with_output().expect("system-monitor did not exit");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (blocks, summary) = stdout.split_once("Summary: ").expect("a summary after the blocks");
+    // The drawn block, then the last reading again
+    assert_eq!(blocks.matches("System Metrics:").count(), 2, "{stdout}");
+    assert!(summary.starts_with("1 sample"), "{stdout}");
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "serve")]
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::Duration;
+
+const ADDR: &str = "127.0.0.1:39517";
+
+/// Kills the server even when an assertion fails
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn get(path: &str) -> String {
+    for _ in 0..50 {
+        if let Ok(mut stream) = TcpStream::connect(ADDR) {
+            write!(stream, "GET {path} HTTP/1.0\r\nHost: localhost\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            return response;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    panic!("server never came up on {ADDR}");
+}
+
+#[test]
+fn test_serve_answers_metrics_scrapes() {
+    let _server = Server(
+        Command::new(env!("CARGO_BIN_EXE_system-monitor"))
+            .args(["export", "--prometheus-listen", ADDR, "--interval", "250ms"])
+            .spawn()
+            .expect("failed to run system-monitor"),
+    );
+
+    let response = get("/metrics");
+    assert!(response.starts_with("HTTP/1.0 200"), "unexpected response: {response}");
+    assert!(response.contains("text/plain; version=0.0.4"));
+    assert!(response.contains("\r\n\r\n# HELP system_cpu_usage_percent"));
+    assert!(response.contains("\nsystem_memory_total_bytes "));
+    assert!(response.contains("# TYPE system_network_received_bytes_total counter\n"));
+
+    let value = |name: &str| -> f64 {
+        let line = response.lines().find(|line| line.starts_with(&format!("{name} "))).expect(name);
+        line[name.len() + 1..].parse().expect("numeric sample value")
+    };
+    assert!(value("system_memory_total_bytes") > 0.0);
+    assert!((0.0..=100.0).contains(&value("system_cpu_usage_percent")));
+
+    assert!(get("/").starts_with("HTTP/1.0 404"));
+}